@@ -0,0 +1,562 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use lego_config::error::LegoError;
+
+use crate::estimation::sectional::{contains_2d, level_bounds, level_z};
+use crate::solid::mesh::Solid;
+use crate::str::cross_reader::common::Extent;
+use crate::str::cross_reader::cross::Cross;
+
+// names of the attributes every workflow shares
+pub const GRADE: &str = "grade";
+pub const DENSITY: &str = "density";
+pub const LYTHOLOGY: &str = "lythology";
+pub const DOMAIN: &str = "domain";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeKind {
+    Real,
+    Integer,
+    Text,
+    Flag,
+}
+
+// one value for every block of the model, none means not estimated / unknown
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValues {
+    Real(Vec<Option<f64>>),
+    Integer(Vec<Option<i64>>),
+    Text(Vec<Option<String>>),
+    Flag(Vec<bool>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    Real(f64),
+    Integer(i64),
+    Text(String),
+    Flag(bool),
+}
+
+impl AttributeValues {
+    fn empty(kind: AttributeKind, len: usize) -> AttributeValues {
+        match kind {
+            AttributeKind::Real => AttributeValues::Real(vec![None; len]),
+            AttributeKind::Integer => AttributeValues::Integer(vec![None; len]),
+            AttributeKind::Text => AttributeValues::Text(vec![None; len]),
+            AttributeKind::Flag => AttributeValues::Flag(vec![false; len]),
+        }
+    }
+
+    pub fn kind(&self) -> AttributeKind {
+        match self {
+            AttributeValues::Real(_) => AttributeKind::Real,
+            AttributeValues::Integer(_) => AttributeKind::Integer,
+            AttributeValues::Text(_) => AttributeKind::Text,
+            AttributeValues::Flag(_) => AttributeKind::Flag,
+        }
+    }
+
+    fn get(&self, index: usize) -> Option<AttributeValue> {
+        match self {
+            AttributeValues::Real(v) => v[index].map(AttributeValue::Real),
+            AttributeValues::Integer(v) => v[index].map(AttributeValue::Integer),
+            AttributeValues::Text(v) => v[index].clone().map(AttributeValue::Text),
+            AttributeValues::Flag(v) => Some(AttributeValue::Flag(v[index])),
+        }
+    }
+
+    // children of a split block take the values of their parent
+    fn repeat(&mut self, index: usize, times: usize) {
+        match self {
+            AttributeValues::Real(v) => splice_copies(v, index, times),
+            AttributeValues::Integer(v) => splice_copies(v, index, times),
+            AttributeValues::Text(v) => splice_copies(v, index, times),
+            AttributeValues::Flag(v) => splice_copies(v, index, times),
+        }
+    }
+}
+
+fn splice_copies<T: Clone>(values: &mut Vec<T>, index: usize, times: usize) {
+    let value = values[index].clone();
+    values.splice(index..index + 1, std::iter::repeat_n(value, times));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Block {
+    // i, j, k of the parent cell
+    pub parent: [usize; 3],
+    // in model coordinates, the origin of the model is 0, 0, 0 and axes are not rotated
+    pub centroid: [f64; 3],
+    pub size: [f64; 3],
+}
+
+impl Block {
+    pub fn volume(&self) -> f64 {
+        self.size[0] * self.size[1] * self.size[2]
+    }
+
+    pub fn is_sub_cell(&self, block_size: &[f64; 3]) -> bool {
+        (0..3).any(|k| self.size[k] < block_size[k])
+    }
+}
+
+// Regular grid of parent cells, some of them may be split into sub cells. Blocks are kept
+// ordered by their parent cell, x runs fastest. The model may be rotated around the
+// vertical axis through its origin, counter clockwise in degrees.
+#[derive(Debug, Clone)]
+pub struct BlockModel {
+    origin: [f64; 3],
+    block_size: [f64; 3],
+    counts: [usize; 3],
+    rotation: f64,
+    blocks: Vec<Block>,
+    attributes: Vec<(String, AttributeValues)>,
+}
+
+impl BlockModel {
+    pub fn new(origin: [f64; 3], block_size: [f64; 3], counts: [usize; 3]) -> Result<BlockModel, LegoError> {
+        if block_size.iter().any(|s| *s <= 0.0) {
+            return Err(LegoError::geometry(format!("block size has to be positive, got {:?}", block_size)));
+        }
+
+        if counts.contains(&0) {
+            return Err(LegoError::geometry(format!("block counts have to be positive, got {:?}", counts)));
+        }
+
+        let mut blocks: Vec<Block> = Vec::with_capacity(counts[0] * counts[1] * counts[2]);
+        for k in 0..counts[2] {
+            for j in 0..counts[1] {
+                for i in 0..counts[0] {
+                    blocks.push(Block {
+                        parent: [i, j, k],
+                        centroid: [(i as f64 + 0.5) * block_size[0],
+                            (j as f64 + 0.5) * block_size[1],
+                            (k as f64 + 0.5) * block_size[2]],
+                        size: block_size,
+                    });
+                }
+            }
+        }
+
+        Ok(BlockModel {
+            origin,
+            block_size,
+            counts,
+            rotation: 0.0,
+            blocks,
+            attributes: vec![],
+        })
+    }
+
+    // smallest unrotated model covering the extent, e.g. find_model_frame of the crosses
+    pub fn new_from_extent(extent: &Extent, block_size: [f64; 3]) -> Result<BlockModel, LegoError> {
+        let lengths = [extent.max_x - extent.min_x, extent.max_y - extent.min_y, extent.max_z - extent.min_z];
+
+        let mut counts = [1; 3];
+        for k in 0..3 {
+            if block_size[k] > 0.0 {
+                counts[k] = ((lengths[k] / block_size[k]).ceil() as usize).max(1);
+            }
+        }
+
+        BlockModel::new([extent.min_x, extent.min_y, extent.min_z], block_size, counts)
+    }
+
+    pub fn set_rotation(&mut self, degrees: f64) {
+        self.rotation = degrees;
+    }
+
+    pub fn get_rotation(&self) -> f64 {
+        self.rotation
+    }
+
+    pub fn get_origin(&self) -> [f64; 3] {
+        self.origin
+    }
+
+    pub fn get_block_size(&self) -> [f64; 3] {
+        self.block_size
+    }
+
+    pub fn get_counts(&self) -> [usize; 3] {
+        self.counts
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    pub fn get_block(&self, index: usize) -> Option<&Block> {
+        self.blocks.get(index)
+    }
+
+    pub fn total_volume(&self) -> f64 {
+        self.blocks.iter().map(|b| b.volume()).sum()
+    }
+
+    pub fn to_world(&self, local: [f64; 3]) -> [f64; 3] {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        [self.origin[0] + local[0] * cos - local[1] * sin,
+            self.origin[1] + local[0] * sin + local[1] * cos,
+            self.origin[2] + local[2]]
+    }
+
+    pub fn to_local(&self, world: [f64; 3]) -> [f64; 3] {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let (dx, dy) = (world[0] - self.origin[0], world[1] - self.origin[1]);
+        [dx * cos + dy * sin, -dx * sin + dy * cos, world[2] - self.origin[2]]
+    }
+
+    pub fn world_centroid(&self, index: usize) -> [f64; 3] {
+        self.to_world(self.blocks[index].centroid)
+    }
+
+    // index of the block containing the world point
+    pub fn find_block(&self, world: [f64; 3]) -> Option<usize> {
+        let local = self.to_local(world);
+
+        let mut parent = [0; 3];
+        for k in 0..3 {
+            let cell = (local[k] / self.block_size[k]).floor();
+            if cell < 0.0 || cell >= self.counts[k] as f64 {
+                return None;
+            }
+            parent[k] = cell as usize;
+        }
+
+        let key = self.parent_key(&parent);
+        let first = self.blocks.partition_point(|b| self.parent_key(&b.parent) < key);
+
+        (first..self.blocks.len())
+            .take_while(|&i| self.blocks[i].parent == parent)
+            .find(|&i| {
+                let b = &self.blocks[i];
+                (0..3).all(|k| {
+                    let low = b.centroid[k] - b.size[k] / 2.0;
+                    local[k] >= low && local[k] < low + b.size[k]
+                })
+            })
+    }
+
+    fn parent_key(&self, parent: &[usize; 3]) -> usize {
+        (parent[2] * self.counts[1] + parent[1]) * self.counts[0] + parent[0]
+    }
+
+    pub fn add_attribute(&mut self, name: &str, kind: AttributeKind) -> Result<(), LegoError> {
+        if self.attributes.iter().any(|(n, _)| n == name) {
+            return Err(LegoError::parameter(format!("block model already has an attribute named {}", name)));
+        }
+
+        self.attributes.push((name.to_string(), AttributeValues::empty(kind, self.blocks.len())));
+        Ok(())
+    }
+
+    // adds the attribute if it is missing, an existing one has to be of the same kind
+    pub fn ensure_attribute(&mut self, name: &str, kind: AttributeKind) -> Result<(), LegoError> {
+        match self.get_attribute(name) {
+            None => self.add_attribute(name, kind),
+            Some(values) if values.kind() == kind => Ok(()),
+            Some(values) => Err(LegoError::parameter(format!("attribute {} keeps {:?} values, not {:?}",
+                                                             name, values.kind(), kind)))
+        }
+    }
+
+    pub fn attribute_names(&self) -> Vec<&String> {
+        self.attributes.iter().map(|(n, _)| n).collect()
+    }
+
+    pub fn get_attribute(&self, name: &str) -> Option<&AttributeValues> {
+        self.attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    pub fn get_value(&self, name: &str, index: usize) -> Option<AttributeValue> {
+        self.get_attribute(name).and_then(|v| v.get(index))
+    }
+
+    pub fn get_real(&self, name: &str, index: usize) -> Option<f64> {
+        match self.get_attribute(name) {
+            Some(AttributeValues::Real(v)) => v[index],
+            _ => None,
+        }
+    }
+
+    pub fn set_value(&mut self, name: &str, index: usize, value: AttributeValue) -> Result<(), LegoError> {
+        if index >= self.blocks.len() {
+            return Err(LegoError::parameter(format!("block index {} is out of range, model has {} blocks",
+                                                    index, self.blocks.len())));
+        }
+
+        let values = match self.attributes.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => v,
+            None => return Err(LegoError::parameter(format!("block model has no attribute named {}", name)))
+        };
+
+        match (values, value) {
+            (AttributeValues::Real(v), AttributeValue::Real(x)) => v[index] = Some(x),
+            (AttributeValues::Integer(v), AttributeValue::Integer(x)) => v[index] = Some(x),
+            (AttributeValues::Text(v), AttributeValue::Text(x)) => v[index] = Some(x),
+            (AttributeValues::Flag(v), AttributeValue::Flag(x)) => v[index] = x,
+            (values, value) => return Err(LegoError::parameter(format!("attribute {} keeps {:?} values, got {:?}",
+                                                                       name, values.kind(), value)))
+        }
+
+        Ok(())
+    }
+
+    // back to not estimated / unknown, flags are cleared
+    pub fn clear_value(&mut self, name: &str, index: usize) -> Result<(), LegoError> {
+        if index >= self.blocks.len() {
+            return Err(LegoError::parameter(format!("block index {} is out of range, model has {} blocks",
+                                                    index, self.blocks.len())));
+        }
+
+        match self.attributes.iter_mut().find(|(n, _)| n == name) {
+            Some((_, AttributeValues::Real(v))) => v[index] = None,
+            Some((_, AttributeValues::Integer(v))) => v[index] = None,
+            Some((_, AttributeValues::Text(v))) => v[index] = None,
+            Some((_, AttributeValues::Flag(v))) => v[index] = false,
+            None => return Err(LegoError::parameter(format!("block model has no attribute named {}", name)))
+        }
+
+        Ok(())
+    }
+
+    // replaces the block with splits[0] x splits[1] x splits[2] sub cells. returns the index
+    // range of the new blocks.
+    pub fn split_block(&mut self, index: usize, splits: [usize; 3]) -> Result<std::ops::Range<usize>, LegoError> {
+        let block = match self.blocks.get(index) {
+            Some(b) => *b,
+            None => return Err(LegoError::parameter(format!("block index {} is out of range, model has {} blocks",
+                                                            index, self.blocks.len())))
+        };
+
+        if splits.contains(&0) {
+            return Err(LegoError::parameter(format!("splits have to be positive, got {:?}", splits)));
+        }
+
+        let size = [block.size[0] / splits[0] as f64, block.size[1] / splits[1] as f64,
+            block.size[2] / splits[2] as f64];
+        let low = [block.centroid[0] - block.size[0] / 2.0, block.centroid[1] - block.size[1] / 2.0,
+            block.centroid[2] - block.size[2] / 2.0];
+
+        let mut children: Vec<Block> = vec![];
+        for k in 0..splits[2] {
+            for j in 0..splits[1] {
+                for i in 0..splits[0] {
+                    children.push(Block {
+                        parent: block.parent,
+                        centroid: [low[0] + (i as f64 + 0.5) * size[0],
+                            low[1] + (j as f64 + 0.5) * size[1],
+                            low[2] + (k as f64 + 0.5) * size[2]],
+                        size,
+                    });
+                }
+            }
+        }
+
+        let count = children.len();
+        self.blocks.splice(index..index + 1, children);
+        for (_, values) in self.attributes.iter_mut() {
+            values.repeat(index, count);
+        }
+
+        Ok(index..index + count)
+    }
+
+    // splits the blocks cut by the surface of the solid. a block is on the surface when its
+    // corners and centroid are not all on the same side.
+    pub fn sub_cell_by_solid(&mut self, solid: &Solid, splits: [usize; 3]) -> Result<usize, LegoError> {
+        let mut split_count = 0;
+        let mut index = 0;
+
+        while index < self.blocks.len() {
+            let block = self.blocks[index];
+            let mut points = vec![block.centroid];
+            for corner in 0..8 {
+                points.push([block.centroid[0] + block.size[0] * ((corner & 1) as f64 - 0.5),
+                    block.centroid[1] + block.size[1] * (((corner >> 1) & 1) as f64 - 0.5),
+                    block.centroid[2] + block.size[2] * (((corner >> 2) & 1) as f64 - 0.5)]);
+            }
+
+            let inside: Vec<bool> = points.iter()
+                .map(|p| {
+                    let w = self.to_world(*p);
+                    solid.contains_point(w[0], w[1], w[2])
+                })
+                .collect();
+
+            if !block.is_sub_cell(&self.block_size) && inside.iter().any(|i| *i) && inside.iter().any(|i| !*i) {
+                index = self.split_block(index, splits)?.end;
+                split_count += 1;
+            } else {
+                index += 1;
+            }
+        }
+
+        Ok(split_count)
+    }
+
+    // flags the blocks whose centroid is inside the solid, returns the count of flagged blocks
+    pub fn flag_inside_solid(&mut self, solid: &Solid, name: &str) -> Result<usize, LegoError> {
+        let flags: Vec<bool> = (0..self.blocks.len())
+            .map(|i| {
+                let w = self.world_centroid(i);
+                solid.contains_point(w[0], w[1], w[2])
+            })
+            .collect();
+
+        self.set_flags(name, flags)
+    }
+
+    // every cross bounds the blocks halfway to its neighbour levels
+    pub fn flag_inside_crosses(&mut self, crosses: &[Cross], name: &str) -> Result<usize, LegoError> {
+        let mut levels: Vec<(f64, &Cross)> = crosses.iter().map(|c| (level_z(c), c)).collect();
+        levels.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let zs: Vec<f64> = levels.iter().map(|(z, _)| *z).collect();
+        let bounds = level_bounds(&zs);
+
+        let flags: Vec<bool> = (0..self.blocks.len())
+            .map(|i| {
+                let w = self.world_centroid(i);
+                levels.iter().zip(bounds.iter())
+                    .any(|((_, c), (lower, upper))| w[2] >= *lower && w[2] < *upper && contains_2d(c, w[0], w[1]))
+            })
+            .collect();
+
+        self.set_flags(name, flags)
+    }
+
+    fn set_flags(&mut self, name: &str, flags: Vec<bool>) -> Result<usize, LegoError> {
+        let count = flags.iter().filter(|f| **f).count();
+
+        match self.attributes.iter_mut().find(|(n, _)| n == name) {
+            Some((_, AttributeValues::Flag(v))) => *v = flags,
+            Some((_, values)) => return Err(LegoError::parameter(format!("attribute {} keeps {:?} values, not flags",
+                                                                         name, values.kind()))),
+            None => self.attributes.push((name.to_string(), AttributeValues::Flag(flags)))
+        }
+
+        Ok(count)
+    }
+}
+
+impl Display for BlockModel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let sub_cells = self.blocks.iter().filter(|b| b.is_sub_cell(&self.block_size)).count();
+
+        write!(f, "origin : {:?} \n\
+                   block size : {:?} \n\
+                   counts : {:?} \n\
+                   rotation : {} \n\
+                   blocks : {} \n\
+                   sub cells : {} \n\
+                   attributes : {:?}", self.origin, self.block_size, self.counts, self.rotation,
+               self.blocks.len(), sub_cells, self.attribute_names())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lego_config::error::LegoError;
+
+    use crate::block::model::{AttributeKind, AttributeValue, BlockModel, GRADE, LYTHOLOGY};
+    use crate::lego_tests::give_me_cross;
+    use crate::solid::loft::loft_crosses;
+    use crate::str::cross_reader::coordinate::CrossCoordinate3d;
+    use crate::str::cross_reader::cross::Cross;
+
+    fn square(group_no: i32, z: f64) -> Cross {
+        let corners = [(2.0, 2.0), (8.0, 2.0), (8.0, 8.0), (2.0, 8.0)];
+        let coordinates = corners.iter().enumerate()
+            .map(|(i, &(x, y))| CrossCoordinate3d::new(x, y, z, i as i32 + 1))
+            .collect();
+        Cross::new(group_no, coordinates)
+    }
+
+    #[test]
+    fn regular_model() {
+        let model = BlockModel::new([100.0, 200.0, 300.0], [5.0, 5.0, 2.0], [4, 3, 2]).unwrap();
+
+        assert_eq!(model.block_count(), 24);
+        assert!((model.total_volume() - 20.0 * 15.0 * 4.0).abs() < 1e-9);
+        assert_eq!(model.world_centroid(0), [102.5, 202.5, 301.0]);
+        assert_eq!(model.find_block([112.0, 207.0, 303.5]), Some(2 + 4 + 12));
+        assert_eq!(model.find_block([99.0, 207.0, 303.5]), None);
+
+        assert!(matches!(BlockModel::new([0.0; 3], [0.0, 1.0, 1.0], [1, 1, 1]), Err(LegoError::Geometry(_))));
+    }
+
+    #[test]
+    fn rotated_model() {
+        let mut model = BlockModel::new([0.0, 0.0, 0.0], [10.0, 10.0, 10.0], [2, 1, 1]).unwrap();
+        model.set_rotation(90.0);
+
+        let c = model.world_centroid(1);
+        assert!((c[0] + 5.0).abs() < 1e-9 && (c[1] - 15.0).abs() < 1e-9);
+        assert_eq!(model.find_block([-5.0, 15.0, 5.0]), Some(1));
+    }
+
+    #[test]
+    fn split_keeps_attributes() {
+        let mut model = BlockModel::new([0.0; 3], [10.0, 10.0, 10.0], [2, 2, 1]).unwrap();
+        model.add_attribute(GRADE, AttributeKind::Real).unwrap();
+        model.add_attribute(LYTHOLOGY, AttributeKind::Text).unwrap();
+        model.set_value(GRADE, 1, AttributeValue::Real(1.5)).unwrap();
+        model.set_value(LYTHOLOGY, 1, AttributeValue::Text(String::from("kalkopirit"))).unwrap();
+        assert!(matches!(model.set_value(GRADE, 1, AttributeValue::Integer(1)), Err(LegoError::Parameter(_))));
+
+        let children = model.split_block(1, [2, 2, 2]).unwrap();
+        assert_eq!(children, 1..9);
+        assert_eq!(model.block_count(), 11);
+        assert!((model.total_volume() - 400.0 * 10.0).abs() < 1e-9);
+        assert!(children.clone().all(|i| model.get_real(GRADE, i) == Some(1.5)));
+        assert_eq!(model.get_real(GRADE, 9), None);
+
+        // sub cell of the second parent
+        assert_eq!(model.find_block([17.0, 3.0, 7.0]), Some(1 + 1 + 4));
+    }
+
+    #[test]
+    fn flag_inside_box() {
+        let solid = loft_crosses(&[square(1, 0.0), square(2, 10.0)]).unwrap();
+        let mut model = BlockModel::new([0.0; 3], [2.0, 2.0, 2.0], [5, 5, 5]).unwrap();
+
+        // centroids 3, 5 and 7 are inside on x and y, all levels on z
+        let flagged = model.flag_inside_solid(&solid, "inside").unwrap();
+        assert_eq!(flagged, 3 * 3 * 5);
+
+        let flagged = model.flag_inside_crosses(&[square(1, 0.0), square(2, 10.0)], "inside").unwrap();
+        assert_eq!(flagged, 3 * 3 * 5);
+    }
+
+    #[test]
+    fn sub_cell_box_boundary() {
+        let solid = loft_crosses(&[square(1, 0.5), square(2, 9.5)]).unwrap();
+        let mut model = BlockModel::new([0.0, 0.0, 1.0], [3.0, 3.0, 8.0], [3, 3, 1]).unwrap();
+
+        let split = model.sub_cell_by_solid(&solid, [3, 3, 1]).unwrap();
+        assert_eq!(split, 8);
+        assert_eq!(model.block_count(), 1 + 8 * 9);
+        println!("{}", model);
+    }
+
+    #[test]
+    fn flag_test_crosses() {
+        let cross_object = give_me_cross();
+        let solid = cross_object.build_solid().unwrap();
+        let mut model = BlockModel::new_from_extent(&solid.extent().unwrap(), [10.0, 10.0, 10.0]).unwrap();
+
+        let in_solid = model.flag_inside_solid(&solid, "solid").unwrap();
+        let in_crosses = model.flag_inside_crosses(&cross_object.data, "crosses").unwrap();
+        println!("{} \ninside solid : {} \ninside crosses : {}", model, in_solid, in_crosses);
+
+        assert!(in_solid > 0);
+        assert!(in_crosses > 0);
+    }
+}
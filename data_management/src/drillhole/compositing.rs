@@ -0,0 +1,496 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use lego_config::error::LegoError;
+
+use crate::drillhole::database::{Drillhole, DrillholeDatabase};
+use crate::drillhole::desurvey::{DesurveyedHole, DesurveyMethod};
+use crate::str::composite_reader::{Composite, CompositeCoordinate, CompositeObject, ACCEPTED_GROUP_NO,
+                                   REJECTED_GROUP_NO};
+
+// tolerance in meters while comparing lengths
+const LENGTH_TOLERANCE: f64 = 1e-6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompositingMethod {
+    // regular intervals starting from the collar
+    FixedLength { length: f64 },
+    // intervals between horizontal planes at reference_elevation + k * bench_height
+    Bench { bench_height: f64, reference_elevation: f64 },
+    // regular intervals restarted at every lythology contact
+    Domain { length: f64 },
+}
+
+impl Display for CompositingMethod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CompositingMethod::FixedLength { length } => write!(f, "fixed length ({} m)", length),
+            CompositingMethod::Bench { bench_height, reference_elevation } =>
+                write!(f, "bench ({} m from {})", bench_height, reference_elevation),
+            CompositingMethod::Domain { length } => write!(f, "domain bounded ({} m)", length)
+        }
+    }
+}
+
+// what to do with the short composites at the end of a hole or a domain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResidualHandling {
+    Keep,
+    Discard,
+    MergeWithPrevious,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompositingParameters {
+    pub method: CompositingMethod,
+    // sampled length / composite length. composites below it are written to the second string
+    pub min_coverage: f64,
+    pub residual: ResidualHandling,
+    pub desurvey_method: DesurveyMethod,
+}
+
+impl CompositingParameters {
+    pub fn new(method: CompositingMethod, min_coverage: f64,
+               residual: ResidualHandling) -> Result<CompositingParameters, LegoError> {
+        let parameters = CompositingParameters {
+            method,
+            min_coverage,
+            residual,
+            desurvey_method: DesurveyMethod::MinimumCurvature,
+        };
+        parameters.validate()?;
+        Ok(parameters)
+    }
+
+    // fields are public, so they are checked again before compositing
+    pub fn validate(&self) -> Result<(), LegoError> {
+        let (name, value) = match self.method {
+            CompositingMethod::FixedLength { length } | CompositingMethod::Domain { length } => ("length", length),
+            CompositingMethod::Bench { bench_height, reference_elevation } => {
+                if !reference_elevation.is_finite() {
+                    return Err(LegoError::parameter(format!("reference elevation ({}) has to be finite",
+                                                            reference_elevation)));
+                }
+                ("bench height", bench_height)
+            }
+        };
+
+        if !value.is_finite() || value <= 0.0 {
+            return Err(LegoError::parameter(format!("{} ({}) has to be a positive number", name, value)));
+        }
+        if !(0.0..=1.0).contains(&self.min_coverage) {
+            return Err(LegoError::parameter(format!("minimum coverage ({}) has to be between 0 and 1",
+                                                    self.min_coverage)));
+        }
+        Ok(())
+    }
+}
+
+impl Display for CompositingParameters {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "method : {} \n\
+                   minimum coverage : {} \n\
+                   residual : {:?} \n\
+                   desurvey method : {}", self.method, self.min_coverage, self.residual,
+               self.desurvey_method)
+    }
+}
+
+// one composite interval before grades are calculated
+#[derive(Debug, Clone)]
+struct Window {
+    start: f64,
+    end: f64,
+    full: bool,
+    run: usize,
+    domain: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct CompositingObject {
+    parameters: CompositingParameters,
+    pub(crate) data: Vec<Composite>,
+}
+
+impl CompositingObject {
+    pub fn new(database: &DrillholeDatabase,
+               parameters: CompositingParameters) -> Result<CompositingObject, LegoError> {
+        parameters.validate()?;
+        let mut data: Vec<Composite> = vec![];
+
+        for hole in database.drillholes() {
+            // holes without collar cannot be desurveyed
+            if let Some(desurveyed) = DesurveyedHole::new(hole, parameters.desurvey_method) {
+                data.extend(composite_hole(hole, &desurveyed, &parameters));
+            }
+        }
+
+        // accepted ones first, like the composite str files
+        data.sort_by_key(|c| c.group_no);
+
+        Ok(CompositingObject {
+            parameters,
+            data,
+        })
+    }
+
+    pub fn parameters(&self) -> &CompositingParameters {
+        &self.parameters
+    }
+
+    pub fn data(&self) -> &[Composite] {
+        &self.data
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Composite> {
+        self.data.iter()
+    }
+
+    // composites of a hole, accepted ones first
+    pub fn get_hole_composites(&self, drill_no: &str) -> Vec<&Composite> {
+        self.data.iter().filter(|c| c.drill_no == drill_no).collect()
+    }
+
+    // same object as the composites read from a str file
+    pub fn into_composite_object(self) -> CompositeObject {
+        CompositeObject::from_records(self.data)
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn write_str(&self, path: &str, name: &str) -> Result<(), LegoError> {
+        self.write_str_records(path, name).map_err(|e| LegoError::io(path, e))
+    }
+
+    fn write_str_records(&self, path: &str, name: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        let today = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0) / 86400;
+        writeln!(writer, "{},{},Downhole composite of assays, ", name, surpac_date(today as i64))?;
+        writeln!(writer, "0,           0.000,           0.000,           0.000,           0.000,           0.000,           0.000")?;
+
+        for group_no in &[ACCEPTED_GROUP_NO, REJECTED_GROUP_NO] {
+            let mut written = false;
+
+            for c in self.data.iter().filter(|c| c.group_no == *group_no) {
+                // surpac string records are y, x, z
+                write!(writer, "{}, {:.3}, {:.3}, {:.3}, {:.4},{},{:.3},{:.3},{:.3}", c.group_no,
+                       c.coordinate.y_coord, c.coordinate.x_coord, c.coordinate.z_coord, c.tenor,
+                       c.drill_no, c.cut_from, c.cut_end, c.cut_taken)?;
+                match &c.domain {
+                    Some(d) => writeln!(writer, ",{}", d)?,
+                    None => writeln!(writer)?
+                }
+                written = true;
+            }
+
+            if written {
+                writeln!(writer, "0, 0.000, 0.000, 0.000,")?;
+            }
+        }
+
+        writeln!(writer, "0, 0.000, 0.000, 0.000, END")?;
+        writer.flush()
+    }
+}
+
+impl Display for CompositingObject {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "parameters : {} \n\
+                   data : {:?}", self.parameters, self.data)
+    }
+}
+
+fn composite_hole(hole: &Drillhole, desurveyed: &DesurveyedHole,
+                  parameters: &CompositingParameters) -> Vec<Composite> {
+    if hole.raw_samples.is_empty() {
+        return vec![];
+    }
+
+    let sample_end = hole.raw_samples.iter().map(|r| r.end).fold(0.0, f64::max);
+    let hole_end = hole.collar_depth().unwrap_or(0.0).max(sample_end);
+
+    let windows = match &parameters.method {
+        CompositingMethod::FixedLength { length } => fixed_windows(0.0, hole_end, *length, 0, None),
+        CompositingMethod::Domain { length } => {
+            let mut windows: Vec<Window> = vec![];
+            for (run, l) in hole.lythology.iter().enumerate() {
+                windows.extend(fixed_windows(l.start, l.end, *length, run, Some(l.lytho.clone())));
+            }
+            windows
+        }
+        CompositingMethod::Bench { bench_height, reference_elevation } =>
+            bench_windows(desurveyed, hole_end, *bench_height, *reference_elevation)
+    };
+
+    let windows = handle_residuals(windows, parameters.residual);
+
+    let mut composites: Vec<Composite> = vec![];
+    for w in windows {
+        let mut sampled = 0.0;
+        let mut metal = 0.0;
+
+        for r in hole.raw_samples.iter() {
+            let overlap = w.end.min(r.end) - w.start.max(r.start);
+            if overlap > 0.0 {
+                sampled += overlap;
+                metal += overlap * r.percent;
+            }
+        }
+
+        if sampled <= LENGTH_TOLERANCE {
+            continue;
+        }
+
+        let coverage = sampled / (w.end - w.start);
+        let group_no = if coverage >= parameters.min_coverage - LENGTH_TOLERANCE {
+            ACCEPTED_GROUP_NO
+        } else {
+            REJECTED_GROUP_NO
+        };
+
+        let middle = desurveyed.position_at((w.start + w.end) / 2.0);
+        composites.push(Composite {
+            group_no,
+            tenor: metal / sampled,
+            drill_no: hole.hole_id.clone(),
+            cut_from: w.start,
+            cut_end: w.end,
+            cut_taken: sampled,
+            coordinate: CompositeCoordinate::new(middle.x_coord, middle.y_coord, middle.z_coord),
+            domain: w.domain,
+        });
+    }
+
+    composites
+}
+
+fn fixed_windows(start: f64, end: f64, length: f64, run: usize, domain: Option<String>) -> Vec<Window> {
+    let mut windows: Vec<Window> = vec![];
+    let mut from = start;
+
+    while from < end - LENGTH_TOLERANCE {
+        let to = (from + length).min(end);
+        windows.push(Window {
+            start: from,
+            end: to,
+            full: to - from >= length - LENGTH_TOLERANCE,
+            run,
+            domain: domain.clone(),
+        });
+        from += length;
+    }
+
+    windows
+}
+
+fn bench_windows(desurveyed: &DesurveyedHole, hole_end: f64, bench_height: f64,
+                 reference_elevation: f64) -> Vec<Window> {
+    let elevation = |depth: f64| desurveyed.position_at(depth).z_coord;
+    let (top, bottom) = (elevation(0.0), elevation(hole_end));
+
+    // depths where the hole crosses bench planes
+    let mut breaks: Vec<f64> = vec![0.0];
+    let mut k = ((top - reference_elevation) / bench_height).floor();
+    loop {
+        let plane = reference_elevation + k * bench_height;
+        if plane <= bottom {
+            break;
+        }
+        if plane < top {
+            breaks.push(find_depth_of_elevation(&elevation, plane, hole_end));
+        }
+        k -= 1.0;
+    }
+    breaks.push(hole_end);
+
+    breaks.windows(2)
+        .filter(|b| b[1] - b[0] > LENGTH_TOLERANCE)
+        .map(|b| Window {
+            start: b[0],
+            end: b[1],
+            full: (elevation(b[0]) - elevation(b[1])).abs() >= bench_height - LENGTH_TOLERANCE,
+            run: 0,
+            domain: None,
+        })
+        .collect()
+}
+
+fn find_depth_of_elevation(elevation: &dyn Fn(f64) -> f64, plane: f64, hole_end: f64) -> f64 {
+    // holes are going downwards, so elevation decreases along the hole
+    let (mut low, mut high) = (0.0, hole_end);
+
+    for _ in 0..100 {
+        let middle = (low + high) / 2.0;
+        if elevation(middle) > plane {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+
+    (low + high) / 2.0
+}
+
+fn handle_residuals(windows: Vec<Window>, residual: ResidualHandling) -> Vec<Window> {
+    let mut handled: Vec<Window> = vec![];
+
+    for w in windows {
+        if w.full || residual == ResidualHandling::Keep {
+            handled.push(w);
+            continue;
+        }
+
+        if residual == ResidualHandling::MergeWithPrevious {
+            match handled.last_mut() {
+                Some(previous) if previous.run == w.run && (previous.end - w.start).abs() < LENGTH_TOLERANCE => {
+                    previous.end = w.end;
+                }
+                _ => handled.push(w)
+            }
+        }
+    }
+
+    handled
+}
+
+// date of the days since 1970-01-01 like 11-Apr-18
+fn surpac_date(days: i64) -> String {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun",
+        "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+    // civil date of h. hinnant, eras of 400 years start on 0000-03-01
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:02}-{}-{:02}", day, MONTHS[(month - 1) as usize], year % 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use lego_config::error::LegoError;
+
+    use crate::drillhole::compositing::{CompositingMethod, CompositingObject, CompositingParameters, ResidualHandling,
+                                        surpac_date};
+    use crate::lego_tests::{give_me_composite, give_me_drillhole_database};
+    use crate::str::composite_reader::{CompositeInformation, CompositeObject};
+
+    #[test]
+    fn regenerate_test_composites() {
+        let database = give_me_drillhole_database();
+        let parameters = CompositingParameters::new(CompositingMethod::FixedLength { length: 4.0 },
+                                                    0.5, ResidualHandling::Keep).unwrap();
+        let generated = CompositingObject::new(&database, parameters).unwrap();
+        let surpac = give_me_composite();
+
+        assert_eq!(generated.len(), surpac.data.len());
+
+        for expected in surpac.data.iter() {
+            let c = generated.data.iter()
+                .find(|c| c.drill_no == expected.drill_no && (c.cut_from - expected.cut_from).abs() < 1e-6)
+                .expect("composite cannot be found !");
+
+            assert_eq!(c.group_no, expected.group_no);
+            assert!((c.cut_end - expected.cut_end).abs() < 1e-3);
+            assert!((c.tenor - expected.tenor).abs() < 1e-4);
+            assert!((c.cut_taken - expected.cut_taken).abs() < 1e-3);
+            assert!((c.coordinate.x_coord - expected.coordinate.x_coord).abs() < 1e-3);
+            assert!((c.coordinate.y_coord - expected.coordinate.y_coord).abs() < 1e-3);
+            assert!((c.coordinate.z_coord - expected.coordinate.z_coord).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn domain_and_bench_composites() {
+        let database = give_me_drillhole_database();
+
+        let domain = CompositingObject::new(&database, CompositingParameters::new(
+            CompositingMethod::Domain { length: 2.0 }, 0.5, ResidualHandling::MergeWithPrevious).unwrap()).unwrap();
+        for c in domain.data.iter() {
+            let hole = database.get_drillhole(&c.drill_no).unwrap();
+            let middle = (c.cut_from + c.cut_end) / 2.0;
+            assert_eq!(c.domain.as_deref(), hole.give_lytho_at(middle));
+        }
+
+        let bench = CompositingObject::new(&database, CompositingParameters::new(
+            CompositingMethod::Bench { bench_height: 5.0, reference_elevation: 0.0 }, 0.5,
+            ResidualHandling::Discard).unwrap()).unwrap();
+        for c in bench.data.iter() {
+            // vertical holes, every bench composite is 5 meters
+            assert!((c.cut_end - c.cut_from - 5.0).abs() < 1e-6);
+            let bench = (c.coordinate.z_coord - 2.5) / 5.0;
+            assert!((bench - bench.round()).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn write_and_read_composite_str() {
+        let database = give_me_drillhole_database();
+        let generated = CompositingObject::new(&database, CompositingParameters::new(
+            CompositingMethod::FixedLength { length: 4.0 }, 0.5, ResidualHandling::Keep).unwrap()).unwrap();
+
+        let path = std::env::temp_dir().join("lego_composite_test.str");
+        let path = path.to_str().unwrap();
+        generated.write_str(path, "cu_composite").unwrap();
+        assert!(matches!(generated.write_str("/missing/lego/composite.str", "cu_composite"),
+                         Err(LegoError::Io { .. })));
+
+        let info = CompositeInformation::new(String::from(path), String::from("cu"), ',');
+        let read_back = CompositeObject::new(info).unwrap();
+        assert_eq!(read_back.data.len(), generated.len());
+
+        let in_memory = generated.into_composite_object();
+        assert_eq!(in_memory.len(), read_back.len());
+        assert_eq!(in_memory.get_hole_composites("SK-12").len(), read_back.get_hole_composites("SK-12").len());
+    }
+
+    #[test]
+    fn surpac_dates() {
+        assert_eq!(surpac_date(0), "01-Jan-70");
+        assert_eq!(surpac_date(10956), "31-Dec-99");
+        assert_eq!(surpac_date(11016), "29-Feb-00");
+        assert_eq!(surpac_date(17632), "11-Apr-18");
+        assert_eq!(surpac_date(18321), "29-Feb-20");
+        assert_eq!(surpac_date(47541), "01-Mar-00");
+    }
+
+    #[test]
+    fn wrong_parameters() {
+        let methods = [CompositingMethod::FixedLength { length: 0.0 },
+            CompositingMethod::FixedLength { length: -2.0 },
+            CompositingMethod::Domain { length: f64::NAN },
+            CompositingMethod::Bench { bench_height: 0.0, reference_elevation: 0.0 },
+            CompositingMethod::Bench { bench_height: 5.0, reference_elevation: f64::INFINITY }];
+        for method in methods.iter() {
+            let error = CompositingParameters::new(method.clone(), 0.5, ResidualHandling::Keep).unwrap_err();
+            assert!(matches!(error, LegoError::Parameter(_)), "{}", error);
+        }
+
+        let error = CompositingParameters::new(CompositingMethod::FixedLength { length: 4.0 }, 1.5,
+                                               ResidualHandling::Keep).unwrap_err();
+        assert_eq!(error.to_string(), "invalid parameter : minimum coverage (1.5) has to be between 0 and 1");
+
+        // public fields are checked before compositing too
+        let mut parameters = CompositingParameters::new(CompositingMethod::FixedLength { length: 4.0 }, 0.5,
+                                                        ResidualHandling::Keep).unwrap();
+        parameters.method = CompositingMethod::FixedLength { length: 0.0 };
+        assert!(CompositingObject::new(&give_me_drillhole_database(), parameters).is_err());
+    }
+}
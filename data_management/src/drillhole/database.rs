@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use lego_config::error::LegoError;
+use lego_config::read::LegoConfig;
+
+use crate::excels::drill_reader::{DrillCoordinate, DrillInformation, DrillObject};
+use crate::excels::lytho_reader::{LythologyCoordinate, LythologyInformation, LythologyObject};
+use crate::excels::rawsample_reader::{RawSampleCoordinate, RawSampleInformation, RawSampleObject};
+use crate::excels::slope_reader::{DrillSlopeInfo, SlopeInformation, SlopeObject};
+
+// joined view of collars, surveys, lythology and raw samples. Every hole id that appears
+// in any of the tables gets one Drillhole, so holes without a collar are kept too.
+#[derive(Clone)]
+pub struct DrillholeDatabase {
+    holes: BTreeMap<String, Drillhole>,
+}
+
+impl DrillholeDatabase {
+    pub fn new(drills: DrillObject, slopes: SlopeObject, lythologies: LythologyObject,
+               raw_samples: RawSampleObject) -> DrillholeDatabase {
+        let mut holes: BTreeMap<String, Drillhole> = BTreeMap::new();
+
+        for d in drills.data {
+            let hole = holes.entry(d.drill_no.clone())
+                .or_insert_with(|| Drillhole::new(d.drill_no.clone()));
+            hole.collar = Some(d.coordinate);
+        }
+
+        for s in slopes.data {
+            holes.entry(s.drill_no.clone())
+                .or_insert_with(|| Drillhole::new(s.drill_no.clone()))
+                .surveys.push(s.drill_info);
+        }
+
+        for l in lythologies.data {
+            holes.entry(l.drill_no.clone())
+                .or_insert_with(|| Drillhole::new(l.drill_no.clone()))
+                .lythology.push(l.coordinate);
+        }
+
+        for r in raw_samples.data {
+            holes.entry(r.drill_no.clone())
+                .or_insert_with(|| Drillhole::new(r.drill_no.clone()))
+                .raw_samples.push(r.coordinate);
+        }
+
+        // csv files are not guaranteed to be ordered by depth
+        for hole in holes.values_mut() {
+            hole.sort_by_depth();
+        }
+
+        DrillholeDatabase {
+            holes,
+        }
+    }
+
+    pub fn new_from_config(config: &LegoConfig) -> Result<DrillholeDatabase, LegoError> {
+        let drills = DrillObject::new(DrillInformation::new_from_config(config)?)?;
+        let slopes = SlopeObject::new(SlopeInformation::new_from_config(config)?)?;
+        let lythologies = LythologyObject::new(LythologyInformation::new_from_config(config)?)?;
+        let raw_samples = RawSampleObject::new(RawSampleInformation::new_from_config(config)?)?;
+
+        Ok(DrillholeDatabase::new(drills, slopes, lythologies, raw_samples))
+    }
+
+    pub fn get_drillhole(&self, hole_id: &str) -> Option<&Drillhole> {
+        self.holes.get(hole_id)
+    }
+
+    pub fn drillholes(&self) -> impl Iterator<Item=&Drillhole> {
+        self.holes.values()
+    }
+
+    pub(crate) fn drillholes_mut(&mut self) -> impl Iterator<Item=&mut Drillhole> {
+        self.holes.values_mut()
+    }
+
+    pub fn hole_ids(&self) -> Vec<&String> {
+        self.holes.keys().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.holes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.holes.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Drillhole {
+    pub(crate) hole_id: String,
+    pub(crate) collar: Option<DrillCoordinate>,
+    pub(crate) surveys: Vec<DrillSlopeInfo>,
+    pub(crate) lythology: Vec<LythologyCoordinate>,
+    pub(crate) raw_samples: Vec<RawSampleCoordinate>,
+}
+
+impl Drillhole {
+    fn new(hole_id: String) -> Drillhole {
+        Drillhole {
+            hole_id,
+            collar: None,
+            surveys: vec![],
+            lythology: vec![],
+            raw_samples: vec![],
+        }
+    }
+
+    fn sort_by_depth(&mut self) {
+        self.surveys.sort_by(|a, b| a.depth.total_cmp(&b.depth));
+        self.lythology.sort_by(|a, b| a.start.total_cmp(&b.start));
+        self.raw_samples.sort_by(|a, b| a.start.total_cmp(&b.start));
+    }
+
+    pub fn hole_id(&self) -> &str {
+        &self.hole_id
+    }
+
+    pub fn has_collar(&self) -> bool {
+        self.collar.is_some()
+    }
+
+    pub fn collar(&self) -> Option<&DrillCoordinate> {
+        self.collar.as_ref()
+    }
+
+    // survey stations, lythology and raw samples are sorted by depth
+    pub fn surveys(&self) -> &[DrillSlopeInfo] {
+        &self.surveys
+    }
+
+    pub fn lythology(&self) -> &[LythologyCoordinate] {
+        &self.lythology
+    }
+
+    pub fn raw_samples(&self) -> &[RawSampleCoordinate] {
+        &self.raw_samples
+    }
+
+    pub fn survey_count(&self) -> usize {
+        self.surveys.len()
+    }
+
+    pub fn lythology_count(&self) -> usize {
+        self.lythology.len()
+    }
+
+    pub fn raw_sample_count(&self) -> usize {
+        self.raw_samples.len()
+    }
+
+    pub fn collar_depth(&self) -> Option<f64> {
+        self.collar.as_ref().map(|c| c.depth)
+    }
+
+    pub fn give_lytho_at(&self, depth: f64) -> Option<&str> {
+        // intervals are [start, end)
+        self.lythology.iter()
+            .find(|l| l.start <= depth && depth < l.end)
+            .map(|l| l.lytho.as_str())
+    }
+}
+
+impl Display for Drillhole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let collar = match &self.collar {
+            Some(c) => c.to_string(),
+            None => String::from("no collar")
+        };
+
+        write!(f, "hole id : {} \n\
+                   collar : {} \n\
+                   surveys : {} \n\
+                   lythology : {} \n\
+                   raw samples : {}", self.hole_id, collar, self.surveys.len(),
+               self.lythology.len(), self.raw_samples.len())
+    }
+}
+
+impl Display for DrillholeDatabase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "count of drillholes : {} \n\
+                   hole ids : {:?}", self.holes.len(), self.hole_ids())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lego_config::read::LegoConfig;
+
+    use crate::drillhole::database::DrillholeDatabase;
+
+    const TEST_CONFIG_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../lego_config/test_settings.toml");
+
+    #[test]
+    fn create_database_from_config() {
+        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
+        let database = DrillholeDatabase::new_from_config(&config_object).unwrap();
+        println!("database : {}", database);
+
+        let hole = database.get_drillhole("SK-12").expect("SK-12 cannot be found !");
+        assert!(hole.has_collar());
+        assert_eq!(hole.survey_count(), 1);
+        assert_eq!(hole.lythology_count(), 6);
+        assert_eq!(hole.raw_sample_count(), 3);
+        assert_eq!(hole.give_lytho_at(259.0), Some("kalkopirit"));
+
+        assert_eq!(hole.collar().map(|c| c.depth()), hole.collar_depth());
+        assert_eq!(hole.surveys().len(), 1);
+        assert!(hole.lythology().windows(2).all(|w| w[0].start() <= w[1].start()));
+        assert_eq!(hole.raw_samples().len(), 3);
+    }
+
+    #[test]
+    fn intervals_are_sorted_by_depth() {
+        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
+        let database = DrillholeDatabase::new_from_config(&config_object).unwrap();
+
+        for hole in database.drillholes() {
+            for i in 1..hole.lythology.len() {
+                assert!(hole.lythology[i - 1].start <= hole.lythology[i].start);
+            }
+            for i in 1..hole.raw_samples.len() {
+                assert!(hole.raw_samples[i - 1].start <= hole.raw_samples[i].start);
+            }
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::drillhole::database::{Drillhole, DrillholeDatabase};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DesurveyMethod {
+    Tangent,
+    BalancedTangent,
+    MinimumCurvature,
+}
+
+impl Display for DesurveyMethod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            DesurveyMethod::Tangent => f.write_str("tangent"),
+            DesurveyMethod::BalancedTangent => f.write_str("balanced tangent"),
+            DesurveyMethod::MinimumCurvature => f.write_str("minimum curvature")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DesurveyPoint {
+    pub depth: f64,
+    pub x_coord: f64,
+    pub y_coord: f64,
+    pub z_coord: f64,
+}
+
+impl DesurveyPoint {
+    fn new(depth: f64, position: [f64; 3]) -> DesurveyPoint {
+        DesurveyPoint {
+            depth,
+            x_coord: position[0],
+            y_coord: position[1],
+            z_coord: position[2],
+        }
+    }
+}
+
+impl Display for DesurveyPoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "depth : {} \n\
+                   x : {} \n\
+                   y : {} \n\
+                   z : {}", self.depth, self.x_coord, self.y_coord, self.z_coord)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DesurveyedInterval {
+    pub start: f64,
+    pub end: f64,
+    pub from_point: DesurveyPoint,
+    pub to_point: DesurveyPoint,
+    pub mid_point: DesurveyPoint,
+}
+
+// survey station with its position already calculated
+#[derive(Debug, Clone, Copy)]
+struct Station {
+    depth: f64,
+    position: [f64; 3],
+    direction: [f64; 3],
+}
+
+#[derive(Debug)]
+pub struct DesurveyedHole {
+    hole_id: String,
+    method: DesurveyMethod,
+    stations: Vec<Station>,
+}
+
+impl DesurveyedHole {
+    pub fn new(hole: &Drillhole, method: DesurveyMethod) -> Option<DesurveyedHole> {
+        let collar = hole.collar.as_ref()?;
+
+        // (depth, dip, azimuth). dips are negative downwards, so -90 is a vertical hole.
+        let mut surveys: Vec<(f64, f64, f64)> = hole.surveys.iter()
+            .map(|s| (s.depth, s.dalim as f64, s.azimuth as f64))
+            .collect();
+
+        if surveys.is_empty() {
+            surveys.push((0.0, -90.0, 0.0));
+        }
+
+        // collar takes the orientation of the first survey
+        if surveys[0].0 > 0.0 {
+            let (_, dip, azimuth) = surveys[0];
+            surveys.insert(0, (0.0, dip, azimuth));
+        }
+
+        let mut stations: Vec<Station> = vec![];
+        let mut position = [collar.x_coord, collar.y_coord, collar.z_coord];
+
+        for (depth, dip, azimuth) in surveys {
+            let direction = direction_vector(dip, azimuth);
+
+            if let Some(previous) = stations.last() {
+                position = step(method, previous, &direction, depth - previous.depth);
+            }
+
+            stations.push(Station {
+                depth,
+                position,
+                direction,
+            });
+        }
+
+        Some(DesurveyedHole {
+            hole_id: hole.hole_id.clone(),
+            method,
+            stations,
+        })
+    }
+
+    pub fn hole_id(&self) -> &str {
+        &self.hole_id
+    }
+
+    pub fn method(&self) -> DesurveyMethod {
+        self.method
+    }
+
+    pub fn position_at(&self, depth: f64) -> DesurveyPoint {
+        let depth = depth.max(0.0);
+
+        // last station whose depth is not deeper than the requested one
+        let index = self.stations.iter()
+            .rposition(|s| s.depth <= depth)
+            .unwrap_or(0);
+        let station = &self.stations[index];
+
+        let position = match self.stations.get(index + 1) {
+            Some(next) => {
+                let length = depth - station.depth;
+                let segment = next.depth - station.depth;
+
+                match self.method {
+                    DesurveyMethod::Tangent => {
+                        step(DesurveyMethod::Tangent, station, &next.direction, length)
+                    }
+                    DesurveyMethod::BalancedTangent => {
+                        let half = segment / 2.0;
+                        if length <= half {
+                            add(&station.position, &scale(&station.direction, length))
+                        } else {
+                            let middle = add(&station.position, &scale(&station.direction, half));
+                            add(&middle, &scale(&next.direction, length - half))
+                        }
+                    }
+                    DesurveyMethod::MinimumCurvature => {
+                        // the hole follows a circular arc, so the direction at the requested
+                        // depth is interpolated along the same arc
+                        let direction = slerp(&station.direction, &next.direction, length / segment);
+                        step(DesurveyMethod::MinimumCurvature, station, &direction, length)
+                    }
+                }
+            }
+            // below the last station the hole goes straight on
+            None => add(&station.position, &scale(&station.direction, depth - station.depth))
+        };
+
+        DesurveyPoint::new(depth, position)
+    }
+
+    pub fn desurvey_interval(&self, start: f64, end: f64) -> DesurveyedInterval {
+        DesurveyedInterval {
+            start,
+            end,
+            from_point: self.position_at(start),
+            to_point: self.position_at(end),
+            mid_point: self.position_at((start + end) / 2.0),
+        }
+    }
+
+    pub fn desurvey_lythology(&self, hole: &Drillhole) -> Vec<DesurveyedInterval> {
+        hole.lythology.iter()
+            .map(|l| self.desurvey_interval(l.start, l.end))
+            .collect()
+    }
+
+    pub fn desurvey_raw_samples(&self, hole: &Drillhole) -> Vec<DesurveyedInterval> {
+        hole.raw_samples.iter()
+            .map(|r| self.desurvey_interval(r.start, r.end))
+            .collect()
+    }
+}
+
+impl Display for DesurveyedHole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "hole id : {} \n\
+                   method : {} \n\
+                   stations : {:?}", self.hole_id, self.method, self.stations)
+    }
+}
+
+pub struct DesurveyObject {
+    method: DesurveyMethod,
+    holes: BTreeMap<String, DesurveyedHole>,
+}
+
+impl DesurveyObject {
+    pub fn new(database: &DrillholeDatabase, method: DesurveyMethod) -> DesurveyObject {
+        // holes without a collar cannot be placed in space
+        let holes = database.drillholes()
+            .filter_map(|h| DesurveyedHole::new(h, method))
+            .map(|d| (d.hole_id.clone(), d))
+            .collect();
+
+        DesurveyObject {
+            method,
+            holes,
+        }
+    }
+
+    pub fn get_desurveyed_hole(&self, hole_id: &str) -> Option<&DesurveyedHole> {
+        self.holes.get(hole_id)
+    }
+
+    pub fn desurveyed_holes(&self) -> impl Iterator<Item=&DesurveyedHole> {
+        self.holes.values()
+    }
+
+    pub fn method(&self) -> DesurveyMethod {
+        self.method
+    }
+}
+
+impl Display for DesurveyObject {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "method : {} \n\
+                   count of desurveyed holes : {}", self.method, self.holes.len())
+    }
+}
+
+fn direction_vector(dip: f64, azimuth: f64) -> [f64; 3] {
+    let (dip, azimuth) = (dip.to_radians(), azimuth.to_radians());
+
+    [dip.cos() * azimuth.sin(), dip.cos() * azimuth.cos(), dip.sin()]
+}
+
+fn step(method: DesurveyMethod, from: &Station, direction: &[f64; 3], length: f64) -> [f64; 3] {
+    match method {
+        DesurveyMethod::Tangent => add(&from.position, &scale(&from.direction, length)),
+        DesurveyMethod::BalancedTangent => {
+            let half = length / 2.0;
+            let middle = add(&from.position, &scale(&from.direction, half));
+            add(&middle, &scale(direction, half))
+        }
+        DesurveyMethod::MinimumCurvature => {
+            let dogleg = angle_between(&from.direction, direction);
+            let ratio_factor = if dogleg < 1e-9 {
+                1.0
+            } else {
+                2.0 / dogleg * (dogleg / 2.0).tan()
+            };
+
+            let sum = add(&from.direction, direction);
+            add(&from.position, &scale(&sum, length / 2.0 * ratio_factor))
+        }
+    }
+}
+
+fn slerp(v1: &[f64; 3], v2: &[f64; 3], t: f64) -> [f64; 3] {
+    let angle = angle_between(v1, v2);
+    if angle < 1e-9 {
+        return *v1;
+    }
+
+    let (w1, w2) = (((1.0 - t) * angle).sin() / angle.sin(), (t * angle).sin() / angle.sin());
+    add(&scale(v1, w1), &scale(v2, w2))
+}
+
+fn angle_between(v1: &[f64; 3], v2: &[f64; 3]) -> f64 {
+    let dot = v1[0] * v2[0] + v1[1] * v2[1] + v1[2] * v2[2];
+    dot.clamp(-1.0, 1.0).acos()
+}
+
+fn add(v1: &[f64; 3], v2: &[f64; 3]) -> [f64; 3] {
+    [v1[0] + v2[0], v1[1] + v2[1], v1[2] + v2[2]]
+}
+
+fn scale(v: &[f64; 3], factor: f64) -> [f64; 3] {
+    [v[0] * factor, v[1] * factor, v[2] * factor]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::drillhole::database::Drillhole;
+    use crate::drillhole::desurvey::{DesurveyedHole, DesurveyMethod, DesurveyObject};
+    use crate::excels::drill_reader::DrillCoordinate;
+    use crate::excels::slope_reader::DrillSlopeInfo;
+    use crate::lego_tests::give_me_drillhole_database;
+
+    fn curved_hole() -> Drillhole {
+        // goes from vertical to horizontal (east) in 100 meters
+        Drillhole {
+            hole_id: String::from("TEST-01"),
+            collar: Some(DrillCoordinate { x_coord: 0.0, y_coord: 0.0, z_coord: 0.0, depth: 150.0 }),
+            surveys: vec![DrillSlopeInfo { depth: 0.0, dalim: -90, azimuth: 90 },
+                          DrillSlopeInfo { depth: 100.0, dalim: 0, azimuth: 90 }],
+            lythology: vec![],
+            raw_samples: vec![],
+        }
+    }
+
+    #[test]
+    fn desurvey_vertical_holes() {
+        let database = give_me_drillhole_database();
+        let desurvey = DesurveyObject::new(&database, DesurveyMethod::MinimumCurvature);
+
+        let hole = desurvey.get_desurveyed_hole("SK-12").unwrap();
+        let point = hole.position_at(100.0);
+
+        assert!((point.x_coord - 68992.03).abs() < 1e-6);
+        assert!((point.y_coord - 376012.92).abs() < 1e-6);
+        assert!((point.z_coord - 463.92).abs() < 1e-6);
+    }
+
+    #[test]
+    fn minimum_curvature_follows_arc() {
+        let hole = DesurveyedHole::new(&curved_hole(), DesurveyMethod::MinimumCurvature).unwrap();
+        let radius = 100.0 / std::f64::consts::FRAC_PI_2;
+
+        let end = hole.position_at(100.0);
+        assert!((end.x_coord - radius).abs() < 1e-6);
+        assert!((end.z_coord + radius).abs() < 1e-6);
+
+        // half way through the arc
+        let middle = hole.position_at(50.0);
+        let expected = radius * std::f64::consts::FRAC_PI_4.sin();
+        assert!((middle.x_coord - (radius - expected)).abs() < 1e-6);
+        assert!((middle.z_coord + expected).abs() < 1e-6);
+
+        // straight on below the last station
+        let below = hole.position_at(150.0);
+        assert!((below.x_coord - (radius + 50.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn tangent_and_balanced_tangent() {
+        let tangent = DesurveyedHole::new(&curved_hole(), DesurveyMethod::Tangent).unwrap();
+        let end = tangent.position_at(100.0);
+        assert!(end.x_coord.abs() < 1e-6);
+        assert!((end.z_coord + 100.0).abs() < 1e-6);
+
+        let balanced = DesurveyedHole::new(&curved_hole(), DesurveyMethod::BalancedTangent).unwrap();
+        let end = balanced.position_at(100.0);
+        assert!((end.x_coord - 50.0).abs() < 1e-6);
+        assert!((end.z_coord + 50.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn desurvey_sample_intervals() {
+        let database = give_me_drillhole_database();
+        let hole = database.get_drillhole("SK-12").unwrap();
+        let desurveyed = DesurveyedHole::new(hole, DesurveyMethod::MinimumCurvature).unwrap();
+
+        let intervals = desurveyed.desurvey_raw_samples(hole);
+        assert_eq!(intervals.len(), hole.raw_sample_count());
+        for i in intervals {
+            assert!(i.from_point.z_coord > i.to_point.z_coord);
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use lego_config::error::LegoError;
+
+use crate::drillhole::database::DrillholeDatabase;
+use crate::estimation::sectional::grade_unit;
+use crate::excels::excel_traits::{report_error, report_text, report_writer, write_report};
+use crate::excels::lytho_reader::LythologyCoordinate;
+use crate::excels::rawsample_reader::RawSampleCoordinate;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InterceptParameters {
+    pub cut_off: f64,
+    pub min_length: f64,
+    // longest continuous run below the cut off that can be carried inside an intercept.
+    // unsampled gaps are taken as zero grade.
+    pub max_dilution: f64,
+}
+
+impl InterceptParameters {
+    pub fn new(cut_off: f64, min_length: f64, max_dilution: f64) -> InterceptParameters {
+        InterceptParameters { cut_off, min_length, max_dilution }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), LegoError> {
+        if self.min_length < 0.0 || self.max_dilution < 0.0 {
+            return Err(LegoError::parameter(format!("minimum length and maximum dilution cannot be negative, got {} {}",
+                                                    self.min_length, self.max_dilution)));
+        }
+        Ok(())
+    }
+}
+
+impl Display for InterceptParameters {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "cut off : {} \n\
+                   minimum length : {} \n\
+                   maximum dilution : {}", self.cut_off, self.min_length, self.max_dilution)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Intercept {
+    pub hole_id: String,
+    pub from: f64,
+    pub to: f64,
+    // length weighted, dilution included
+    pub grade: f64,
+    pub dilution: f64,
+    // lythologies crossed by the intercept in depth order
+    pub lythology: Vec<String>,
+}
+
+impl Intercept {
+    pub fn length(&self) -> f64 {
+        self.to - self.from
+    }
+
+    pub fn grade_thickness(&self) -> f64 {
+        self.grade * self.length()
+    }
+}
+
+// significant intercepts of every hole ordered by hole id and depth
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterceptTable {
+    pub parameters: InterceptParameters,
+    pub mining_type: String,
+    pub intercepts: Vec<Intercept>,
+}
+
+impl InterceptTable {
+    pub fn new(database: &DrillholeDatabase, parameters: InterceptParameters,
+               mining_type: &str) -> Result<InterceptTable, LegoError> {
+        parameters.validate()?;
+
+        let mut intercepts: Vec<Intercept> = vec![];
+        for hole in database.drillholes() {
+            intercepts.extend(hole_intercepts(hole.hole_id(), &hole.raw_samples, &hole.lythology, &parameters));
+        }
+
+        Ok(InterceptTable {
+            parameters,
+            mining_type: mining_type.to_string(),
+            intercepts,
+        })
+    }
+
+    pub fn get_hole_intercepts(&self, hole_id: &str) -> Vec<&Intercept> {
+        self.intercepts.iter().filter(|i| i.hole_id == hole_id).collect()
+    }
+
+    // one line of a press release, e.g. SK-12: 5.0 m @ 5.44% Cu from 258.9 m (kalkopirit)
+    pub fn describe(&self, intercept: &Intercept) -> String {
+        let mut element = self.mining_type.clone();
+        if let Some(first) = element.get_mut(0..1) {
+            first.make_ascii_uppercase();
+        }
+
+        let unit = grade_unit(&self.mining_type);
+        let separator = if unit == "%" { "" } else { " " };
+        format!("{}: {:.1} m @ {:.2}{}{} {} from {:.1} m ({})", intercept.hole_id, intercept.length(),
+                intercept.grade, separator, unit, element, intercept.from, intercept.lythology.join("/"))
+    }
+
+    pub fn to_csv(&self, seperator: char) -> Result<String, LegoError> {
+        let mut writer = report_writer(seperator);
+
+        writer.write_record(["hole_id", "from", "to", "length", "grade", "grade_thickness", "dilution",
+            "lytho"]).map_err(report_error)?;
+        for i in self.intercepts.iter() {
+            writer.write_record(&[i.hole_id.clone(), i.from.to_string(), i.to.to_string(), i.length().to_string(),
+                i.grade.to_string(), i.grade_thickness().to_string(), i.dilution.to_string(),
+                i.lythology.join("/")]).map_err(report_error)?;
+        }
+
+        report_text(writer)
+    }
+
+    pub fn write_csv(&self, path: &str, seperator: char) -> Result<(), LegoError> {
+        write_report(path, &self.to_csv(seperator)?)
+    }
+}
+
+impl Display for InterceptTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} \nintercepts : {}", self.parameters, self.intercepts.len())?;
+        for i in self.intercepts.iter() {
+            write!(f, "\n{}", self.describe(i))?;
+        }
+        Ok(())
+    }
+}
+
+// Intercepts start and end on samples above the cut off. A run below the cut off is carried
+// when it is not longer than the maximum dilution and the grade stays above the cut off.
+fn hole_intercepts(hole_id: &str, samples: &[RawSampleCoordinate], lythology: &[LythologyCoordinate],
+                   parameters: &InterceptParameters) -> Vec<Intercept> {
+    // gaps between the samples are added as zero grade intervals
+    let mut intervals: Vec<(f64, f64, f64)> = vec![];
+    for r in samples.iter().filter(|r| r.end > r.start) {
+        if let Some(&(_, end, _)) = intervals.last() {
+            if r.start > end {
+                intervals.push((end, r.start, 0.0));
+            }
+        }
+        intervals.push((r.start, r.end, r.percent));
+    }
+
+    let above = |i: usize| intervals[i].2 >= parameters.cut_off;
+    let metal = |from: usize, to: usize| intervals[from..=to].iter().map(|(s, e, g)| (e - s) * g).sum::<f64>();
+    let length = |from: usize, to: usize| intervals[to].1 - intervals[from].0;
+
+    let mut intercepts: Vec<Intercept> = vec![];
+    let mut i = 0;
+
+    while i < intervals.len() {
+        if !above(i) {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let mut end = i;
+        let mut next = i + 1;
+
+        loop {
+            // skip the run below the cut off
+            let mut k = next;
+            while k < intervals.len() && !above(k) {
+                k += 1;
+            }
+            if k == intervals.len() {
+                break;
+            }
+
+            let run = if k > next { intervals[k - 1].1 - intervals[next].0 } else { 0.0 };
+            if run > parameters.max_dilution || metal(start, k) / length(start, k) < parameters.cut_off {
+                break;
+            }
+
+            end = k;
+            next = k + 1;
+        }
+
+        let (from, to) = (intervals[start].0, intervals[end].1);
+        if to - from >= parameters.min_length {
+            let dilution = intervals[start..=end].iter()
+                .filter(|(_, _, g)| *g < parameters.cut_off)
+                .map(|(s, e, _)| e - s)
+                .sum();
+
+            let mut names: Vec<String> = vec![];
+            for l in lythology.iter().filter(|l| l.start < to && l.end > from) {
+                if names.last() != Some(&l.lytho) {
+                    names.push(l.lytho.clone());
+                }
+            }
+
+            intercepts.push(Intercept {
+                hole_id: hole_id.to_string(),
+                from,
+                to,
+                grade: metal(start, end) / (to - from),
+                dilution,
+                lythology: names,
+            });
+        }
+
+        i = end + 1;
+    }
+
+    intercepts
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::drillhole::intercepts::{hole_intercepts, InterceptParameters, InterceptTable};
+    use crate::excels::lytho_reader::LythologyCoordinate;
+    use crate::excels::rawsample_reader::RawSampleCoordinate;
+    use crate::lego_tests::give_me_drillhole_database;
+
+    fn sample(start: f64, end: f64, percent: f64) -> RawSampleCoordinate {
+        RawSampleCoordinate { start, end, percent }
+    }
+
+    #[test]
+    fn internal_dilution() {
+        let samples = vec![sample(0.0, 2.0, 0.1), sample(2.0, 4.0, 2.0), sample(4.0, 6.0, 0.2),
+                           sample(6.0, 8.0, 1.5), sample(8.0, 12.0, 0.1), sample(12.0, 13.0, 3.0),
+                           sample(16.0, 17.0, 4.0)];
+        let lythology = vec![LythologyCoordinate { start: 0.0, end: 5.0, lytho: String::from("andezit") },
+                             LythologyCoordinate { start: 5.0, end: 20.0, lytho: String::from("kalkopirit") }];
+
+        // 2 m of dilution is carried, 4 m and the 3 m gap are not
+        let intercepts = hole_intercepts("A", &samples, &lythology, &InterceptParameters::new(1.0, 2.0, 2.0));
+        assert_eq!(intercepts.len(), 1);
+        assert_eq!((intercepts[0].from, intercepts[0].to), (2.0, 8.0));
+        assert!((intercepts[0].grade - 3.7 / 6.0 * 2.0).abs() < 1e-12);
+        assert_eq!(intercepts[0].dilution, 2.0);
+        assert_eq!(intercepts[0].lythology, vec![String::from("andezit"), String::from("kalkopirit")]);
+
+        // without dilution the short ones are dropped
+        let intercepts = hole_intercepts("A", &samples, &lythology, &InterceptParameters::new(1.0, 2.0, 0.0));
+        assert_eq!(intercepts.len(), 2);
+        assert_eq!(intercepts[1].from, 6.0);
+
+        // 8 - 12 would take the grade below the cut off, the gap is carried
+        let intercepts = hole_intercepts("A", &samples, &lythology, &InterceptParameters::new(1.0, 0.5, 5.0));
+        assert_eq!(intercepts.len(), 2);
+        assert_eq!((intercepts[0].from, intercepts[0].to), (2.0, 8.0));
+        assert_eq!((intercepts[1].from, intercepts[1].to, intercepts[1].dilution), (12.0, 17.0, 3.0));
+    }
+
+    #[test]
+    fn test_hole_intercepts() {
+        let table = InterceptTable::new(&give_me_drillhole_database(), InterceptParameters::new(3.0, 2.0, 2.0),
+                                        "cu").unwrap();
+        println!("{}", table);
+
+        let sk_12 = table.get_hole_intercepts("SK-12");
+        assert_eq!(sk_12.len(), 1);
+        assert_eq!(table.describe(sk_12[0]), "SK-12: 5.0 m @ 5.44% Cu from 258.9 m (kalkopirit)");
+        assert_eq!(table.to_csv(';').unwrap().lines().count(), table.intercepts.len() + 1);
+
+        assert!(InterceptTable::new(&give_me_drillhole_database(), InterceptParameters::new(3.0, -1.0, 2.0),
+                                    "cu").is_err());
+    }
+}
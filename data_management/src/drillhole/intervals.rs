@@ -0,0 +1,181 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use lego_config::error::LegoError;
+
+use crate::drillhole::database::{Drillhole, DrillholeDatabase};
+use crate::drillhole::desurvey::{DesurveyedInterval, DesurveyObject};
+use crate::excels::excel_traits::{report_error, report_text, report_writer, write_report};
+use crate::statistics::descriptive::{DomainValue, UNDEFINED_DOMAIN};
+
+// breakpoints closer than this are taken as the same one
+const LENGTH_TOLERANCE: f64 = 1e-6;
+
+// piece of a hole between two breakpoints of the lythology and raw sample tables
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedInterval {
+    pub hole_id: String,
+    pub start: f64,
+    pub end: f64,
+    // None where the piece is not logged or not sampled
+    pub lytho: Option<String>,
+    pub percent: Option<f64>,
+}
+
+impl MergedInterval {
+    pub fn length(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+// Lythology and raw samples of every hole split at the union of their breakpoints. Pieces
+// which are neither logged nor sampled are left out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntervalTable {
+    // ordered by hole id and depth
+    intervals: Vec<MergedInterval>,
+}
+
+impl IntervalTable {
+    pub fn new(database: &DrillholeDatabase) -> IntervalTable {
+        IntervalTable {
+            intervals: database.drillholes().flat_map(merge_hole).collect(),
+        }
+    }
+
+    pub fn intervals(&self) -> &[MergedInterval] {
+        &self.intervals
+    }
+
+    pub fn get_hole_intervals(&self, hole_id: &str) -> Vec<&MergedInterval> {
+        self.intervals.iter().filter(|i| i.hole_id == hole_id).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.intervals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    // intervals of the holes without collar are left out
+    pub fn desurvey<'a>(&'a self, desurveyed: &DesurveyObject) -> Vec<(&'a MergedInterval, DesurveyedInterval)> {
+        self.intervals.iter()
+            .filter_map(|i| desurveyed.get_desurveyed_hole(&i.hole_id)
+                .map(|h| (i, h.desurvey_interval(i.start, i.end))))
+            .collect()
+    }
+
+    // sampled pieces coded with their lythology
+    pub fn domain_values(&self) -> Vec<DomainValue> {
+        self.intervals.iter()
+            .filter_map(|i| i.percent.map(|p| {
+                DomainValue::new(i.lytho.clone().unwrap_or_else(|| UNDEFINED_DOMAIN.to_string()), p, i.length())
+                    .with_source(&i.hole_id, i.start, i.end)
+            }))
+            .collect()
+    }
+
+    pub fn to_csv(&self, seperator: char) -> Result<String, LegoError> {
+        let mut writer = report_writer(seperator);
+
+        writer.write_record(["hole_id", "from", "to", "lytho", "percent"]).map_err(report_error)?;
+        for i in self.intervals.iter() {
+            writer.write_record(&[i.hole_id.clone(), i.start.to_string(), i.end.to_string(),
+                i.lytho.clone().unwrap_or_default(), i.percent.map(|p| p.to_string()).unwrap_or_default()])
+                .map_err(report_error)?;
+        }
+
+        report_text(writer)
+    }
+
+    pub fn write_csv(&self, path: &str, seperator: char) -> Result<(), LegoError> {
+        write_report(path, &self.to_csv(seperator)?)
+    }
+}
+
+impl Display for IntervalTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "intervals : {} \n\
+                   logged : {} \n\
+                   sampled : {}", self.intervals.len(),
+               self.intervals.iter().filter(|i| i.lytho.is_some()).count(),
+               self.intervals.iter().filter(|i| i.percent.is_some()).count())
+    }
+}
+
+fn merge_hole(hole: &Drillhole) -> Vec<MergedInterval> {
+    let mut breaks: Vec<f64> = hole.lythology.iter().flat_map(|l| [l.start, l.end])
+        .chain(hole.raw_samples.iter().flat_map(|r| [r.start, r.end]))
+        .collect();
+    breaks.sort_by(|a, b| a.total_cmp(b));
+    breaks.dedup_by(|b, a| *b - *a < LENGTH_TOLERANCE);
+
+    breaks.windows(2)
+        .filter_map(|b| {
+            let middle = (b[0] + b[1]) / 2.0;
+            let lytho = hole.give_lytho_at(middle).map(|l| l.to_string());
+            let percent = hole.raw_samples.iter()
+                .find(|r| r.start <= middle && middle < r.end)
+                .map(|r| r.percent);
+
+            if lytho.is_none() && percent.is_none() {
+                return None;
+            }
+
+            Some(MergedInterval {
+                hole_id: hole.hole_id.clone(),
+                start: b[0],
+                end: b[1],
+                lytho,
+                percent,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::drillhole::desurvey::{DesurveyMethod, DesurveyObject};
+    use crate::drillhole::intervals::IntervalTable;
+    use crate::lego_tests::give_me_drillhole_database;
+
+    #[test]
+    fn merge_test_tables() {
+        let database = give_me_drillhole_database();
+        let table = IntervalTable::new(&database);
+        println!("{}", table);
+
+        // SK-12 is logged down to 283.92 and sampled between 258.92 and 263.92 on the lythology breaks
+        let sk_12 = table.get_hole_intervals("SK-12");
+        assert_eq!(sk_12.len(), 6);
+        assert_eq!(sk_12[2].lytho.as_deref(), Some("kalkopirit"));
+        assert_eq!((sk_12[2].start, sk_12[2].percent), (258.92, Some(5.21)));
+        assert_eq!(sk_12[5].percent, None);
+        for pair in sk_12.windows(2) {
+            assert!(pair[0].end <= pair[1].start + 1e-9);
+        }
+
+        // splitting keeps the sampled length and the metal of every hole
+        for hole in database.drillholes() {
+            let length: f64 = hole.raw_samples.iter().map(|r| r.end - r.start).sum();
+            let metal: f64 = hole.raw_samples.iter().map(|r| (r.end - r.start) * r.percent).sum();
+            let pieces = table.get_hole_intervals(hole.hole_id());
+            let merged_length: f64 = pieces.iter().filter(|i| i.percent.is_some()).map(|i| i.length()).sum();
+            let merged_metal: f64 = pieces.iter().filter_map(|i| i.percent.map(|p| p * i.length())).sum();
+            assert!((length - merged_length).abs() < 1e-6 && (metal - merged_metal).abs() < 1e-6);
+        }
+
+        let values = table.domain_values();
+        assert_eq!(values.len(), table.intervals().iter().filter(|i| i.percent.is_some()).count());
+
+        let desurveyed = table.desurvey(&DesurveyObject::new(&database, DesurveyMethod::MinimumCurvature));
+        assert!(!desurveyed.is_empty());
+        for (i, d) in desurveyed.iter() {
+            assert_eq!((i.start, i.end), (d.start, d.end));
+        }
+
+        assert_eq!(table.to_csv(';').unwrap().lines().count(), table.len() + 1);
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use lego_config::error::LegoError;
+use lego_config::read::LegoConfig;
+
+use crate::excels::drill_reader::{Drill, DrillInformation, DrillObject};
+use crate::excels::lytho_reader::{Lythology, LythologyInformation, LythologyObject};
+use crate::excels::rawsample_reader::{RawSample, RawSampleInformation, RawSampleObject};
+use crate::excels::slope_reader::{Slope, SlopeInformation, SlopeObject};
+
+// tolerance in meters while comparing depths
+const DEPTH_TOLERANCE: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Severity::Warning => f.write_str("WARNING"),
+            Severity::Error => f.write_str("ERROR")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceTable {
+    Drill,
+    Slope,
+    Lythology,
+    RawSample,
+}
+
+impl Display for SourceTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            SourceTable::Drill => f.write_str("drill"),
+            SourceTable::Slope => f.write_str("slope"),
+            SourceTable::Lythology => f.write_str("lythology"),
+            SourceTable::RawSample => f.write_str("raw sample")
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub table: SourceTable,
+    pub hole_id: String,
+    // 1 based data row of the table, header is not counted
+    pub row: Option<usize>,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn new(severity: Severity, table: SourceTable, hole_id: &str, row: Option<usize>,
+           message: String) -> ValidationIssue {
+        ValidationIssue {
+            severity,
+            table,
+            hole_id: hole_id.to_string(),
+            row,
+            message,
+        }
+    }
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let row = match self.row {
+            Some(r) => r.to_string(),
+            None => String::from("-")
+        };
+
+        write!(f, "[{}] {} | hole : {} | row : {} | {}", self.severity, self.table,
+               self.hole_id, row, self.message)
+    }
+}
+
+pub struct ValidationReport {
+    issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn new(drills: &DrillObject, slopes: &SlopeObject, lythologies: &LythologyObject,
+               raw_samples: &RawSampleObject) -> ValidationReport {
+        ValidationReport::validate(&drills.data, &slopes.data, &lythologies.data, &raw_samples.data)
+    }
+
+    pub fn new_from_config(config: &LegoConfig) -> Result<ValidationReport, LegoError> {
+        let drills = DrillObject::new(DrillInformation::new_from_config(config)?)?;
+        let slopes = SlopeObject::new(SlopeInformation::new_from_config(config)?)?;
+        let lythologies = LythologyObject::new(LythologyInformation::new_from_config(config)?)?;
+        let raw_samples = RawSampleObject::new(RawSampleInformation::new_from_config(config)?)?;
+
+        Ok(ValidationReport::new(&drills, &slopes, &lythologies, &raw_samples))
+    }
+
+    fn validate(drills: &[Drill], slopes: &[Slope], lythologies: &[Lythology],
+                raw_samples: &[RawSample]) -> ValidationReport {
+        let mut issues: Vec<ValidationIssue> = vec![];
+
+        // collar depths by hole id
+        let mut collars: HashMap<&str, f64> = HashMap::new();
+        for (index, d) in drills.iter().enumerate() {
+            if collars.insert(&d.drill_no, d.coordinate.depth).is_some() {
+                issues.push(ValidationIssue::new(Severity::Error, SourceTable::Drill, &d.drill_no,
+                                                 Some(index + 1), String::from("duplicate collar")));
+            }
+        }
+
+        let lythology_rows: Vec<(&str, f64, f64)> = lythologies.iter()
+            .map(|l| (l.drill_no.as_str(), l.coordinate.start, l.coordinate.end))
+            .collect();
+        let raw_sample_rows: Vec<(&str, f64, f64)> = raw_samples.iter()
+            .map(|r| (r.drill_no.as_str(), r.coordinate.start, r.coordinate.end))
+            .collect();
+
+        check_intervals(SourceTable::Lythology, &lythology_rows, &collars, &mut issues);
+        check_intervals(SourceTable::RawSample, &raw_sample_rows, &collars, &mut issues);
+        check_surveys(slopes, &collars, &mut issues);
+
+        ValidationReport {
+            issues,
+        }
+    }
+
+    pub fn issues(&self) -> &Vec<ValidationIssue> {
+        &self.issues
+    }
+
+    pub fn errors(&self) -> Vec<&ValidationIssue> {
+        self.issues.iter().filter(|i| i.severity == Severity::Error).collect()
+    }
+
+    pub fn warnings(&self) -> Vec<&ValidationIssue> {
+        self.issues.iter().filter(|i| i.severity == Severity::Warning).collect()
+    }
+
+    pub fn issues_for_hole(&self, hole_id: &str) -> Vec<&ValidationIssue> {
+        self.issues.iter().filter(|i| i.hole_id == hole_id).collect()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|i| i.severity == Severity::Error)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "errors : {} \n\
+                     warnings : {}", self.errors().len(), self.warnings().len())?;
+
+        for issue in self.issues.iter() {
+            writeln!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+fn check_intervals(table: SourceTable, rows: &[(&str, f64, f64)], collars: &HashMap<&str, f64>,
+                   issues: &mut Vec<ValidationIssue>) {
+    // (row, start, end) grouped by hole id
+    let mut holes: HashMap<&str, Vec<(usize, f64, f64)>> = HashMap::new();
+    let mut orphans: HashSet<&str> = HashSet::new();
+
+    for (index, &(hole_id, start, end)) in rows.iter().enumerate() {
+        let row = index + 1;
+
+        let valid = start < end;
+        if !valid {
+            issues.push(ValidationIssue::new(Severity::Error, table, hole_id, Some(row),
+                                             format!("FROM ({}) must be smaller than TO ({})", start, end)));
+        }
+
+        match collars.get(hole_id) {
+            Some(depth) => {
+                if end > depth + DEPTH_TOLERANCE {
+                    issues.push(ValidationIssue::new(Severity::Error, table, hole_id, Some(row),
+                                                     format!("TO ({}) exceeds collar depth ({})", end, depth)));
+                }
+            }
+            None => {
+                // reporting orphan hole once, not for each of its rows
+                if orphans.insert(hole_id) {
+                    issues.push(ValidationIssue::new(Severity::Error, table, hole_id, Some(row),
+                                                     String::from("hole does not exist in drill table")));
+                }
+            }
+        }
+
+        // invalid intervals are reported already, they would only add false gaps and overlaps
+        let intervals = holes.entry(hole_id).or_default();
+        if valid {
+            intervals.push((row, start, end));
+        }
+    }
+
+    let mut hole_ids: Vec<&&str> = holes.keys().collect();
+    hole_ids.sort();
+
+    for hole_id in hole_ids {
+        let mut intervals = holes[*hole_id].clone();
+        intervals.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        // interval reaching the deepest so far, nested intervals are compared with it
+        // instead of their neighbour
+        let mut deepest: Option<(usize, f64, f64)> = None;
+
+        for &current in intervals.iter() {
+            if let Some(previous) = deepest {
+                if current.1 < previous.2 - DEPTH_TOLERANCE {
+                    issues.push(ValidationIssue::new(Severity::Error, table, hole_id, Some(current.0),
+                                                     format!("interval {}-{} overlaps with row {} ({}-{})",
+                                                             current.1, current.2, previous.0, previous.1,
+                                                             previous.2)));
+                } else if current.1 > previous.2 + DEPTH_TOLERANCE {
+                    issues.push(ValidationIssue::new(Severity::Warning, table, hole_id, Some(current.0),
+                                                     format!("gap between {} and {}", previous.2, current.1)));
+                }
+            }
+
+            if deepest.map(|d| current.2 > d.2).unwrap_or(true) {
+                deepest = Some(current);
+            }
+        }
+    }
+}
+
+fn check_surveys(slopes: &[Slope], collars: &HashMap<&str, f64>, issues: &mut Vec<ValidationIssue>) {
+    let mut orphans: HashSet<&str> = HashSet::new();
+
+    for (index, s) in slopes.iter().enumerate() {
+        let (hole_id, row) = (s.drill_no.as_str(), index + 1);
+        let depth = s.drill_info.depth;
+
+        if depth < 0.0 {
+            issues.push(ValidationIssue::new(Severity::Error, SourceTable::Slope, hole_id, Some(row),
+                                             format!("negative survey depth ({})", depth)));
+        }
+
+        if !(-90..=90).contains(&s.drill_info.dalim) {
+            issues.push(ValidationIssue::new(Severity::Error, SourceTable::Slope, hole_id, Some(row),
+                                             format!("dip ({}) is out of -90..90", s.drill_info.dalim)));
+        }
+
+        match collars.get(hole_id) {
+            Some(collar_depth) => {
+                if depth > collar_depth + DEPTH_TOLERANCE {
+                    issues.push(ValidationIssue::new(Severity::Warning, SourceTable::Slope, hole_id, Some(row),
+                                                     format!("survey depth ({}) exceeds collar depth ({})",
+                                                             depth, collar_depth)));
+                }
+            }
+            None => {
+                if orphans.insert(hole_id) {
+                    issues.push(ValidationIssue::new(Severity::Error, SourceTable::Slope, hole_id, Some(row),
+                                                     String::from("hole does not exist in drill table")));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lego_config::read::LegoConfig;
+
+    use crate::drillhole::validation::{SourceTable, ValidationReport};
+    use crate::excels::drill_reader::{Drill, DrillCoordinate, DrillInformation, DrillObject};
+    use crate::excels::lytho_reader::{Lythology, LythologyCoordinate, LythologyInformation, LythologyObject};
+    use crate::excels::rawsample_reader::{RawSample, RawSampleCoordinate, RawSampleInformation, RawSampleObject};
+    use crate::excels::slope_reader::{SlopeInformation, SlopeObject};
+
+    const TEST_CONFIG_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../lego_config/test_settings.toml");
+
+    fn lytho(drill_no: &str, start: f64, end: f64) -> Lythology {
+        Lythology {
+            drill_no: drill_no.to_string(),
+            coordinate: LythologyCoordinate { start, end, lytho: String::from("andezit") },
+            extra: vec![],
+        }
+    }
+
+    #[test]
+    fn validate_test_data() {
+        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
+        let report = ValidationReport::new_from_config(&config_object).unwrap();
+        println!("validation report : {}", report);
+
+        // test data is clean
+        assert!(report.is_valid());
+        assert!(report.errors().is_empty() && report.warnings().is_empty());
+
+        // same data with a duplicate collar and an orphan lythology row
+        let drills = DrillObject::new(DrillInformation::new_from_config(&config_object).unwrap()).unwrap();
+        let lythologies = LythologyObject::new(LythologyInformation::new_from_config(&config_object).unwrap())
+            .unwrap();
+        let raw_samples = RawSampleObject::new(RawSampleInformation::new_from_config(&config_object).unwrap())
+            .unwrap();
+        let slopes = SlopeObject::new(SlopeInformation::new_from_config(&config_object).unwrap()).unwrap();
+
+        let mut drill_rows = drills.data.clone();
+        drill_rows.push(drills.data[0].clone());
+        let mut lythology_rows = lythologies.data.clone();
+        lythology_rows.push(lytho("SK-99", 0.0, 10.0));
+
+        let report = ValidationReport::validate(&drill_rows, &slopes.data, &lythology_rows, &raw_samples.data);
+        println!("validation report : {}", report);
+
+        let errors = report.errors();
+        assert_eq!(errors.len(), 2);
+        assert!(report.warnings().is_empty());
+        assert_eq!(errors[0].to_string(),
+                   format!("[ERROR] drill | hole : {} | row : {} | duplicate collar", drills.data[0].drill_no,
+                           drill_rows.len()));
+        assert_eq!(errors[1].to_string(),
+                   format!("[ERROR] lythology | hole : SK-99 | row : {} | hole does not exist in drill table",
+                           lythology_rows.len()));
+    }
+
+    #[test]
+    fn find_nested_intervals() {
+        let drills = vec![Drill::new(String::from("SK-01"), DrillCoordinate::new(0.0, 0.0, 0.0, 100.0))];
+        let lythologies = vec![lytho("SK-01", 0.0, 100.0),
+                               lytho("SK-01", 10.0, 20.0),
+                               lytho("SK-01", 30.0, 40.0)];
+
+        let report = ValidationReport::validate(&drills, &[], &lythologies, &[]);
+        for issue in report.issues() {
+            println!("{}", issue);
+        }
+
+        // both of the inner intervals overlap the outer one and there is no gap
+        assert!(report.warnings().is_empty());
+        let messages: Vec<(Option<usize>, &str)> = report.errors().iter()
+            .map(|i| (i.row, i.message.as_str()))
+            .collect();
+        assert_eq!(messages, vec![(Some(2), "interval 10-20 overlaps with row 1 (0-100)"),
+                                  (Some(3), "interval 30-40 overlaps with row 1 (0-100)")]);
+    }
+
+    #[test]
+    fn find_broken_intervals() {
+        let drills = vec![Drill {
+            drill_no: String::from("SK-01"),
+            coordinate: DrillCoordinate { x_coord: 0.0, y_coord: 0.0, z_coord: 0.0, depth: 100.0 },
+            extra: vec![],
+        }];
+        let lythologies = vec![lytho("SK-01", 0.0, 50.0),
+                               lytho("SK-01", 40.0, 60.0),
+                               lytho("SK-01", 70.0, 65.0),
+                               lytho("SK-01", 80.0, 120.0),
+                               lytho("SK-99", 0.0, 10.0)];
+        let raw_samples = vec![RawSample {
+            drill_no: String::from("SK-01"),
+            coordinate: RawSampleCoordinate { start: 10.0, end: 11.0, percent: 1.0 },
+            extra: vec![],
+        }];
+
+        let report = ValidationReport::validate(&drills, &[], &lythologies, &raw_samples);
+        for issue in report.issues() {
+            println!("{}", issue);
+        }
+
+        let errors = report.errors();
+        assert!(errors.iter().any(|i| i.row == Some(2) && i.message.contains("overlaps")));
+        assert!(errors.iter().any(|i| i.row == Some(3) && i.message.contains("FROM")));
+        assert!(errors.iter().any(|i| i.row == Some(4) && i.message.contains("collar depth")));
+        assert!(errors.iter().any(|i| i.hole_id == "SK-99" && i.table == SourceTable::Lythology));
+        // the invalid 70-65 interval is left out of the gaps
+        let warnings: Vec<(Option<usize>, &str)> = report.warnings().iter()
+            .map(|i| (i.row, i.message.as_str()))
+            .collect();
+        assert_eq!(warnings, vec![(Some(4), "gap between 60 and 80")]);
+        assert!(report.issues_for_hole("SK-01").iter().all(|i| i.table == SourceTable::Lythology));
+    }
+}
@@ -0,0 +1,289 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use lego_config::error::LegoError;
+
+use crate::block::model::{AttributeKind, AttributeValue, BlockModel};
+use crate::estimation::search::{Neighbour, Sample, SearchParameters};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InterpolationMethod {
+    InverseDistance { power: f64 },
+    NearestNeighbour,
+}
+
+impl Display for InterpolationMethod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InterpolationMethod::InverseDistance { power } => write!(f, "inverse distance power {}", power),
+            InterpolationMethod::NearestNeighbour => write!(f, "nearest neighbour"),
+        }
+    }
+}
+
+// counts of a finished estimation
+#[derive(Debug, Clone, PartialEq)]
+pub struct EstimationSummary {
+    pub attribute: String,
+    pub candidates: usize,
+    // estimated block count of every pass
+    pub per_pass: Vec<usize>,
+    pub unestimated: usize,
+}
+
+impl EstimationSummary {
+    pub(crate) fn new(attribute: &str, pass_count: usize) -> EstimationSummary {
+        EstimationSummary {
+            attribute: attribute.to_string(),
+            candidates: 0,
+            per_pass: vec![0; pass_count],
+            unestimated: 0,
+        }
+    }
+
+    pub fn estimated(&self) -> usize {
+        self.per_pass.iter().sum()
+    }
+}
+
+impl Display for EstimationSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "attribute : {} \n\
+                   candidate blocks : {} \n\
+                   estimated per pass : {:?} \n\
+                   unestimated : {}", self.attribute, self.candidates, self.per_pass, self.unestimated)
+    }
+}
+
+// Writes the estimate to the attribute and the audit values to <attribute>_samples,
+// <attribute>_distance (mean euclidean distance of the used samples) and <attribute>_pass.
+#[derive(Debug, Clone)]
+pub struct DistanceEstimator {
+    pub method: InterpolationMethod,
+    pub search: SearchParameters,
+}
+
+impl DistanceEstimator {
+    pub fn new(method: InterpolationMethod, search: SearchParameters) -> DistanceEstimator {
+        DistanceEstimator { method, search }
+    }
+
+    // only blocks flagged by `within` are estimated when it is given
+    pub fn estimate(&self, model: &mut BlockModel, samples: &[Sample], attribute: &str,
+                    within: Option<&str>) -> Result<EstimationSummary, LegoError> {
+        self.search.validate()?;
+        if let InterpolationMethod::InverseDistance { power } = self.method {
+            if power < 0.0 {
+                return Err(LegoError::parameter(format!("inverse distance power cannot be negative, got {}", power)));
+            }
+        }
+
+        let audit = AuditAttributes::prepare(model, attribute)?;
+        let mut summary = EstimationSummary::new(attribute, self.search.passes.len());
+
+        for index in 0..model.block_count() {
+            if !is_candidate(model, within, index)? {
+                continue;
+            }
+            summary.candidates += 1;
+            audit.clear(model, index)?;
+
+            let (pass, found) = match self.search.search(samples, model.world_centroid(index)) {
+                Some(result) => result,
+                None => {
+                    summary.unestimated += 1;
+                    continue;
+                }
+            };
+
+            let value = match self.method {
+                InterpolationMethod::NearestNeighbour => samples[found[0].index].value,
+                InterpolationMethod::InverseDistance { power } => inverse_distance(samples, &found, power),
+            };
+
+            let used = match self.method {
+                InterpolationMethod::NearestNeighbour => &found[..1],
+                InterpolationMethod::InverseDistance { .. } => &found[..],
+            };
+
+            model.set_value(attribute, index, AttributeValue::Real(value))?;
+            audit.record(model, index, used, pass)?;
+            summary.per_pass[pass - 1] += 1;
+        }
+
+        Ok(summary)
+    }
+}
+
+fn inverse_distance(samples: &[Sample], found: &[Neighbour], power: f64) -> f64 {
+    // a sample on the centroid takes the whole weight
+    if let Some(n) = found.iter().find(|n| n.anisotropic < 1e-9) {
+        return samples[n.index].value;
+    }
+
+    let weights: Vec<f64> = found.iter().map(|n| 1.0 / n.anisotropic.powf(power)).collect();
+    let total: f64 = weights.iter().sum();
+
+    found.iter().zip(weights.iter())
+        .map(|(n, w)| samples[n.index].value * w)
+        .sum::<f64>() / total
+}
+
+pub(crate) fn is_candidate(model: &BlockModel, within: Option<&str>, index: usize) -> Result<bool, LegoError> {
+    match within {
+        None => Ok(true),
+        Some(name) => match model.get_value(name, index) {
+            Some(AttributeValue::Flag(flag)) => Ok(flag),
+            _ => Err(LegoError::parameter(format!("block model has no flag attribute named {}", name)))
+        }
+    }
+}
+
+pub(crate) struct AuditAttributes {
+    estimate: String,
+    samples: String,
+    distance: String,
+    pass: String,
+}
+
+impl AuditAttributes {
+    pub(crate) fn prepare(model: &mut BlockModel, attribute: &str) -> Result<AuditAttributes, LegoError> {
+        let audit = AuditAttributes {
+            estimate: attribute.to_string(),
+            samples: format!("{}_samples", attribute),
+            distance: format!("{}_distance", attribute),
+            pass: format!("{}_pass", attribute),
+        };
+
+        model.ensure_attribute(attribute, AttributeKind::Real)?;
+        model.ensure_attribute(&audit.samples, AttributeKind::Integer)?;
+        model.ensure_attribute(&audit.distance, AttributeKind::Real)?;
+        model.ensure_attribute(&audit.pass, AttributeKind::Integer)?;
+
+        Ok(audit)
+    }
+
+    // a rerun into the same attribute must not leave the values of the previous run on the
+    // blocks it cannot estimate
+    pub(crate) fn clear(&self, model: &mut BlockModel, index: usize) -> Result<(), LegoError> {
+        for name in [&self.estimate, &self.samples, &self.distance, &self.pass] {
+            model.clear_value(name, index)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn record(&self, model: &mut BlockModel, index: usize, used: &[Neighbour],
+                         pass: usize) -> Result<(), LegoError> {
+        let distance = used.iter().map(|n| n.euclidean).sum::<f64>() / used.len() as f64;
+
+        model.set_value(&self.samples, index, AttributeValue::Integer(used.len() as i64))?;
+        model.set_value(&self.distance, index, AttributeValue::Real(distance))?;
+        model.set_value(&self.pass, index, AttributeValue::Integer(pass as i64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::block::model::{AttributeValue, BlockModel};
+    use crate::estimation::idw::{DistanceEstimator, InterpolationMethod};
+    use crate::estimation::search::{Sample, SearchEllipsoid, SearchParameters, SearchPass};
+    use crate::lego_tests::{give_me_composite, give_me_cross};
+
+    fn samples() -> Vec<Sample> {
+        vec![Sample::new(5.0, 5.0, 5.0, 1.0, String::from("A")),
+             Sample::new(15.0, 5.0, 5.0, 3.0, String::from("B"))]
+    }
+
+    fn search(range: f64) -> SearchParameters {
+        SearchParameters::new(SearchEllipsoid::isotropic(range),
+                              vec![SearchPass::new(1.0, 2, 8), SearchPass::new(3.0, 1, 8)])
+    }
+
+    #[test]
+    fn inverse_distance_weights() {
+        let mut model = BlockModel::new([0.0; 3], [10.0, 10.0, 10.0], [3, 1, 1]).unwrap();
+        let estimator = DistanceEstimator::new(InterpolationMethod::InverseDistance { power: 2.0 }, search(12.0));
+
+        let summary = estimator.estimate(&mut model, &samples(), "cu", None).unwrap();
+        println!("{}", summary);
+
+        // samples on the centroids of the first two blocks
+        assert_eq!(model.get_real("cu", 0), Some(1.0));
+        assert_eq!(model.get_real("cu", 1), Some(3.0));
+
+        // third block is 10 and 20 meters away: (3 / 100 + 1 / 400) / (1 / 100 + 1 / 400)
+        let expected = (3.0 / 100.0 + 1.0 / 400.0) / (1.0 / 100.0 + 1.0 / 400.0);
+        assert!((model.get_real("cu", 2).unwrap() - expected).abs() < 1e-9);
+        assert_eq!(model.get_value("cu_pass", 2), Some(AttributeValue::Integer(2)));
+        assert_eq!(model.get_value("cu_samples", 2), Some(AttributeValue::Integer(2)));
+        assert_eq!(model.get_real("cu_distance", 2), Some(15.0));
+        assert_eq!(summary.per_pass, vec![2, 1]);
+    }
+
+    #[test]
+    fn rerun_clears_previous_values() {
+        let mut model = BlockModel::new([0.0; 3], [10.0, 10.0, 10.0], [3, 1, 1]).unwrap();
+        let method = InterpolationMethod::InverseDistance { power: 2.0 };
+        DistanceEstimator::new(method, search(12.0)).estimate(&mut model, &samples(), "cu", None).unwrap();
+        assert!(model.get_real("cu", 2).is_some());
+
+        // third block is out of the smaller search
+        let summary = DistanceEstimator::new(method, search(2.0))
+            .estimate(&mut model, &samples(), "cu", None).unwrap();
+        assert_eq!(summary.unestimated, 1);
+        assert_eq!(model.get_real("cu", 0), Some(1.0));
+        for name in ["cu", "cu_samples", "cu_distance", "cu_pass"] {
+            assert_eq!(model.get_value(name, 2), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn nearest_neighbour() {
+        let mut model = BlockModel::new([0.0; 3], [10.0, 10.0, 10.0], [3, 1, 1]).unwrap();
+        let estimator = DistanceEstimator::new(InterpolationMethod::NearestNeighbour, search(12.0));
+
+        estimator.estimate(&mut model, &samples(), "cu", None).unwrap();
+        assert_eq!(model.get_real("cu", 2), Some(3.0));
+        assert_eq!(model.get_value("cu_samples", 2), Some(AttributeValue::Integer(1)));
+    }
+
+    #[test]
+    fn estimate_within_flag() {
+        let mut model = BlockModel::new([0.0; 3], [10.0, 10.0, 10.0], [3, 1, 1]).unwrap();
+        let estimator = DistanceEstimator::new(InterpolationMethod::NearestNeighbour, search(1.0));
+
+        assert!(estimator.estimate(&mut model, &samples(), "cu", Some("ore")).is_err());
+
+        let summary = estimator.estimate(&mut model, &samples(), "cu", None).unwrap();
+        assert_eq!(summary.per_pass, vec![0, 2]);
+        assert_eq!(summary.unestimated, 1);
+        assert_eq!(model.get_real("cu", 2), None);
+    }
+
+    #[test]
+    fn estimate_test_composites() {
+        let cross_object = give_me_cross();
+        let solid = cross_object.build_solid().unwrap();
+        let mut model = BlockModel::new_from_extent(&solid.extent().unwrap(), [20.0, 20.0, 10.0]).unwrap();
+        model.flag_inside_solid(&solid, "ore").unwrap();
+
+        let mut ellipsoid = SearchEllipsoid::new(80.0, 60.0, 20.0);
+        ellipsoid.set_orientation(45.0, 0.0, 0.0);
+        let mut first = SearchPass::new(1.0, 4, 12);
+        first.max_per_hole = Some(3);
+        let search = SearchParameters::new(ellipsoid, vec![first, SearchPass::new(2.0, 2, 12)]);
+
+        let estimator = DistanceEstimator::new(InterpolationMethod::InverseDistance { power: 2.0 }, search);
+        let summary = estimator.estimate(&mut model, &Sample::from_composites(&give_me_composite()),
+                                         "cu", Some("ore")).unwrap();
+        println!("{}", summary);
+
+        assert!(summary.estimated() > 0);
+        assert_eq!(summary.estimated() + summary.unestimated, summary.candidates);
+        for i in 0..model.block_count() {
+            if let Some(AttributeValue::Integer(n)) = model.get_value("cu_samples", i) {
+                assert!(n <= 12);
+            }
+        }
+    }
+}
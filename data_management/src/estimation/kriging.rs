@@ -0,0 +1,353 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use lego_config::error::LegoError;
+
+use crate::block::model::{AttributeKind, AttributeValue, BlockModel};
+use crate::estimation::idw::{AuditAttributes, EstimationSummary, is_candidate};
+use crate::estimation::search::{Neighbour, Sample, SearchParameters};
+use crate::estimation::variogram::VariogramModel;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KrigingType {
+    Ordinary,
+    Simple { mean: f64 },
+}
+
+impl Display for KrigingType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            KrigingType::Ordinary => write!(f, "ordinary"),
+            KrigingType::Simple { mean } => write!(f, "simple with mean {}", mean),
+        }
+    }
+}
+
+// result of one kriging system
+#[derive(Debug, Clone, PartialEq)]
+pub struct KrigingResult {
+    pub estimate: f64,
+    pub weights: Vec<f64>,
+    pub variance: f64,
+    pub slope: f64,
+    pub efficiency: f64,
+}
+
+// Besides the estimate and the audit attributes of the distance estimator, writes the
+// kriging variance, slope of regression and kriging efficiency to <attribute>_kv,
+// <attribute>_slope and <attribute>_ke. Blocks are discretised into the given count of
+// points on each axis.
+#[derive(Debug, Clone)]
+pub struct KrigingEstimator {
+    pub kriging_type: KrigingType,
+    pub variogram: VariogramModel,
+    pub search: SearchParameters,
+    pub discretisation: [usize; 3],
+}
+
+impl KrigingEstimator {
+    pub fn new(kriging_type: KrigingType, variogram: VariogramModel, search: SearchParameters) -> KrigingEstimator {
+        KrigingEstimator {
+            kriging_type,
+            variogram,
+            search,
+            discretisation: [4, 4, 2],
+        }
+    }
+
+    pub fn estimate(&self, model: &mut BlockModel, samples: &[Sample], attribute: &str,
+                    within: Option<&str>) -> Result<EstimationSummary, LegoError> {
+        self.search.validate()?;
+        self.variogram.validate()?;
+        if self.discretisation.contains(&0) {
+            return Err(LegoError::parameter(format!("discretisation has to be positive, got {:?}",
+                                                    self.discretisation)));
+        }
+
+        let audit = AuditAttributes::prepare(model, attribute)?;
+        let (kv, slope, ke) = (format!("{}_kv", attribute), format!("{}_slope", attribute),
+                               format!("{}_ke", attribute));
+        for name in [&kv, &slope, &ke].iter() {
+            model.ensure_attribute(name, AttributeKind::Real)?;
+        }
+
+        let mut summary = EstimationSummary::new(attribute, self.search.passes.len());
+
+        for index in 0..model.block_count() {
+            if !is_candidate(model, within, index)? {
+                continue;
+            }
+            summary.candidates += 1;
+            audit.clear(model, index)?;
+            for name in [&kv, &slope, &ke] {
+                model.clear_value(name, index)?;
+            }
+
+            let (pass, found) = match self.search.search(samples, model.world_centroid(index)) {
+                Some(result) => result,
+                None => {
+                    summary.unestimated += 1;
+                    continue;
+                }
+            };
+
+            let points = self.discretise(model, index);
+            let result = match self.krige(samples, &found, &points) {
+                Some(r) => r,
+                // singular system, e.g. repeated samples
+                None => {
+                    summary.unestimated += 1;
+                    continue;
+                }
+            };
+
+            model.set_value(attribute, index, AttributeValue::Real(result.estimate))?;
+            model.set_value(&kv, index, AttributeValue::Real(result.variance))?;
+            model.set_value(&slope, index, AttributeValue::Real(result.slope))?;
+            model.set_value(&ke, index, AttributeValue::Real(result.efficiency))?;
+            audit.record(model, index, &found, pass)?;
+            summary.per_pass[pass - 1] += 1;
+        }
+
+        Ok(summary)
+    }
+
+    // world coordinates of the points inside the block
+    fn discretise(&self, model: &BlockModel, index: usize) -> Vec<[f64; 3]> {
+        let block = model.get_block(index).unwrap();
+        let [nx, ny, nz] = self.discretisation;
+
+        let mut points: Vec<[f64; 3]> = vec![];
+        for k in 0..nz {
+            for j in 0..ny {
+                for i in 0..nx {
+                    let local = [
+                        block.centroid[0] + block.size[0] * ((i as f64 + 0.5) / nx as f64 - 0.5),
+                        block.centroid[1] + block.size[1] * ((j as f64 + 0.5) / ny as f64 - 0.5),
+                        block.centroid[2] + block.size[2] * ((k as f64 + 0.5) / nz as f64 - 0.5),
+                    ];
+                    points.push(model.to_world(local));
+                }
+            }
+        }
+
+        points
+    }
+
+    pub(crate) fn krige(&self, samples: &[Sample], found: &[Neighbour], points: &[[f64; 3]]) -> Option<KrigingResult> {
+        let n = found.len();
+        let position = |k: usize| {
+            let s = &samples[found[k].index];
+            [s.x, s.y, s.z]
+        };
+        let covariance = |a: [f64; 3], b: [f64; 3]| self.variogram.covariance([b[0] - a[0], b[1] - a[1], b[2] - a[2]]);
+
+        // mean covariance between the block and itself, and the samples and the block
+        let block_block = points.iter()
+            .map(|p| points.iter().map(|q| covariance(*p, *q)).sum::<f64>())
+            .sum::<f64>() / (points.len() * points.len()) as f64;
+        let sample_block: Vec<f64> = (0..n)
+            .map(|i| points.iter().map(|p| covariance(position(i), *p)).sum::<f64>() / points.len() as f64)
+            .collect();
+
+        let size = match self.kriging_type {
+            KrigingType::Ordinary => n + 1,
+            KrigingType::Simple { .. } => n,
+        };
+
+        let mut matrix = vec![vec![0.0; size]; size];
+        let mut right = vec![0.0; size];
+        for (i, row) in matrix.iter_mut().take(n).enumerate() {
+            for (j, cell) in row.iter_mut().take(n).enumerate() {
+                *cell = covariance(position(i), position(j));
+            }
+        }
+        right[..n].copy_from_slice(&sample_block);
+
+        if let KrigingType::Ordinary = self.kriging_type {
+            for row in matrix.iter_mut().take(n) {
+                row[n] = 1.0;
+            }
+            for cell in matrix[n].iter_mut().take(n) {
+                *cell = 1.0;
+            }
+            right[n] = 1.0;
+        }
+
+        let solution = solve(matrix, right)?;
+        let weights = solution[..n].to_vec();
+        let weighted_covariance: f64 = weights.iter().zip(sample_block.iter()).map(|(w, c)| w * c).sum();
+
+        let (estimate, variance, slope) = match self.kriging_type {
+            KrigingType::Ordinary => {
+                let lagrange = solution[n];
+                let estimate = weights.iter().zip(found.iter()).map(|(w, f)| w * samples[f.index].value).sum();
+                let variance = block_block - weighted_covariance - lagrange;
+                (estimate, variance, weighted_covariance / (weighted_covariance - lagrange))
+            }
+            KrigingType::Simple { mean } => {
+                let estimate = mean + weights.iter().zip(found.iter())
+                    .map(|(w, f)| w * (samples[f.index].value - mean)).sum::<f64>();
+                (estimate, block_block - weighted_covariance, 1.0)
+            }
+        };
+
+        Some(KrigingResult {
+            estimate,
+            weights,
+            variance,
+            slope,
+            efficiency: (block_block - variance) / block_block,
+        })
+    }
+}
+
+// gaussian elimination with partial pivoting, none if the matrix is singular
+pub(crate) fn solve(mut matrix: Vec<Vec<f64>>, mut right: Vec<f64>) -> Option<Vec<f64>> {
+    let n = right.len();
+
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|&a, &b| matrix[a][column].abs().partial_cmp(&matrix[b][column].abs()).unwrap())?;
+        if matrix[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        right.swap(column, pivot);
+
+        for row in column + 1..n {
+            let factor = matrix[row][column] / matrix[column][column];
+            if factor == 0.0 {
+                continue;
+            }
+            let (upper, lower) = matrix.split_at_mut(row);
+            for (cell, p) in lower[0][column..].iter_mut().zip(upper[column][column..].iter()) {
+                *cell -= factor * p;
+            }
+            right[row] -= factor * right[column];
+        }
+    }
+
+    let mut solution = vec![0.0; n];
+    for row in (0..n).rev() {
+        let rest: f64 = (row + 1..n).map(|k| matrix[row][k] * solution[k]).sum();
+        solution[row] = (right[row] - rest) / matrix[row][row];
+    }
+
+    Some(solution)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::block::model::BlockModel;
+    use crate::estimation::kriging::{KrigingEstimator, KrigingType, solve};
+    use crate::estimation::search::{Neighbour, Sample, SearchEllipsoid, SearchParameters, SearchPass};
+    use crate::estimation::variogram::{StructureType, VariogramModel, VariogramStructure};
+    use crate::lego_tests::{give_me_composite, give_me_cross};
+
+    fn variogram(nugget: f64) -> VariogramModel {
+        VariogramModel::new(nugget, vec![
+            VariogramStructure::new(StructureType::Spherical, 1.0 - nugget, SearchEllipsoid::isotropic(100.0))])
+    }
+
+    fn search() -> SearchParameters {
+        SearchParameters::new(SearchEllipsoid::isotropic(150.0), vec![SearchPass::new(1.0, 1, 16)])
+    }
+
+    fn neighbours(count: usize) -> Vec<Neighbour> {
+        (0..count).map(|index| Neighbour { index, anisotropic: 0.0, euclidean: 0.0 }).collect()
+    }
+
+    #[test]
+    fn solve_linear_system() {
+        let solution = solve(vec![vec![0.0, 2.0], vec![1.0, 1.0]], vec![4.0, 3.0]).unwrap();
+        assert!((solution[0] - 1.0).abs() < 1e-12 && (solution[1] - 2.0).abs() < 1e-12);
+        assert!(solve(vec![vec![1.0, 1.0], vec![1.0, 1.0]], vec![1.0, 1.0]).is_none());
+    }
+
+    #[test]
+    fn ordinary_kriging_point() {
+        let samples = vec![Sample::new(-10.0, 0.0, 0.0, 1.0, String::from("A")),
+                           Sample::new(10.0, 0.0, 0.0, 3.0, String::from("B"))];
+        let estimator = KrigingEstimator::new(KrigingType::Ordinary, variogram(0.1), search());
+
+        // symmetric samples share the weight
+        let result = estimator.krige(&samples, &neighbours(2), &[[0.0, 0.0, 0.0]]).unwrap();
+        assert!((result.weights[0] - 0.5).abs() < 1e-12);
+        assert!((result.estimate - 2.0).abs() < 1e-12);
+        assert!(result.variance > 0.0);
+        assert!((result.weights.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+
+        // exact interpolator on a sample without nugget
+        let exact = KrigingEstimator::new(KrigingType::Ordinary, variogram(0.0), search());
+        let result = exact.krige(&samples, &neighbours(2), &[[10.0, 0.0, 0.0]]).unwrap();
+        assert!((result.estimate - 3.0).abs() < 1e-9);
+        assert!(result.variance.abs() < 1e-9);
+        assert!((result.slope - 1.0).abs() < 1e-9);
+        assert!((result.efficiency - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rerun_clears_previous_values() {
+        let mut model = BlockModel::new([0.0; 3], [10.0, 10.0, 10.0], [3, 1, 1]).unwrap();
+        let samples = vec![Sample::new(5.0, 5.0, 5.0, 1.0, String::from("A")),
+                           Sample::new(15.0, 5.0, 5.0, 3.0, String::from("B"))];
+        KrigingEstimator::new(KrigingType::Ordinary, variogram(0.1), search())
+            .estimate(&mut model, &samples, "cu", None).unwrap();
+        assert!(model.get_real("cu_kv", 2).is_some());
+
+        // third block is out of the smaller search
+        let small = SearchParameters::new(SearchEllipsoid::isotropic(2.0), vec![SearchPass::new(1.0, 1, 16)]);
+        let summary = KrigingEstimator::new(KrigingType::Ordinary, variogram(0.1), small)
+            .estimate(&mut model, &samples, "cu", None).unwrap();
+        assert_eq!(summary.unestimated, 1);
+        assert!(model.get_real("cu", 0).is_some());
+        for name in ["cu", "cu_kv", "cu_slope", "cu_ke", "cu_samples", "cu_distance", "cu_pass"] {
+            assert_eq!(model.get_value(name, 2), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn simple_kriging_goes_to_mean() {
+        let samples = vec![Sample::new(0.0, 0.0, 0.0, 5.0, String::from("A"))];
+        let estimator = KrigingEstimator::new(KrigingType::Simple { mean: 1.0 }, variogram(0.0), search());
+
+        // out of the range the sample has no weight
+        let result = estimator.krige(&samples, &neighbours(1), &[[120.0, 0.0, 0.0]]).unwrap();
+        assert!((result.estimate - 1.0).abs() < 1e-12);
+        assert!((result.variance - 1.0).abs() < 1e-12);
+        assert!(result.efficiency.abs() < 1e-12);
+    }
+
+    #[test]
+    fn krige_test_composites() {
+        let cross_object = give_me_cross();
+        let solid = cross_object.build_solid().unwrap();
+        let mut model = BlockModel::new_from_extent(&solid.extent().unwrap(), [20.0, 20.0, 10.0]).unwrap();
+        model.flag_inside_solid(&solid, "ore").unwrap();
+
+        let mut first = SearchPass::new(1.0, 4, 12);
+        first.max_per_hole = Some(3);
+        let search = SearchParameters::new(SearchEllipsoid::new(80.0, 60.0, 20.0),
+                                           vec![first, SearchPass::new(2.0, 2, 12)]);
+        let variogram = VariogramModel::new(0.5, vec![
+            VariogramStructure::new(StructureType::Spherical, 1.5, SearchEllipsoid::new(60.0, 40.0, 15.0)),
+            VariogramStructure::new(StructureType::Exponential, 1.0, SearchEllipsoid::isotropic(150.0)),
+        ]);
+
+        let samples = Sample::from_composites(&give_me_composite());
+        let estimator = KrigingEstimator::new(KrigingType::Ordinary, variogram, search);
+        let summary = estimator.estimate(&mut model, &samples, "cu", Some("ore")).unwrap();
+        println!("{}", summary);
+
+        assert!(summary.estimated() > 0);
+        let (min, max) = samples.iter().fold((f64::MAX, f64::MIN), |(a, b), s| (a.min(s.value), b.max(s.value)));
+        for i in 0..model.block_count() {
+            if let Some(cu) = model.get_real("cu", i) {
+                assert!(cu >= min - 1.0 && cu <= max + 1.0);
+                assert!(model.get_real("cu_kv", i).unwrap() >= -1e-9);
+                assert!(model.get_real("cu_ke", i).unwrap() <= 1.0 + 1e-9);
+            }
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use lego_config::error::LegoError;
+use serde::{Deserialize, Serialize};
+
+use crate::drillhole::database::DrillholeDatabase;
+use crate::drillhole::desurvey::{DesurveyedHole, DesurveyMethod};
+use crate::str::composite_reader::CompositeObject;
+
+// point data used by the estimators
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub value: f64,
+    pub hole: String,
+    // from and to depths of the sample in its hole, None for the points without an interval
+    pub interval: Option<(f64, f64)>,
+}
+
+impl Sample {
+    pub fn new(x: f64, y: f64, z: f64, value: f64, hole: String) -> Sample {
+        Sample { x, y, z, value, hole, interval: None }
+    }
+
+    pub fn with_interval(mut self, from: f64, to: f64) -> Sample {
+        self.interval = Some((from, to));
+        self
+    }
+
+    // composites below the minimum coverage and the ones without any sampled length are
+    // left out
+    pub fn from_composites(composites: &CompositeObject) -> Vec<Sample> {
+        composites.data.iter()
+            .filter(|c| c.is_accepted() && c.cut_taken > 0.0)
+            .map(|c| Sample::new(c.coordinate.x_coord, c.coordinate.y_coord, c.coordinate.z_coord,
+                                 c.tenor, c.drill_no.clone()).with_interval(c.cut_from, c.cut_end))
+            .collect()
+    }
+
+    // raw samples placed on the middle of their intervals, holes without collar are left out
+    pub fn from_raw_samples(database: &DrillholeDatabase, method: DesurveyMethod) -> Vec<Sample> {
+        let mut samples: Vec<Sample> = vec![];
+
+        for hole in database.drillholes() {
+            if let Some(desurveyed) = DesurveyedHole::new(hole, method) {
+                for r in hole.raw_samples.iter() {
+                    let mid = desurveyed.position_at((r.start + r.end) / 2.0);
+                    samples.push(Sample::new(mid.x_coord, mid.y_coord, mid.z_coord, r.percent,
+                                             hole.hole_id.clone()).with_interval(r.start, r.end));
+                }
+            }
+        }
+
+        samples
+    }
+}
+
+// Bearing is clockwise from north and plunge is upwards, both for the major axis. Dip
+// rotates the semi major and minor axes around the major one. Angles are in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SearchEllipsoid {
+    pub major: f64,
+    pub semi_major: f64,
+    pub minor: f64,
+    pub bearing: f64,
+    pub plunge: f64,
+    pub dip: f64,
+}
+
+impl SearchEllipsoid {
+    pub fn new(major: f64, semi_major: f64, minor: f64) -> SearchEllipsoid {
+        SearchEllipsoid {
+            major,
+            semi_major,
+            minor,
+            bearing: 0.0,
+            plunge: 0.0,
+            dip: 0.0,
+        }
+    }
+
+    pub fn isotropic(range: f64) -> SearchEllipsoid {
+        SearchEllipsoid::new(range, range, range)
+    }
+
+    pub fn set_orientation(&mut self, bearing: f64, plunge: f64, dip: f64) {
+        self.bearing = bearing;
+        self.plunge = plunge;
+        self.dip = dip;
+    }
+
+    pub fn scaled(&self, factor: f64) -> SearchEllipsoid {
+        SearchEllipsoid {
+            major: self.major * factor,
+            semi_major: self.semi_major * factor,
+            minor: self.minor * factor,
+            ..*self
+        }
+    }
+
+    // unit vectors of the major, semi major and minor axes
+    pub(crate) fn axes(&self) -> [[f64; 3]; 3] {
+        let (sin_b, cos_b) = self.bearing.to_radians().sin_cos();
+        let (sin_p, cos_p) = self.plunge.to_radians().sin_cos();
+        let (sin_d, cos_d) = self.dip.to_radians().sin_cos();
+
+        let major = [sin_b * cos_p, cos_b * cos_p, sin_p];
+        let semi = [cos_b, -sin_b, 0.0];
+        // semi x major
+        let minor = [semi[1] * major[2] - semi[2] * major[1],
+            semi[2] * major[0] - semi[0] * major[2],
+            semi[0] * major[1] - semi[1] * major[0]];
+
+        let rotated_semi = [semi[0] * cos_d + minor[0] * sin_d, semi[1] * cos_d + minor[1] * sin_d,
+            semi[2] * cos_d + minor[2] * sin_d];
+        let rotated_minor = [minor[0] * cos_d - semi[0] * sin_d, minor[1] * cos_d - semi[1] * sin_d,
+            minor[2] * cos_d - semi[2] * sin_d];
+
+        [major, rotated_semi, rotated_minor]
+    }
+
+    // offset expressed along the axes of the ellipsoid
+    pub(crate) fn rotate(&self, offset: [f64; 3]) -> [f64; 3] {
+        let axes = self.axes();
+        let project = |a: &[f64; 3]| a[0] * offset[0] + a[1] * offset[1] + a[2] * offset[2];
+        [project(&axes[0]), project(&axes[1]), project(&axes[2])]
+    }
+
+    // 1 on the surface of the ellipsoid
+    pub fn normalized_distance(&self, offset: [f64; 3]) -> f64 {
+        let r = self.rotate(offset);
+        ((r[0] / self.major).powi(2) + (r[1] / self.semi_major).powi(2) + (r[2] / self.minor).powi(2)).sqrt()
+    }
+
+    // distance stretched to the major axis, so anisotropy is removed for the weights
+    pub fn anisotropic_distance(&self, offset: [f64; 3]) -> f64 {
+        self.normalized_distance(offset) * self.major
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), LegoError> {
+        if self.major <= 0.0 || self.semi_major <= 0.0 || self.minor <= 0.0 {
+            return Err(LegoError::parameter(format!("search ranges have to be positive, got {} {} {}",
+                                                    self.major, self.semi_major, self.minor)));
+        }
+        Ok(())
+    }
+}
+
+impl Display for SearchEllipsoid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "ranges : {} {} {} \n\
+                   bearing : {} \n\
+                   plunge : {} \n\
+                   dip : {}", self.major, self.semi_major, self.minor, self.bearing, self.plunge, self.dip)
+    }
+}
+
+// one search of a multi pass estimation, the ellipsoid is multiplied by the factor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchPass {
+    pub factor: f64,
+    pub min_samples: usize,
+    pub max_samples: usize,
+    pub max_per_hole: Option<usize>,
+}
+
+impl SearchPass {
+    pub fn new(factor: f64, min_samples: usize, max_samples: usize) -> SearchPass {
+        SearchPass {
+            factor,
+            min_samples,
+            max_samples,
+            max_per_hole: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchParameters {
+    pub ellipsoid: SearchEllipsoid,
+    pub passes: Vec<SearchPass>,
+}
+
+impl SearchParameters {
+    pub fn new(ellipsoid: SearchEllipsoid, passes: Vec<SearchPass>) -> SearchParameters {
+        SearchParameters { ellipsoid, passes }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), LegoError> {
+        self.ellipsoid.validate()?;
+
+        if self.passes.is_empty() {
+            return Err(LegoError::parameter("at least one search pass is needed"));
+        }
+
+        for (i, p) in self.passes.iter().enumerate() {
+            if p.factor <= 0.0 || p.min_samples == 0 || p.min_samples > p.max_samples ||
+                p.max_per_hole == Some(0) {
+                return Err(LegoError::parameter(format!("search pass {} is not valid : {:?}", i + 1, p)));
+            }
+        }
+
+        Ok(())
+    }
+
+    // first pass finding enough samples. returns the pass number starting from 1 and the
+    // found samples ordered by anisotropic distance.
+    pub(crate) fn search(&self, samples: &[Sample], point: [f64; 3]) -> Option<(usize, Vec<Neighbour>)> {
+        for (i, pass) in self.passes.iter().enumerate() {
+            let found = search_pass(samples, point, &self.ellipsoid.scaled(pass.factor), pass);
+            if found.len() >= pass.min_samples {
+                return Some((i + 1, found));
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Neighbour {
+    pub(crate) index: usize,
+    pub(crate) anisotropic: f64,
+    pub(crate) euclidean: f64,
+}
+
+fn search_pass(samples: &[Sample], point: [f64; 3], ellipsoid: &SearchEllipsoid, pass: &SearchPass) -> Vec<Neighbour> {
+    let mut candidates: Vec<Neighbour> = samples.iter().enumerate()
+        .filter_map(|(index, s)| {
+            let offset = [s.x - point[0], s.y - point[1], s.z - point[2]];
+            let normalized = ellipsoid.normalized_distance(offset);
+            if normalized > 1.0 {
+                return None;
+            }
+
+            Some(Neighbour {
+                index,
+                anisotropic: normalized * ellipsoid.major,
+                euclidean: (offset[0].powi(2) + offset[1].powi(2) + offset[2].powi(2)).sqrt(),
+            })
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.anisotropic.partial_cmp(&b.anisotropic).unwrap());
+
+    let mut per_hole: HashMap<&str, usize> = HashMap::new();
+    let mut found: Vec<Neighbour> = vec![];
+
+    for c in candidates {
+        if found.len() == pass.max_samples {
+            break;
+        }
+
+        let used = per_hole.entry(samples[c.index].hole.as_str()).or_insert(0);
+        if pass.max_per_hole.is_some_and(|limit| *used >= limit) {
+            continue;
+        }
+
+        *used += 1;
+        found.push(c);
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::estimation::search::{Sample, SearchEllipsoid, SearchParameters, SearchPass};
+    use crate::lego_tests::give_me_composite;
+
+    fn sample(x: f64, y: f64, hole: &str) -> Sample {
+        Sample::new(x, y, 0.0, 1.0, hole.to_string())
+    }
+
+    #[test]
+    fn samples_of_accepted_composites() {
+        let composites = give_me_composite();
+        let samples = Sample::from_composites(&composites);
+
+        // second string of the test file is below the minimum coverage
+        let rejected = composites.iter().filter(|c| !c.is_accepted()).count();
+        assert_eq!(rejected, 26);
+        assert_eq!(samples.len(), composites.len() - rejected);
+        assert!(samples.iter().all(|s| composites.iter()
+            .any(|c| c.is_accepted() && c.drill_no() == s.hole && c.tenor() == s.value)));
+    }
+
+    #[test]
+    fn rotated_ellipsoid() {
+        let mut ellipsoid = SearchEllipsoid::new(100.0, 50.0, 10.0);
+
+        // major axis looks to north without rotation
+        assert!((ellipsoid.normalized_distance([0.0, 100.0, 0.0]) - 1.0).abs() < 1e-9);
+        assert!((ellipsoid.normalized_distance([50.0, 0.0, 0.0]) - 1.0).abs() < 1e-9);
+        assert!((ellipsoid.normalized_distance([0.0, 0.0, 10.0]) - 1.0).abs() < 1e-9);
+
+        ellipsoid.set_orientation(90.0, 0.0, 0.0);
+        assert!((ellipsoid.normalized_distance([100.0, 0.0, 0.0]) - 1.0).abs() < 1e-9);
+
+        ellipsoid.set_orientation(0.0, 90.0, 0.0);
+        assert!((ellipsoid.normalized_distance([0.0, 0.0, 100.0]) - 1.0).abs() < 1e-9);
+
+        ellipsoid.set_orientation(0.0, 0.0, 90.0);
+        assert!((ellipsoid.normalized_distance([0.0, 0.0, 50.0]) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn passes_and_hole_limit() {
+        let samples = vec![sample(1.0, 0.0, "A"), sample(2.0, 0.0, "A"), sample(3.0, 0.0, "A"),
+                           sample(0.0, 30.0, "B")];
+
+        let mut first = SearchPass::new(1.0, 2, 4);
+        first.max_per_hole = Some(1);
+        let parameters = SearchParameters::new(SearchEllipsoid::isotropic(10.0),
+                                               vec![first, SearchPass::new(4.0, 2, 3)]);
+        assert!(parameters.validate().is_ok());
+
+        // only one sample of A can be used in the first pass, B is out of it
+        let (pass, found) = parameters.search(&samples, [0.0, 0.0, 0.0]).unwrap();
+        assert_eq!(pass, 2);
+        assert_eq!(found.iter().map(|n| n.index).collect::<Vec<usize>>(), vec![0, 1, 2]);
+
+        assert!(parameters.search(&samples, [500.0, 0.0, 0.0]).is_none());
+    }
+}
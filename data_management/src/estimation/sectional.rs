@@ -0,0 +1,363 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use lego_config::error::LegoError;
+
+use crate::str::composite_reader::{Composite, CompositeObject};
+use crate::str::cross_reader::cross::Cross;
+use crate::str::cross_reader::cross_main::CrossObject;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VolumeMethod {
+    // mean of the two end areas times the distance
+    EndArea,
+    // truncated pyramid, better when successive areas differ a lot
+    Frustum,
+}
+
+impl Display for VolumeMethod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            VolumeMethod::EndArea => write!(f, "end area"),
+            VolumeMethod::Frustum => write!(f, "frustum"),
+        }
+    }
+}
+
+// one cross section with the composites falling inside of it
+#[derive(Debug, Clone)]
+pub struct SectionalLevel {
+    pub group_no: i32,
+    pub z: f64,
+    pub area: f64,
+    pub sample_count: usize,
+    pub sample_length: f64,
+    // length weighted, none if there is no composite inside the cross
+    pub grade: Option<f64>,
+}
+
+// volume between two successive levels
+#[derive(Debug, Clone)]
+pub struct SectionalSlab {
+    pub from_z: f64,
+    pub to_z: f64,
+    pub volume: f64,
+    pub grade: Option<f64>,
+    pub tonnes: f64,
+    pub metal: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SectionalEstimate {
+    pub mining_type: String,
+    pub density: f64,
+    pub method: VolumeMethod,
+    pub levels: Vec<SectionalLevel>,
+    pub slabs: Vec<SectionalSlab>,
+    pub volume: f64,
+    pub tonnes: f64,
+    pub grade: Option<f64>,
+    pub metal: f64,
+}
+
+impl SectionalEstimate {
+    pub fn new(crosses: &CrossObject, composites: &CompositeObject, density: f64,
+               method: VolumeMethod) -> Result<SectionalEstimate, LegoError> {
+        estimate(&crosses.data, &composites.data, crosses.info.get_mining_type(), density, method)
+    }
+}
+
+pub(crate) fn estimate(crosses: &[Cross], composites: &[Composite], mining_type: &str, density: f64,
+                       method: VolumeMethod) -> Result<SectionalEstimate, LegoError> {
+    if crosses.len() < 2 {
+        return Err(LegoError::geometry(format!("at least two crosses are needed for a sectional estimate, got {}",
+                                               crosses.len())));
+    }
+
+    if !density.is_finite() || density <= 0.0 {
+        return Err(LegoError::parameter(format!("density has to be positive, got {}", density)));
+    }
+
+    let mut levels: Vec<SectionalLevel> = crosses.iter()
+        .map(|c| SectionalLevel {
+            group_no: c.group_no,
+            z: level_z(c),
+            area: polygon_area(c),
+            sample_count: 0,
+            sample_length: 0.0,
+            grade: None,
+        })
+        .collect();
+    levels.sort_by(|a, b| a.z.total_cmp(&b.z));
+
+    let zs: Vec<f64> = levels.iter().map(|l| l.z).collect();
+    let bounds = level_bounds(&zs);
+
+    for (level, (lower, upper)) in levels.iter_mut().zip(bounds.iter()) {
+        let cross = crosses.iter().find(|c| c.group_no == level.group_no).unwrap();
+        let mut weighted = 0.0;
+
+        // rejected composites are below the minimum coverage
+        for c in composites.iter().filter(|c| c.is_accepted() && c.cut_taken > 0.0) {
+            let p = &c.coordinate;
+            if p.z_coord >= *lower && p.z_coord < *upper && contains_2d(cross, p.x_coord, p.y_coord) {
+                level.sample_count += 1;
+                level.sample_length += c.cut_taken;
+                weighted += c.tenor * c.cut_taken;
+            }
+        }
+
+        if level.sample_length > 0.0 {
+            level.grade = Some(weighted / level.sample_length);
+        }
+    }
+
+    let factor = metal_factor(mining_type);
+    let slabs: Vec<SectionalSlab> = levels.windows(2).map(|pair| {
+        let (bottom, top) = (&pair[0], &pair[1]);
+        let height = top.z - bottom.z;
+
+        let volume = match method {
+            VolumeMethod::EndArea => height * (bottom.area + top.area) / 2.0,
+            VolumeMethod::Frustum => height * (bottom.area + top.area + (bottom.area * top.area).sqrt()) / 3.0,
+        };
+
+        // area weighted grade of the ends which have samples
+        let graded: Vec<(f64, f64)> = [bottom, top].iter()
+            .filter_map(|l| l.grade.map(|g| (l.area, g)))
+            .collect();
+        let graded_area: f64 = graded.iter().map(|(a, _)| a).sum();
+        let grade = if graded_area > 0.0 {
+            Some(graded.iter().map(|(a, g)| a * g).sum::<f64>() / graded_area)
+        } else {
+            None
+        };
+
+        let tonnes = volume * density;
+        SectionalSlab {
+            from_z: bottom.z,
+            to_z: top.z,
+            volume,
+            grade,
+            tonnes,
+            metal: grade.map_or(0.0, |g| tonnes * g * factor),
+        }
+    }).collect();
+
+    let volume = slabs.iter().map(|s| s.volume).sum();
+    let tonnes = slabs.iter().map(|s| s.tonnes).sum();
+    let metal = slabs.iter().map(|s| s.metal).sum();
+    let graded_tonnes: f64 = slabs.iter().filter(|s| s.grade.is_some()).map(|s| s.tonnes).sum();
+    let grade = if graded_tonnes > 0.0 { Some(metal / factor / graded_tonnes) } else { None };
+
+    Ok(SectionalEstimate {
+        mining_type: mining_type.to_string(),
+        density,
+        method,
+        levels,
+        slabs,
+        volume,
+        tonnes,
+        grade,
+        metal,
+    })
+}
+
+// crosses are digitized on levels, small z differences between vertices are ignored
+pub(crate) fn level_z(cross: &Cross) -> f64 {
+    cross.coordinate.iter().map(|v| v.z_coord).sum::<f64>() / cross.coordinate.len() as f64
+}
+
+// every level reaches halfway to its neighbours, levels have to be sorted
+pub(crate) fn level_bounds(zs: &[f64]) -> Vec<(f64, f64)> {
+    let count = zs.len();
+    if count < 2 {
+        return zs.iter().map(|_| (f64::NEG_INFINITY, f64::INFINITY)).collect();
+    }
+
+    (0..count).map(|i| {
+        let lower = if i == 0 {
+            zs[0] - (zs[1] - zs[0]) / 2.0
+        } else {
+            (zs[i - 1] + zs[i]) / 2.0
+        };
+        let upper = if i == count - 1 {
+            zs[i] + (zs[i] - zs[i - 1]) / 2.0
+        } else {
+            (zs[i] + zs[i + 1]) / 2.0
+        };
+        (lower, upper)
+    }).collect()
+}
+
+// grades of precious metals are in g/t, the others in percent
+pub(crate) fn metal_factor(mining_type: &str) -> f64 {
+    match mining_type.to_lowercase().as_str() {
+        "au" | "ag" | "pt" | "pd" => 1.0,
+        _ => 0.01,
+    }
+}
+
+pub(crate) fn metal_unit(mining_type: &str) -> &'static str {
+    if metal_factor(mining_type) == 1.0 { "g" } else { "t" }
+}
+
+pub(crate) fn grade_unit(mining_type: &str) -> &'static str {
+    if metal_factor(mining_type) == 1.0 { "g/t" } else { "%" }
+}
+
+fn polygon_area(cross: &Cross) -> f64 {
+    let points = &cross.coordinate;
+    let mut area = 0.0;
+    for k in 0..points.len() {
+        let (p, q) = (&points[k], &points[(k + 1) % points.len()]);
+        area += p.x_coord * q.y_coord - q.x_coord * p.y_coord;
+    }
+    (area / 2.0).abs()
+}
+
+// ray casting on the xy plane
+pub(crate) fn contains_2d(cross: &Cross, x: f64, y: f64) -> bool {
+    let points = &cross.coordinate;
+    let mut inside = false;
+    let mut j = points.len() - 1;
+
+    for i in 0..points.len() {
+        let (a, b) = (&points[i], &points[j]);
+        if (a.y_coord > y) != (b.y_coord > y) &&
+            x < (b.x_coord - a.x_coord) * (y - a.y_coord) / (b.y_coord - a.y_coord) + a.x_coord {
+            inside = !inside;
+        }
+        j = i;
+    }
+
+    inside
+}
+
+fn format_grade(grade: Option<f64>) -> String {
+    grade.map_or(String::from("-"), |g| format!("{:.3}", g))
+}
+
+impl Display for SectionalEstimate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "mining type : {} \n\
+                     density : {} \n\
+                     method : {}", self.mining_type, self.density, self.method)?;
+
+        writeln!(f, "{:>10} {:>14} {:>8} {:>10} {:>10}", "level z", "area", "samples", "length", "grade")?;
+        for l in self.levels.iter() {
+            writeln!(f, "{:>10.2} {:>14.2} {:>8} {:>10.2} {:>10}", l.z, l.area, l.sample_count,
+                     l.sample_length, format_grade(l.grade))?;
+        }
+
+        let metal = format!("metal ({})", metal_unit(&self.mining_type));
+        writeln!(f, "{:>10} {:>10} {:>16} {:>16} {:>10} {:>16}", "from z", "to z", "volume", "tonnes",
+                 "grade", metal)?;
+        for s in self.slabs.iter() {
+            writeln!(f, "{:>10.2} {:>10.2} {:>16.2} {:>16.2} {:>10} {:>16.2}", s.from_z, s.to_z, s.volume,
+                     s.tonnes, format_grade(s.grade), s.metal)?;
+        }
+
+        write!(f, "{:>10} {:>10} {:>16.2} {:>16.2} {:>10} {:>16.2}", "total", "", self.volume, self.tonnes,
+               format_grade(self.grade), self.metal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lego_config::error::LegoError;
+
+    use crate::estimation::sectional::{estimate, SectionalEstimate, VolumeMethod};
+    use crate::lego_tests::{give_me_composite, give_me_cross};
+    use crate::str::composite_reader::{Composite, CompositeCoordinate};
+    use crate::str::cross_reader::coordinate::CrossCoordinate3d;
+    use crate::str::cross_reader::cross::Cross;
+
+    fn square(group_no: i32, z: f64, size: f64) -> Cross {
+        let corners = [(0.0, 0.0), (size, 0.0), (size, size), (0.0, size), (0.0, 0.0)];
+        let coordinates = corners.iter().enumerate()
+            .map(|(i, &(x, y))| CrossCoordinate3d::new(x, y, z, i as i32 + 1))
+            .collect();
+        Cross::new(group_no, coordinates)
+    }
+
+    fn composite(x: f64, y: f64, z: f64, tenor: f64, length: f64) -> Composite {
+        Composite::new(1, tenor, String::from("S-1"), 0.0, length, length,
+                       CompositeCoordinate::new(x, y, z))
+    }
+
+    #[test]
+    fn end_area_and_frustum() {
+        let crosses = [square(1, 0.0, 10.0), square(2, 10.0, 20.0)];
+        let composites = [
+            composite(5.0, 5.0, 1.0, 1.0, 2.0),
+            composite(5.0, 5.0, -1.0, 2.0, 2.0),
+            composite(15.0, 15.0, 9.0, 0.5, 1.0),
+            // out of the polygon of its level
+            composite(15.0, 15.0, 1.0, 9.0, 1.0),
+        ];
+
+        let end_area = estimate(&crosses, &composites, "cu", 2.5, VolumeMethod::EndArea).unwrap();
+        println!("{}", end_area);
+
+        assert!((end_area.levels[0].area - 100.0).abs() < 1e-9);
+        assert!((end_area.levels[0].grade.unwrap() - 1.5).abs() < 1e-9);
+        assert_eq!(end_area.levels[1].sample_count, 1);
+        assert!((end_area.volume - 2500.0).abs() < 1e-9);
+        assert!((end_area.tonnes - 6250.0).abs() < 1e-9);
+
+        // area weighted grade of both ends: (100 * 1.5 + 400 * 0.5) / 500
+        assert!((end_area.grade.unwrap() - 0.7).abs() < 1e-9);
+        assert!((end_area.metal - 6250.0 * 0.7 / 100.0).abs() < 1e-9);
+
+        let frustum = estimate(&crosses, &composites, "cu", 2.5, VolumeMethod::Frustum).unwrap();
+        assert!((frustum.volume - 7000.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn gold_metal_is_in_grams() {
+        let crosses = [square(1, 0.0, 10.0), square(2, 10.0, 10.0)];
+        let composites = [composite(5.0, 5.0, 5.0, 2.0, 1.0)];
+
+        let result = estimate(&crosses, &composites, "au", 2.0, VolumeMethod::EndArea).unwrap();
+        assert!((result.metal - 2000.0 * 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn skip_rejected_composites() {
+        let crosses = [square(1, 0.0, 10.0), square(2, 10.0, 10.0)];
+        let mut rejected = composite(5.0, 5.0, 1.0, 9.0, 2.0);
+        rejected.group_no = 2;
+        let composites = [composite(5.0, 5.0, 0.0, 1.0, 2.0), rejected];
+
+        let result = estimate(&crosses, &composites, "cu", 2.5, VolumeMethod::EndArea).unwrap();
+        assert_eq!(result.levels[0].sample_count, 1);
+        assert_eq!(result.levels[0].grade, Some(1.0));
+        assert_eq!(result.grade, Some(1.0));
+    }
+
+    #[test]
+    fn need_two_crosses() {
+        assert!(matches!(estimate(&[square(1, 0.0, 10.0)], &[], "cu", 2.5, VolumeMethod::EndArea),
+                         Err(LegoError::Geometry(_))));
+    }
+
+    #[test]
+    fn wrong_density() {
+        let crosses = [square(1, 0.0, 10.0), square(2, 10.0, 10.0)];
+        assert!(estimate(&crosses, &[], "cu", f64::NAN, VolumeMethod::EndArea).is_err());
+        assert!(matches!(estimate(&crosses, &[], "cu", 0.0, VolumeMethod::EndArea), Err(LegoError::Parameter(_))));
+    }
+
+    #[test]
+    fn estimate_test_data() {
+        let result = SectionalEstimate::new(&give_me_cross(), &give_me_composite(), 2.7,
+                                            VolumeMethod::Frustum).unwrap();
+        println!("{}", result);
+
+        assert_eq!(result.slabs.len(), result.levels.len() - 1);
+        assert!(result.volume > 0.0);
+        assert!(result.levels.iter().any(|l| l.sample_count > 0));
+        assert!(result.grade.is_some());
+    }
+}
@@ -0,0 +1,186 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs;
+
+use lego_config::error::LegoError;
+use serde::{Deserialize, Serialize};
+
+use crate::estimation::search::SearchEllipsoid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StructureType {
+    Spherical,
+    // ranges are practical ranges, where 95% of the sill is reached
+    Exponential,
+    Gaussian,
+}
+
+impl StructureType {
+    // value of the unit structure at a lag normalized by the range
+    pub(crate) fn unit_gamma(&self, h: f64) -> f64 {
+        match self {
+            StructureType::Spherical => if h >= 1.0 { 1.0 } else { 1.5 * h - 0.5 * h.powi(3) },
+            StructureType::Exponential => 1.0 - (-3.0 * h).exp(),
+            StructureType::Gaussian => 1.0 - (-3.0 * h * h).exp(),
+        }
+    }
+}
+
+impl Display for StructureType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StructureType::Spherical => write!(f, "spherical"),
+            StructureType::Exponential => write!(f, "exponential"),
+            StructureType::Gaussian => write!(f, "gaussian"),
+        }
+    }
+}
+
+// one nested structure, ranges and orientation are kept in an ellipsoid
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VariogramStructure {
+    pub structure_type: StructureType,
+    pub sill: f64,
+    pub ranges: SearchEllipsoid,
+}
+
+impl VariogramStructure {
+    pub fn new(structure_type: StructureType, sill: f64, ranges: SearchEllipsoid) -> VariogramStructure {
+        VariogramStructure { structure_type, sill, ranges }
+    }
+
+    pub fn gamma(&self, offset: [f64; 3]) -> f64 {
+        self.sill * self.structure_type.unit_gamma(self.ranges.normalized_distance(offset))
+    }
+}
+
+// kept in toml files, so a fitted model can be given to the estimators later
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VariogramModel {
+    pub nugget: f64,
+    pub structures: Vec<VariogramStructure>,
+}
+
+impl VariogramModel {
+    pub fn new(nugget: f64, structures: Vec<VariogramStructure>) -> VariogramModel {
+        VariogramModel { nugget, structures }
+    }
+
+    pub fn sill(&self) -> f64 {
+        self.nugget + self.structures.iter().map(|s| s.sill).sum::<f64>()
+    }
+
+    // nugget jumps in right after the origin
+    pub fn gamma(&self, offset: [f64; 3]) -> f64 {
+        if offset.iter().all(|o| o.abs() < 1e-9) {
+            return 0.0;
+        }
+
+        self.nugget + self.structures.iter().map(|s| s.gamma(offset)).sum::<f64>()
+    }
+
+    pub fn covariance(&self, offset: [f64; 3]) -> f64 {
+        self.sill() - self.gamma(offset)
+    }
+
+    pub fn to_toml(&self) -> Result<String, LegoError> {
+        toml::to_string(self).map_err(|e| LegoError::parameter(format!("variogram cannot be written as toml : {}", e)))
+    }
+
+    pub fn from_toml(text: &str) -> Result<VariogramModel, LegoError> {
+        VariogramModel::parse_toml(text, "toml text")
+    }
+
+    pub fn write_toml(&self, path: &str) -> Result<(), LegoError> {
+        fs::write(path, self.to_toml()?).map_err(|e| LegoError::io(path, e))
+    }
+
+    pub fn read_toml(path: &str) -> Result<VariogramModel, LegoError> {
+        let text = fs::read_to_string(path).map_err(|e| LegoError::io(path, e))?;
+        VariogramModel::parse_toml(&text, path)
+    }
+
+    // source is the path of the file, or a name of the text
+    fn parse_toml(text: &str, source: &str) -> Result<VariogramModel, LegoError> {
+        let model: VariogramModel = toml::from_str(text)
+            .map_err(|e| LegoError::Config { path: source.to_string(), message: e.to_string() })?;
+        model.validate()?;
+        Ok(model)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), LegoError> {
+        if self.nugget < 0.0 || self.structures.iter().any(|s| s.sill < 0.0) {
+            return Err(LegoError::parameter("nugget and sills of the variogram cannot be negative"));
+        }
+
+        if self.sill() <= 0.0 {
+            return Err(LegoError::parameter("total sill of the variogram has to be positive"));
+        }
+
+        for s in self.structures.iter() {
+            s.ranges.validate()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for VariogramModel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "nugget : {}", self.nugget)?;
+        for (i, s) in self.structures.iter().enumerate() {
+            write!(f, " \nstructure {} : {} sill {} ranges {} {} {} angles {} {} {}", i + 1, s.structure_type,
+                   s.sill, s.ranges.major, s.ranges.semi_major, s.ranges.minor, s.ranges.bearing,
+                   s.ranges.plunge, s.ranges.dip)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lego_config::error::LegoError;
+
+    use crate::estimation::search::SearchEllipsoid;
+    use crate::estimation::variogram::{StructureType, VariogramModel, VariogramStructure};
+
+    #[test]
+    fn nested_structures() {
+        let model = VariogramModel::new(0.2, vec![
+            VariogramStructure::new(StructureType::Spherical, 0.5, SearchEllipsoid::new(50.0, 25.0, 10.0)),
+            VariogramStructure::new(StructureType::Exponential, 0.3, SearchEllipsoid::isotropic(200.0)),
+        ]);
+
+        assert!((model.sill() - 1.0).abs() < 1e-12);
+        assert_eq!(model.gamma([0.0, 0.0, 0.0]), 0.0);
+        assert!((model.covariance([0.0, 0.0, 0.0]) - 1.0).abs() < 1e-12);
+
+        // spherical structure reaches its sill on the range
+        let along_major = model.gamma([0.0, 50.0, 0.0]);
+        assert!((along_major - (0.2 + 0.5 + 0.3 * (1.0 - (-0.75f64).exp()))).abs() < 1e-12);
+        assert!(model.gamma([0.0, 0.0, 10.0]) > model.gamma([0.0, 10.0, 0.0]));
+
+        let gaussian = VariogramStructure::new(StructureType::Gaussian, 1.0, SearchEllipsoid::isotropic(10.0));
+        assert!((gaussian.gamma([10.0, 0.0, 0.0]) - (1.0 - (-3.0f64).exp())).abs() < 1e-12);
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let mut ranges = SearchEllipsoid::new(80.0, 40.0, 10.0);
+        ranges.set_orientation(45.0, 10.0, 0.0);
+        let model = VariogramModel::new(0.1, vec![
+            VariogramStructure::new(StructureType::Spherical, 0.6, ranges),
+            VariogramStructure::new(StructureType::Gaussian, 0.3, SearchEllipsoid::isotropic(150.0)),
+        ]);
+
+        let text = model.to_toml().unwrap();
+        println!("{}", text);
+        assert!(text.contains("structure_type = \"spherical\""));
+        assert_eq!(VariogramModel::from_toml(&text).unwrap(), model);
+
+        assert!(matches!(VariogramModel::from_toml("nugget = -1.0\nstructures = []"), Err(LegoError::Parameter(_))));
+        assert!(matches!(VariogramModel::from_toml("nugget = "), Err(LegoError::Config { .. })));
+        assert!(matches!(VariogramModel::read_toml("/missing/lego/variogram.toml"), Err(LegoError::Io { .. })));
+    }
+}
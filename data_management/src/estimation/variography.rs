@@ -0,0 +1,525 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use lego_config::error::LegoError;
+
+use crate::estimation::kriging::solve;
+use crate::estimation::search::{Sample, SearchEllipsoid};
+use crate::estimation::variogram::{StructureType, VariogramModel, VariogramStructure};
+
+// Pairs are taken when the angle between them and the direction is under the tolerance and
+// they are not farther than the bandwidth from the direction line. Angle tolerance of 90
+// degrees means omni directional. Angles are in degrees like the search ellipsoid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VariogramDirection {
+    pub bearing: f64,
+    pub plunge: f64,
+    pub angle_tolerance: f64,
+    pub bandwidth: f64,
+}
+
+impl VariogramDirection {
+    pub fn new(bearing: f64, plunge: f64, angle_tolerance: f64, bandwidth: f64) -> VariogramDirection {
+        VariogramDirection { bearing, plunge, angle_tolerance, bandwidth }
+    }
+
+    pub fn omni() -> VariogramDirection {
+        VariogramDirection::new(0.0, 0.0, 90.0, f64::INFINITY)
+    }
+
+    pub fn is_omni(&self) -> bool {
+        self.angle_tolerance >= 90.0
+    }
+
+    pub(crate) fn unit(&self) -> [f64; 3] {
+        let (sin_b, cos_b) = self.bearing.to_radians().sin_cos();
+        let (sin_p, cos_p) = self.plunge.to_radians().sin_cos();
+        [sin_b * cos_p, cos_b * cos_p, sin_p]
+    }
+
+    fn accepts(&self, offset: [f64; 3], distance: f64) -> bool {
+        if self.is_omni() {
+            return true;
+        }
+
+        let u = self.unit();
+        let along = (offset[0] * u[0] + offset[1] * u[1] + offset[2] * u[2]).abs();
+        let across = (distance * distance - along * along).max(0.0).sqrt();
+
+        along >= distance * self.angle_tolerance.to_radians().cos() && across <= self.bandwidth
+    }
+}
+
+impl Display for VariogramDirection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_omni() {
+            return write!(f, "omni");
+        }
+
+        write!(f, "bearing {} plunge {} tolerance {} bandwidth {}", self.bearing, self.plunge,
+               self.angle_tolerance, self.bandwidth)
+    }
+}
+
+// pairs in lag * k +- tolerance are gathered into the k. lag, k runs from 1 to count
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LagParameters {
+    pub lag: f64,
+    pub count: usize,
+    pub tolerance: f64,
+}
+
+impl LagParameters {
+    pub fn new(lag: f64, count: usize) -> LagParameters {
+        LagParameters {
+            lag,
+            count,
+            tolerance: lag / 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VariogramLag {
+    // mean distance of the pairs
+    pub distance: f64,
+    pub gamma: f64,
+    pub pairs: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExperimentalVariogram {
+    pub direction: VariogramDirection,
+    // lags without any pair are left out
+    pub lags: Vec<VariogramLag>,
+}
+
+impl ExperimentalVariogram {
+    pub fn new(samples: &[Sample], direction: VariogramDirection,
+               lags: LagParameters) -> Result<ExperimentalVariogram, LegoError> {
+        if lags.lag <= 0.0 || lags.count == 0 || lags.tolerance <= 0.0 {
+            return Err(LegoError::parameter(format!("lag parameters are not valid : {:?}", lags)));
+        }
+
+        let mut sums = vec![(0.0, 0.0, 0); lags.count];
+
+        for (i, a) in samples.iter().enumerate() {
+            for b in samples[i + 1..].iter() {
+                let offset = [b.x - a.x, b.y - a.y, b.z - a.z];
+                let distance = (offset[0].powi(2) + offset[1].powi(2) + offset[2].powi(2)).sqrt();
+
+                let k = (distance / lags.lag).round();
+                if k < 1.0 || k > lags.count as f64 || (distance - k * lags.lag).abs() > lags.tolerance {
+                    continue;
+                }
+
+                if !direction.accepts(offset, distance) {
+                    continue;
+                }
+
+                let sum = &mut sums[k as usize - 1];
+                sum.0 += distance;
+                sum.1 += (a.value - b.value).powi(2);
+                sum.2 += 1;
+            }
+        }
+
+        let lags = sums.iter()
+            .filter(|(_, _, pairs)| *pairs > 0)
+            .map(|(distance, squares, pairs)| VariogramLag {
+                distance: distance / *pairs as f64,
+                gamma: squares / (2.0 * *pairs as f64),
+                pairs: *pairs,
+            })
+            .collect();
+
+        Ok(ExperimentalVariogram { direction, lags })
+    }
+
+    // offset of the lag used while comparing with a model, omni ones are compared along
+    // the major axis of the shape
+    fn lag_offset(&self, distance: f64, shape: &SearchEllipsoid) -> [f64; 3] {
+        let u = if self.direction.is_omni() { shape.axes()[0] } else { self.direction.unit() };
+        [u[0] * distance, u[1] * distance, u[2] * distance]
+    }
+}
+
+impl Display for ExperimentalVariogram {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "direction : {}", self.direction)?;
+        for l in self.lags.iter() {
+            write!(f, " \n{:>10.2} {:>12.4} {:>8}", l.distance, l.gamma, l.pairs)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VariogramMapCell {
+    pub dx: f64,
+    pub dy: f64,
+    pub gamma: Option<f64>,
+    pub pairs: usize,
+}
+
+// Variogram of the horizontal offsets on a (2 * count + 1) square grid centered on zero
+// offset. Pairs farther than the vertical tolerance on z are left out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariogramMap {
+    pub cell: f64,
+    pub count: usize,
+    pub cells: Vec<VariogramMapCell>,
+}
+
+impl VariogramMap {
+    pub fn new(samples: &[Sample], cell: f64, count: usize,
+               vertical_tolerance: f64) -> Result<VariogramMap, LegoError> {
+        if cell <= 0.0 || count == 0 {
+            return Err(LegoError::parameter(format!("variogram map needs a positive cell size and count, got {} {}",
+                                                    cell, count)));
+        }
+
+        let side = 2 * count + 1;
+        let mut sums = vec![(0.0, 0); side * side];
+        let index = |dx: f64, dy: f64| {
+            let (i, j) = ((dx / cell).round() + count as f64, (dy / cell).round() + count as f64);
+            if i < 0.0 || j < 0.0 || i >= side as f64 || j >= side as f64 {
+                None
+            } else {
+                Some(j as usize * side + i as usize)
+            }
+        };
+
+        for (i, a) in samples.iter().enumerate() {
+            for b in samples[i + 1..].iter() {
+                if (b.z - a.z).abs() > vertical_tolerance {
+                    continue;
+                }
+
+                let square = (a.value - b.value).powi(2);
+                // the map is symmetric
+                for (dx, dy) in [(b.x - a.x, b.y - a.y), (a.x - b.x, a.y - b.y)].iter() {
+                    if let Some(k) = index(*dx, *dy) {
+                        sums[k].0 += square;
+                        sums[k].1 += 1;
+                    }
+                }
+            }
+        }
+
+        let cells = sums.iter().enumerate()
+            .map(|(k, (squares, pairs))| VariogramMapCell {
+                dx: ((k % side) as f64 - count as f64) * cell,
+                dy: ((k / side) as f64 - count as f64) * cell,
+                gamma: if *pairs > 0 { Some(squares / (2.0 * *pairs as f64)) } else { None },
+                pairs: *pairs,
+            })
+            .collect();
+
+        Ok(VariogramMap { cell, count, cells })
+    }
+
+    pub fn get_cell(&self, i: i64, j: i64) -> Option<&VariogramMapCell> {
+        let side = (2 * self.count + 1) as i64;
+        let (i, j) = (i + self.count as i64, j + self.count as i64);
+        if i < 0 || j < 0 || i >= side || j >= side {
+            return None;
+        }
+        self.cells.get((j * side + i) as usize)
+    }
+}
+
+// Structures are fitted with their major ranges, semi major and minor ranges keep the
+// ratios of the shape and take its orientation. Nugget and ranges are searched unless
+// they are given, which makes a semi automatic fit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FitParameters {
+    pub structure_types: Vec<StructureType>,
+    pub shape: SearchEllipsoid,
+    pub nugget: Option<f64>,
+    pub ranges: Option<Vec<f64>>,
+}
+
+impl FitParameters {
+    pub fn new(structure_types: Vec<StructureType>) -> FitParameters {
+        FitParameters {
+            structure_types,
+            shape: SearchEllipsoid::isotropic(1.0),
+            nugget: None,
+            ranges: None,
+        }
+    }
+
+    fn structure_ranges(&self, major: f64) -> SearchEllipsoid {
+        self.shape.scaled(major / self.shape.major)
+    }
+}
+
+// weighted by pair counts. sills and nugget are found by non negative least squares for
+// every tried set of ranges.
+pub fn fit_variogram(variograms: &[ExperimentalVariogram],
+                     parameters: &FitParameters) -> Result<VariogramModel, LegoError> {
+    let structure_count = parameters.structure_types.len();
+    if structure_count == 0 || structure_count > 4 {
+        return Err(LegoError::parameter(format!("one to four structures can be fitted, got {}", structure_count)));
+    }
+    parameters.shape.validate()?;
+
+    let lags: Vec<(&ExperimentalVariogram, &VariogramLag)> = variograms.iter()
+        .flat_map(|v| v.lags.iter().map(move |l| (v, l)))
+        .collect();
+    if lags.is_empty() {
+        return Err(LegoError::parameter("experimental variograms do not have any lag to fit"));
+    }
+
+    let max_distance = lags.iter().map(|(_, l)| l.distance).fold(0.0, f64::max);
+    let min_distance = lags.iter().map(|(_, l)| l.distance).fold(f64::MAX, f64::min);
+
+    let ranges = match &parameters.ranges {
+        Some(r) if r.len() != structure_count || r.iter().any(|x| *x <= 0.0) => {
+            return Err(LegoError::parameter(format!("{} positive ranges are needed, got {:?}", structure_count, r)));
+        }
+        Some(r) => r.clone(),
+        None => search_ranges(&lags, parameters, (min_distance / 2.0).max(1e-6), max_distance * 2.0)
+    };
+
+    let (model, _) = fit_sills(&lags, parameters, &ranges);
+    model.validate()?;
+    Ok(model)
+}
+
+// grid search on a geometric series of ranges, nested ranges are kept increasing. the
+// best one is refined range by range.
+fn search_ranges(lags: &[(&ExperimentalVariogram, &VariogramLag)], parameters: &FitParameters,
+                 low: f64, high: f64) -> Vec<f64> {
+    let steps: usize = 30;
+    let ratio = (high / low).powf(1.0 / (steps - 1) as f64);
+    let candidates: Vec<f64> = (0..steps).map(|i| low * ratio.powi(i as i32)).collect();
+
+    let count = parameters.structure_types.len();
+    let mut best: Option<(f64, Vec<f64>)> = None;
+    let mut indices: Vec<usize> = (0..count).collect();
+
+    loop {
+        let ranges: Vec<f64> = indices.iter().map(|&i| candidates[i]).collect();
+        let (_, error) = fit_sills(lags, parameters, &ranges);
+        if best.as_ref().is_none_or(|(e, _)| error < *e) {
+            best = Some((error, ranges));
+        }
+
+        // next increasing combination
+        let mut k = count;
+        loop {
+            if k == 0 {
+                let (_, mut ranges) = best.unwrap();
+                refine_ranges(lags, parameters, &mut ranges);
+                return ranges;
+            }
+            k -= 1;
+            if indices[k] < steps - (count - k) {
+                indices[k] += 1;
+                for m in k + 1..count {
+                    indices[m] = indices[m - 1] + 1;
+                }
+                break;
+            }
+        }
+    }
+}
+
+fn refine_ranges(lags: &[(&ExperimentalVariogram, &VariogramLag)], parameters: &FitParameters, ranges: &mut [f64]) {
+    for k in 0..ranges.len() {
+        let center = ranges[k];
+        let mut best = (fit_sills(lags, parameters, ranges).1, center);
+
+        for i in 0..=20 {
+            ranges[k] = center * (0.8 + 0.02 * i as f64);
+            let error = fit_sills(lags, parameters, ranges).1;
+            if error < best.0 {
+                best = (error, ranges[k]);
+            }
+        }
+        ranges[k] = best.1;
+    }
+}
+
+// returns the model and its weighted squared error
+fn fit_sills(lags: &[(&ExperimentalVariogram, &VariogramLag)], parameters: &FitParameters,
+             ranges: &[f64]) -> (VariogramModel, f64) {
+    let structures: Vec<VariogramStructure> = parameters.structure_types.iter().zip(ranges.iter())
+        .map(|(t, r)| VariogramStructure::new(*t, 1.0, parameters.structure_ranges(*r)))
+        .collect();
+
+    let fixed_nugget = parameters.nugget;
+    let unknowns = structures.len() + if fixed_nugget.is_some() { 0 } else { 1 };
+
+    // one row for every lag: unit structure values (and 1 for the nugget), target, weight
+    let rows: Vec<(Vec<f64>, f64, f64)> = lags.iter()
+        .map(|(v, l)| {
+            let offset = v.lag_offset(l.distance, &parameters.shape);
+            let mut basis: Vec<f64> = structures.iter().map(|s| s.gamma(offset)).collect();
+            if fixed_nugget.is_none() {
+                basis.push(1.0);
+            }
+            (basis, l.gamma - fixed_nugget.unwrap_or(0.0), l.pairs as f64)
+        })
+        .collect();
+
+    let mut active: Vec<usize> = (0..unknowns).collect();
+    let mut coefficients = vec![0.0; unknowns];
+
+    // drops the most negative coefficient until all are non negative
+    while !active.is_empty() {
+        let n = active.len();
+        let mut matrix = vec![vec![0.0; n]; n];
+        let mut right = vec![0.0; n];
+        for (basis, target, weight) in rows.iter() {
+            for (a, &i) in active.iter().enumerate() {
+                for (b, &j) in active.iter().enumerate() {
+                    matrix[a][b] += weight * basis[i] * basis[j];
+                }
+                right[a] += weight * basis[i] * target;
+            }
+        }
+
+        let solution = match solve(matrix, right) {
+            Some(s) => s,
+            None => {
+                active.pop();
+                continue;
+            }
+        };
+
+        let (worst, value) = solution.iter().enumerate()
+            .fold((0, 0.0), |(wi, wv), (i, v)| if *v < wv { (i, *v) } else { (wi, wv) });
+        if value < 0.0 {
+            active.remove(worst);
+            continue;
+        }
+
+        coefficients = vec![0.0; unknowns];
+        for (a, &i) in active.iter().enumerate() {
+            coefficients[i] = solution[a];
+        }
+        break;
+    }
+
+    let error: f64 = rows.iter()
+        .map(|(basis, target, weight)| {
+            let fitted: f64 = basis.iter().zip(coefficients.iter()).map(|(b, c)| b * c).sum();
+            weight * (fitted - target).powi(2)
+        })
+        .sum();
+
+    let nugget = fixed_nugget.unwrap_or_else(|| coefficients[unknowns - 1]);
+    let structures = structures.iter().zip(coefficients.iter())
+        .map(|(s, c)| VariogramStructure::new(s.structure_type, *c, s.ranges))
+        .collect();
+
+    (VariogramModel::new(nugget, structures), error)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::estimation::search::{Sample, SearchEllipsoid};
+    use crate::estimation::variogram::{StructureType, VariogramModel, VariogramStructure};
+    use crate::estimation::variography::{ExperimentalVariogram, FitParameters, fit_variogram, LagParameters,
+                                         VariogramDirection, VariogramLag, VariogramMap};
+    use crate::drillhole::desurvey::DesurveyMethod;
+    use crate::lego_tests::{give_me_composite, give_me_drillhole_database};
+
+    // values grow along x, nothing changes along y
+    fn trend_samples() -> Vec<Sample> {
+        let mut samples = vec![];
+        for i in 0..10 {
+            for j in 0..10 {
+                samples.push(Sample::new(i as f64, j as f64, 0.0, i as f64, format!("S-{}", i)));
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn directional_variograms() {
+        let samples = trend_samples();
+        let lags = LagParameters::new(1.0, 3);
+
+        let east = ExperimentalVariogram::new(&samples, VariogramDirection::new(90.0, 0.0, 10.0, 0.5), lags).unwrap();
+        let north = ExperimentalVariogram::new(&samples, VariogramDirection::new(0.0, 0.0, 10.0, 0.5), lags).unwrap();
+        println!("{} \n{}", east, north);
+
+        // gamma of a linear trend is h * h / 2
+        assert_eq!(east.lags.len(), 3);
+        assert!((east.lags[1].gamma - 2.0).abs() < 1e-12);
+        assert_eq!(east.lags[0].pairs, 9 * 10);
+        assert!(north.lags.iter().all(|l| l.gamma == 0.0));
+
+        let omni = ExperimentalVariogram::new(&samples, VariogramDirection::omni(), lags).unwrap();
+        assert!(omni.lags[0].pairs > east.lags[0].pairs);
+    }
+
+    #[test]
+    fn variogram_map_of_trend() {
+        let map = VariogramMap::new(&trend_samples(), 1.0, 2, 0.5).unwrap();
+
+        assert_eq!(map.cells.len(), 25);
+        assert!((map.get_cell(1, 0).unwrap().gamma.unwrap() - 0.5).abs() < 1e-12);
+        assert!((map.get_cell(-2, 0).unwrap().gamma.unwrap() - 2.0).abs() < 1e-12);
+        assert_eq!(map.get_cell(0, 1).unwrap().gamma, Some(0.0));
+        assert_eq!(map.get_cell(0, 0).unwrap().gamma, None);
+        assert!(map.get_cell(3, 0).is_none());
+    }
+
+    #[test]
+    fn fit_known_model() {
+        let model = VariogramModel::new(0.2, vec![
+            VariogramStructure::new(StructureType::Spherical, 0.5, SearchEllipsoid::isotropic(40.0)),
+            VariogramStructure::new(StructureType::Spherical, 0.3, SearchEllipsoid::isotropic(120.0)),
+        ]);
+
+        let lags = (1..=30).map(|k| {
+            let distance = k as f64 * 5.0;
+            VariogramLag { distance, gamma: model.gamma([0.0, distance, 0.0]), pairs: 100 }
+        }).collect();
+        let experimental = ExperimentalVariogram { direction: VariogramDirection::omni(), lags };
+
+        let fitted = fit_variogram(std::slice::from_ref(&experimental),
+                                   &FitParameters::new(vec![StructureType::Spherical, StructureType::Spherical])).unwrap();
+        println!("{}", fitted);
+        assert!((fitted.sill() - 1.0).abs() < 0.02);
+        assert!((fitted.nugget - 0.2).abs() < 0.05);
+        assert!((fitted.structures[0].ranges.major - 40.0).abs() < 4.0);
+
+        // semi automatic, only sills are fitted
+        let mut parameters = FitParameters::new(vec![StructureType::Spherical, StructureType::Spherical]);
+        parameters.ranges = Some(vec![40.0, 120.0]);
+        let fitted = fit_variogram(&[experimental], &parameters).unwrap();
+        assert!((fitted.nugget - 0.2).abs() < 1e-9);
+        assert!((fitted.structures[1].sill - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn raw_sample_variogram() {
+        let samples = Sample::from_raw_samples(&give_me_drillhole_database(), DesurveyMethod::MinimumCurvature);
+        let down_hole = ExperimentalVariogram::new(&samples, VariogramDirection::new(0.0, -90.0, 15.0, 2.0),
+                                                   LagParameters::new(1.0, 10)).unwrap();
+        println!("{}", down_hole);
+
+        assert!(!samples.is_empty());
+        assert!(down_hole.lags.iter().all(|l| l.pairs > 0));
+    }
+
+    #[test]
+    fn fit_test_composites() {
+        let samples = Sample::from_composites(&give_me_composite());
+        let experimental = ExperimentalVariogram::new(&samples, VariogramDirection::omni(),
+                                                      LagParameters::new(10.0, 15)).unwrap();
+        println!("{}", experimental);
+
+        let fitted = fit_variogram(&[experimental], &FitParameters::new(vec![StructureType::Spherical])).unwrap();
+        println!("{}", fitted);
+
+        assert!(fitted.sill() > 0.0);
+        assert_eq!(VariogramModel::from_toml(&fitted.to_toml().unwrap()).unwrap(), fitted);
+    }
+}
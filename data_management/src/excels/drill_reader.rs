@@ -1,30 +1,61 @@
-use std::collections::HashMap;
-use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::fmt;
 use std::hash::Hash;
-use std::string::ToString;
 
-use csv::{Reader, StringRecord};
+use lego_config::error::LegoError;
 use lego_config::read::{LegoConfig, DataManagementObjects};
+use lego_config::settings::DrillHeaders;
 use plotly::{Plot, Scatter};
-use crate::excels::excel_traits::WebDraw;
+use crate::excels::excel_traits::{csv_error, open_csv, CsvColumn, CsvColumns, WebDraw};
 use plotly::{Surface, Layout};
 use plotly::surface::{Lighting, PlaneContours, PlaneProject, SurfaceContours};
 
+#[derive(Clone)]
 pub struct DrillObject {
-    info: DrillInformation,
-    data: Vec<Drill>,
+    // None for the drills which are not read from a file
+    info: Option<DrillInformation>,
+    pub(crate) data: Vec<Drill>,
 }
 
 impl DrillObject {
-    pub(crate) fn new(info: DrillInformation) -> DrillObject {
-        let data = info.read().unwrap();
+    pub fn new(info: DrillInformation) -> Result<DrillObject, LegoError> {
+        let data = info.read()?;
+        Ok(DrillObject {
+            info: Some(info),
+            data,
+        })
+    }
+
+    pub fn from_records(data: Vec<Drill>) -> DrillObject {
         DrillObject {
-            info,
+            info: None,
             data,
         }
     }
+
+    pub fn info(&self) -> Option<&DrillInformation> {
+        self.info.as_ref()
+    }
+
+    pub fn data(&self) -> &[Drill] {
+        &self.data
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Drill> {
+        self.data.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn get_drill(&self, drill_no: &str) -> Option<&Drill> {
+        self.data.iter().find(|d| d.drill_no == drill_no)
+    }
 }
 
 impl WebDraw for DrillObject {
@@ -63,16 +94,17 @@ impl WebDraw for DrillObject {
     }
 }
 
+#[derive(Clone)]
 pub struct DrillInformation {
     path: String,
     mining_type: String,
     seperator: char,
-    columns: HashMap<String, String>,  // todo sonra hallederim
+    columns: DrillHeaders,
 }
 
 impl DrillInformation {
-    fn new(path: String, mining_type: String, seperator: char,
-           columns: HashMap<String, String>) -> DrillInformation {
+    pub fn new(path: String, mining_type: String, seperator: char,
+               columns: DrillHeaders) -> DrillInformation {
         DrillInformation {
             path,
             mining_type,
@@ -81,57 +113,59 @@ impl DrillInformation {
         }
     }
 
-    fn read(&self) -> Result<Vec<Drill>, Box<dyn Error>> {
+    fn read(&self) -> Result<Vec<Drill>, LegoError> {
         // our data
         let mut drill_objects: Vec<Drill> = vec![];
 
         let drill_csv_path: &String = &self.path;
-        let mut reader = Reader::from_path(drill_csv_path)?;
+        let mut reader = open_csv(drill_csv_path, self.seperator)?;
 
-        // columns
-        reader.set_headers(StringRecord::from(vec!["SONDAJNO", "X", "Y", "Z", "DERINLIK"]));
+        // columns are found by their configured headers
+        let headers = reader.headers().map_err(|e| csv_error(drill_csv_path, e))?.clone();
+        let columns: CsvColumns<DrillColumns> = CsvColumns::new(drill_csv_path, &headers, &self.columns)?;
 
-        let mut results = reader.records();
+        for result in reader.records() {
+            let record = result.map_err(|e| csv_error(drill_csv_path, e))?;
 
-        // skip first row
-        results.next();
-
-        for result in results {
-            let record = result?;
-            let record: Vec<String> = record[0].to_string().split(";").map(|s| s.to_string()).collect();
-
-            let drill_no = record[0].to_owned();
-            let x: f64 = record[1].parse().expect("numerik deger çevirilemedi");
-            let y: f64 = record[2].parse().expect("numerik deger çevirilemedi");
-            let z: f64 = record[3].parse().expect("numerik deger çevirilemedi");
-            let depth: f64 = record[4].parse().expect("numerik deger çevirilemedi");
+            let drill_no = columns.text(&record, DrillColumns::DRILLNO);
+            let x: f64 = columns.field(&record, DrillColumns::X)?;
+            let y: f64 = columns.field(&record, DrillColumns::Y)?;
+            let z: f64 = columns.field(&record, DrillColumns::Z)?;
+            let depth: f64 = columns.field(&record, DrillColumns::DEPTH)?;
 
             let drill_coordinate = DrillCoordinate::new(x, y, z, depth);
-            let d_row = Drill::new(drill_no, drill_coordinate);
+            let d_row = Drill::new(drill_no, drill_coordinate).with_extra(columns.extra(&record));
 
             drill_objects.push(d_row);
         }
         Ok(drill_objects)
     }
 
-    pub(crate) fn new_from_config(config: &LegoConfig) -> DrillInformation {
+    pub fn new_from_config(config: &LegoConfig) -> Result<DrillInformation, LegoError> {
         // getting mining information
-        let path = config.get_drill_csv_path();
-        let mining_type = config.get_mining_type();
-        let seperator = config.get_x_seperator("drill_csv_seperator");
-        let columns = config.get_x_columns("drill_columns");
+        let path = config.get_drill_csv_path()?;
+        let mining_type = config.get_mining_type().to_string();
+        let seperator = config.get_mining_information().drill_csv_seperator;
+        let columns = config.get_settings().drill_columns.clone();
 
-        DrillInformation {
+        Ok(DrillInformation {
             path,
             mining_type,
             seperator,
             columns
-        }
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn mining_type(&self) -> &str {
+        &self.mining_type
     }
 }
 
-#[allow(unused)]
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum DrillColumns {
     DRILLNO,
     X,
@@ -140,31 +174,82 @@ enum DrillColumns {
     DEPTH,
 }
 
-#[derive(Debug)]
-struct Drill {
-    drill_no: String,
-    coordinate: DrillCoordinate,
+impl CsvColumn for DrillColumns {
+    type Headers = DrillHeaders;
+
+    const ALL: &'static [DrillColumns] = &[DrillColumns::DRILLNO, DrillColumns::X, DrillColumns::Y,
+        DrillColumns::Z, DrillColumns::DEPTH];
+
+    fn key(&self) -> &'static str {
+        match self {
+            DrillColumns::DRILLNO => "DRILLNO",
+            DrillColumns::X => "X",
+            DrillColumns::Y => "Y",
+            DrillColumns::Z => "Z",
+            DrillColumns::DEPTH => "DEPTH",
+        }
+    }
+
+    fn header<'a>(&self, headers: &'a DrillHeaders) -> &'a str {
+        match self {
+            DrillColumns::DRILLNO => &headers.drill_no,
+            DrillColumns::X => &headers.x,
+            DrillColumns::Y => &headers.y,
+            DrillColumns::Z => &headers.z,
+            DrillColumns::DEPTH => &headers.depth,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drill {
+    pub(crate) drill_no: String,
+    pub(crate) coordinate: DrillCoordinate,
+    // columns of the file which are not mapped, header and value
+    pub(crate) extra: Vec<(String, String)>,
 }
 
 impl Drill {
-    fn new(drill_no: String, coordinate: DrillCoordinate) -> Drill {
+    pub fn new(drill_no: String, coordinate: DrillCoordinate) -> Drill {
         Drill {
             drill_no,
             coordinate,
+            extra: vec![],
         }
     }
+
+    pub fn with_extra(mut self, extra: Vec<(String, String)>) -> Drill {
+        self.extra = extra;
+        self
+    }
+
+    pub fn drill_no(&self) -> &str {
+        &self.drill_no
+    }
+
+    pub fn coordinate(&self) -> &DrillCoordinate {
+        &self.coordinate
+    }
+
+    pub fn extra(&self) -> &[(String, String)] {
+        &self.extra
+    }
+
+    pub fn get_extra(&self, header: &str) -> Option<&str> {
+        self.extra.iter().find(|(h, _)| h == header).map(|(_, v)| v.as_str())
+    }
 }
 
-#[derive(Debug)]
-struct DrillCoordinate {
-    x_coord: f64,
-    y_coord: f64,
-    z_coord: f64,
-    depth: f64,
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrillCoordinate {
+    pub(crate) x_coord: f64,
+    pub(crate) y_coord: f64,
+    pub(crate) z_coord: f64,
+    pub(crate) depth: f64,
 }
 
 impl DrillCoordinate {
-    fn new(x: f64, y: f64, z: f64, depth: f64) -> DrillCoordinate {
+    pub fn new(x: f64, y: f64, z: f64, depth: f64) -> DrillCoordinate {
         DrillCoordinate {
             x_coord: x,
             y_coord: y,
@@ -172,6 +257,22 @@ impl DrillCoordinate {
             depth,
         }
     }
+
+    pub fn x(&self) -> f64 {
+        self.x_coord
+    }
+
+    pub fn y(&self) -> f64 {
+        self.y_coord
+    }
+
+    pub fn z(&self) -> f64 {
+        self.z_coord
+    }
+
+    pub fn depth(&self) -> f64 {
+        self.depth
+    }
 }
 
 impl Display for DrillCoordinate {
@@ -187,14 +288,17 @@ impl Display for DrillInformation {
         write!(f, "path : {} \n\
                    mining type : {} \n\
                    seperator : {} \n\
-                   columns: {:?}", self.path, self.mining_type, self.seperator, self.columns)
+                   columns : {:?}", self.path, self.mining_type, self.seperator, self.columns)
     }
 }
 
 impl Display for DrillObject {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "Information : {} \n\
-                   Drills : {:?}", self.info, self.data)
+        match &self.info {
+            Some(info) => writeln!(f, "Information : {} ", info)?,
+            None => writeln!(f, "Information : in memory ")?
+        }
+        write!(f, "Drills : {:?}", self.data)
     }
 }
 
@@ -207,19 +311,22 @@ impl Display for Drill {
 
 #[cfg(test)]
 mod tests {
-    use crate::excels::drill_reader::{DrillInformation, DrillObject};
+    use crate::excels::drill_reader::{Drill, DrillCoordinate, DrillInformation, DrillObject};
+    use lego_config::error::LegoError;
     use lego_config::read::{LegoConfig};
+    use lego_config::settings::DrillHeaders;
     use crate::excels::excel_traits::WebDraw;
 
-    const TEST_CONFIG_PATH: &str = "/home/umut/CLionProjects/LegoRust/lego_config/test_settings.toml";
+    const TEST_CONFIG_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../lego_config/test_settings.toml");
+    const MAPPED_CSV_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../tests/data/excels_mapped/collars.csv");
 
     #[test]
     fn creating_drill_object_from_config() {
         // config path
-        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH));
-        let d_info = DrillInformation::new_from_config(&config_object);
+        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
+        let d_info = DrillInformation::new_from_config(&config_object).unwrap();
 
-        let drill_object = DrillObject::new(d_info);
+        let drill_object = DrillObject::new(d_info).unwrap();
         println!("drill object : {}", drill_object);
 
     }
@@ -227,11 +334,52 @@ mod tests {
     #[test]
     fn create_topo () {
         // config path
-        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH));
-        let d_info = DrillInformation::new_from_config(&config_object);
+        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
+        let d_info = DrillInformation::new_from_config(&config_object).unwrap();
 
-        let drill_object = DrillObject::new(d_info);
+        let drill_object = DrillObject::new(d_info).unwrap();
         drill_object.generate_topograpy(true);
 
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn read_mapped_columns() {
+        // comma seperated, other headers in another order and an extra column
+        let mut columns = DrillHeaders {
+            drill_no: String::from("HOLE"),
+            x: String::from("EAST"),
+            y: String::from("NORTH"),
+            z: String::from("ELEV"),
+            depth: String::from("LENGTH"),
+        };
+
+        let info = DrillInformation::new(String::from(MAPPED_CSV_PATH), String::from("cu"), ',', columns.clone());
+        let drill_object = DrillObject::new(info).unwrap();
+        assert_eq!(drill_object.data.len(), 2);
+
+        let drill = &drill_object.data[0];
+        assert_eq!(drill.drill_no, "SK-01");
+        assert_eq!((drill.coordinate.x_coord, drill.coordinate.y_coord), (68931.72, 375995.75));
+        assert_eq!((drill.coordinate.z_coord, drill.coordinate.depth), (544.16, 264.16));
+        assert_eq!(drill.extra, vec![(String::from("COMPANY"), String::from("MTA"))]);
+
+        columns.depth = String::from("DERINLIK");
+        let info = DrillInformation::new(String::from(MAPPED_CSV_PATH), String::from("cu"), ',', columns);
+        assert!(matches!(DrillObject::new(info), Err(LegoError::MissingColumn { .. })));
+    }
+
+    #[test]
+    fn drills_from_records() {
+        let drill_object = DrillObject::from_records(vec![
+            Drill::new(String::from("SK-01"), DrillCoordinate::new(68931.72, 375995.75, 544.16, 264.16)),
+            Drill::new(String::from("SK-02"), DrillCoordinate::new(68950.0, 376010.0, 540.0, 180.0)),
+        ]);
+        assert!(drill_object.info().is_none());
+        assert_eq!(drill_object.len(), 2);
+
+        let drill = drill_object.get_drill("SK-02").unwrap();
+        assert_eq!((drill.coordinate().z(), drill.coordinate().depth()), (540.0, 180.0));
+        assert!(drill_object.get_drill("SK-99").is_none());
+        assert_eq!(drill_object.iter().map(|d| d.drill_no()).collect::<Vec<_>>(), vec!["SK-01", "SK-02"]);
+    }
+}
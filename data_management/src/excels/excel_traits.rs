@@ -1,5 +1,121 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use csv::{Reader, ReaderBuilder, StringRecord, Trim, Writer, WriterBuilder};
+use lego_config::error::LegoError;
+
 pub trait WebDraw {
     fn generate_topograpy(&self, auto_show: bool);
 
 
-}
\ No newline at end of file
+}
+
+// csv errors carry an io error or the position of the broken record
+pub(crate) fn csv_error(path: &str, error: csv::Error) -> LegoError {
+    let line = error.position().map(|p| p.line() as usize).unwrap_or(0);
+    let message = error.to_string();
+
+    match error.into_kind() {
+        csv::ErrorKind::Io(e) => LegoError::io(path, e),
+        _ => LegoError::parse(path, line, "record", &message)
+    }
+}
+
+// csv reports are written into memory before they are saved
+pub(crate) fn report_writer(seperator: char) -> Writer<Vec<u8>> {
+    WriterBuilder::new().delimiter(seperator as u8).from_writer(vec![])
+}
+
+// the records of a report have a fixed length, so writing them can fail only on a broken report
+pub(crate) fn report_error(error: impl Display) -> LegoError {
+    LegoError::parameter(format!("report cannot be written as csv : {}", error))
+}
+
+pub(crate) fn report_text(writer: Writer<Vec<u8>>) -> Result<String, LegoError> {
+    let bytes = writer.into_inner().map_err(report_error)?;
+    String::from_utf8(bytes).map_err(report_error)
+}
+
+pub(crate) fn write_report(path: &str, text: &str) -> Result<(), LegoError> {
+    std::fs::write(path, text).map_err(|e| LegoError::io(path, e))
+}
+
+// missing fields are reported with an empty value
+pub(crate) fn record_field<T: FromStr>(record: &[String], index: usize, column: &str, path: &str,
+                                       line: usize) -> Result<T, LegoError> {
+    let value = record.get(index).map(|s| s.as_str()).unwrap_or("");
+    LegoError::parse_field(value, path, line, column)
+}
+
+// logical columns of a csv file, their headers are given in the [x_columns] sections of the config
+pub(crate) trait CsvColumn: Copy + Eq + Hash + 'static {
+    type Headers;
+
+    const ALL: &'static [Self];
+
+    // key in the config
+    fn key(&self) -> &'static str;
+
+    fn header<'a>(&self, headers: &'a Self::Headers) -> &'a str;
+}
+
+pub(crate) fn open_csv(path: &str, seperator: char) -> Result<Reader<File>, LegoError> {
+    ReaderBuilder::new()
+        .delimiter(seperator as u8)
+        .trim(Trim::All)
+        .from_path(path)
+        .map_err(|e| csv_error(path, e))
+}
+
+// positions of the logical columns in the header of a file, the other columns are kept as extra
+pub(crate) struct CsvColumns<C: CsvColumn> {
+    path: String,
+    headers: Vec<String>,
+    positions: HashMap<C, usize>,
+}
+
+impl<C: CsvColumn> CsvColumns<C> {
+    // headers are matched case insensitive, in any order
+    pub(crate) fn new(path: &str, record: &StringRecord, headers: &C::Headers) -> Result<CsvColumns<C>, LegoError> {
+        let file_headers: Vec<String> = record.iter().map(|h| h.to_string()).collect();
+        let mut positions: HashMap<C, usize> = HashMap::new();
+
+        for column in C::ALL.iter() {
+            let header = column.header(headers);
+            let position = file_headers.iter().position(|h| h.eq_ignore_ascii_case(header))
+                .ok_or_else(|| LegoError::MissingColumn {
+                    path: path.to_string(),
+                    column: column.key().to_string(),
+                    header: header.to_string(),
+                })?;
+            positions.insert(*column, position);
+        }
+
+        Ok(CsvColumns {
+            path: path.to_string(),
+            headers: file_headers,
+            positions,
+        })
+    }
+
+    pub(crate) fn text(&self, record: &StringRecord, column: C) -> String {
+        record.get(self.positions[&column]).unwrap_or("").to_string()
+    }
+
+    pub(crate) fn field<T: FromStr>(&self, record: &StringRecord, column: C) -> Result<T, LegoError> {
+        let line = record.position().map(|p| p.line() as usize).unwrap_or(0);
+        let value = record.get(self.positions[&column]).unwrap_or("");
+        LegoError::parse_field(value, &self.path, line, column.key())
+    }
+
+    // header and value of the columns which are not mapped, in file order
+    pub(crate) fn extra(&self, record: &StringRecord) -> Vec<(String, String)> {
+        self.headers.iter().enumerate()
+            .filter(|(i, _)| !self.positions.values().any(|p| p == i))
+            .map(|(i, h)| (h.clone(), record.get(i).unwrap_or("").to_string()))
+            .collect()
+    }
+}
@@ -1,38 +1,73 @@
-use std::collections::HashMap;
-use std::error::Error;
-use csv::{Reader, StringRecord};
 use std::hash::Hash;
 use std::fmt::{Display, Formatter};
 use std::fmt;
-use std::string::ToString;
+use lego_config::error::LegoError;
 use lego_config::read::{LegoConfig, DataManagementObjects};
+use lego_config::settings::LythologyHeaders;
 
+use crate::excels::excel_traits::{csv_error, open_csv, CsvColumn, CsvColumns};
+
+#[derive(Clone)]
 pub struct LythologyObject {
-    info: LythologyInformation,
-    data: Vec<Lythology>,
+    // None for the lythologies which are not read from a file
+    info: Option<LythologyInformation>,
+    pub(crate) data: Vec<Lythology>,
 }
 
 impl LythologyObject {
-    pub(crate) fn new(info: LythologyInformation) -> LythologyObject {
-        let data = info.read().expect("Lythology excel okunurken bir hata oluştu !");
+    pub fn new(info: LythologyInformation) -> Result<LythologyObject, LegoError> {
+        let data = info.read()?;
+
+        Ok(LythologyObject {
+            info: Some(info),
+            data,
+        })
+    }
 
+    pub fn from_records(data: Vec<Lythology>) -> LythologyObject {
         LythologyObject {
-            info,
+            info: None,
             data,
         }
     }
+
+    pub fn info(&self) -> Option<&LythologyInformation> {
+        self.info.as_ref()
+    }
+
+    pub fn data(&self) -> &[Lythology] {
+        &self.data
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Lythology> {
+        self.data.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    // intervals of a hole in the order of the file
+    pub fn get_hole_lythology(&self, drill_no: &str) -> Vec<&Lythology> {
+        self.data.iter().filter(|l| l.drill_no == drill_no).collect()
+    }
 }
 
+#[derive(Clone)]
 pub struct LythologyInformation {
     path: String,
     mining_type: String,
     seperator: char,
-    columns: HashMap<String, String>, // todo: we will change here to enum
+    columns: LythologyHeaders,
 }
 
 impl LythologyInformation {
-    fn new(path: String, mining_type: String, seperator: char,
-           columns: HashMap<String, String>) -> LythologyInformation {
+    pub fn new(path: String, mining_type: String, seperator: char,
+               columns: LythologyHeaders) -> LythologyInformation {
         LythologyInformation {
             path,
             mining_type,
@@ -41,47 +76,50 @@ impl LythologyInformation {
         }
     }
 
-    pub(crate) fn new_from_config(config: &LegoConfig) -> LythologyInformation {
+    pub fn new_from_config(config: &LegoConfig) -> Result<LythologyInformation, LegoError> {
         // getting mining information
-        let path = config.get_lythology_csv_path();
-        let mining_type = config.get_mining_type();
-        let seperator = config.get_x_seperator("lythology_csv_seperator");
-        let columns = config.get_x_columns("lythology_columns");
+        let path = config.get_lythology_csv_path()?;
+        let mining_type = config.get_mining_type().to_string();
+        let seperator = config.get_mining_information().lythology_csv_seperator;
+        let columns = config.get_settings().lythology_columns.clone();
 
-        LythologyInformation {
+        Ok(LythologyInformation {
             path,
             mining_type,
             seperator,
             columns
-        }
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn mining_type(&self) -> &str {
+        &self.mining_type
     }
 
-    fn read(&self) -> Result<Vec<Lythology>, Box<dyn Error>> {
+    fn read(&self) -> Result<Vec<Lythology>, LegoError> {
         // our data
         let mut lythology_objects: Vec<Lythology> = vec![];
 
         let lythology_csv_path: &String = &self.path;
-        let mut reader = Reader::from_path(lythology_csv_path)?;
-
-        // columns
-        reader.set_headers(StringRecord::from(vec!["SONDAJNO", "FROM", "TO", "LYTHO"]));
+        let mut reader = open_csv(lythology_csv_path, self.seperator)?;
 
-        let mut results = reader.records();
+        // columns are found by their configured headers
+        let headers = reader.headers().map_err(|e| csv_error(lythology_csv_path, e))?.clone();
+        let columns: CsvColumns<LythologyColumns> = CsvColumns::new(lythology_csv_path, &headers, &self.columns)?;
 
-        // skip first row
-        results.next();
+        for result in reader.records() {
+            let record = result.map_err(|e| csv_error(lythology_csv_path, e))?;
 
-        for result in results {
-            let record = result?;
-            let record: Vec<String> = record[0].to_string().split(";").map(|s| s.to_string()).collect();
-
-            let drill_no = record[0].to_owned();
-            let start: f64 = record[1].parse().expect("numerik deger çevirilemedi");
-            let end: f64 = record[2].parse().expect("numerik deger çevirilemedi");
-            let lytho: String = record[3].parse().expect("numerik deger çevirilemedi");
+            let drill_no = columns.text(&record, LythologyColumns::DRILLNO);
+            let start: f64 = columns.field(&record, LythologyColumns::FROM)?;
+            let end: f64 = columns.field(&record, LythologyColumns::TO)?;
+            let lytho: String = columns.field(&record, LythologyColumns::LYTHO)?;
 
             let lythology_coordinate = LythologyCoordinate::new(start, end, lytho);
-            let d_row = Lythology::new(drill_no, lythology_coordinate);
+            let d_row = Lythology::new(drill_no, lythology_coordinate).with_extra(columns.extra(&record));
 
             lythology_objects.push(d_row);
         }
@@ -89,8 +127,7 @@ impl LythologyInformation {
     }
 }
 
-#[allow(unused)]
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum LythologyColumns {
     DRILLNO,
     FROM,
@@ -98,38 +135,99 @@ enum LythologyColumns {
     LYTHO,
 }
 
-#[derive(Debug)]
-struct Lythology {
-    drill_no: String,
-    coordinate: LythologyCoordinate,
+impl CsvColumn for LythologyColumns {
+    type Headers = LythologyHeaders;
+
+    const ALL: &'static [LythologyColumns] = &[LythologyColumns::DRILLNO, LythologyColumns::FROM,
+        LythologyColumns::TO, LythologyColumns::LYTHO];
+
+    fn key(&self) -> &'static str {
+        match self {
+            LythologyColumns::DRILLNO => "DRILLNO",
+            LythologyColumns::FROM => "FROM",
+            LythologyColumns::TO => "TO",
+            LythologyColumns::LYTHO => "LYTHO",
+        }
+    }
+
+    fn header<'a>(&self, headers: &'a LythologyHeaders) -> &'a str {
+        match self {
+            LythologyColumns::DRILLNO => &headers.drill_no,
+            LythologyColumns::FROM => &headers.from,
+            LythologyColumns::TO => &headers.to,
+            LythologyColumns::LYTHO => &headers.lytho,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lythology {
+    pub(crate) drill_no: String,
+    pub(crate) coordinate: LythologyCoordinate,
+    // columns of the file which are not mapped, header and value
+    pub(crate) extra: Vec<(String, String)>,
 }
 
 impl Lythology {
-    fn new(drill_no: String, coordinate: LythologyCoordinate) -> Lythology {
+    pub fn new(drill_no: String, coordinate: LythologyCoordinate) -> Lythology {
         Lythology {
             drill_no,
             coordinate,
+            extra: vec![],
         }
     }
+
+    pub fn with_extra(mut self, extra: Vec<(String, String)>) -> Lythology {
+        self.extra = extra;
+        self
+    }
+
+    pub fn drill_no(&self) -> &str {
+        &self.drill_no
+    }
+
+    pub fn coordinate(&self) -> &LythologyCoordinate {
+        &self.coordinate
+    }
+
+    pub fn extra(&self) -> &[(String, String)] {
+        &self.extra
+    }
+
+    pub fn get_extra(&self, header: &str) -> Option<&str> {
+        self.extra.iter().find(|(h, _)| h == header).map(|(_, v)| v.as_str())
+    }
 }
 
 
 
-#[derive(Debug)]
-struct LythologyCoordinate {
-    start: f64,
-    end: f64,
-    lytho: String,
+#[derive(Debug, Clone, PartialEq)]
+pub struct LythologyCoordinate {
+    pub(crate) start: f64,
+    pub(crate) end: f64,
+    pub(crate) lytho: String,
 }
 
 impl LythologyCoordinate {
-    fn new(start: f64, end: f64, lytho: String) -> LythologyCoordinate {
+    pub fn new(start: f64, end: f64, lytho: String) -> LythologyCoordinate {
         LythologyCoordinate {
             start,
             end,
             lytho,
         }
     }
+
+    pub fn start(&self) -> f64 {
+        self.start
+    }
+
+    pub fn end(&self) -> f64 {
+        self.end
+    }
+
+    pub fn lytho(&self) -> &str {
+        &self.lytho
+    }
 }
 
 impl Display for LythologyCoordinate {
@@ -149,8 +247,11 @@ impl Display for Lythology {
 
 impl Display for LythologyObject {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "info : {} \n\
-                   data : {:?}", self.info, self.data)
+        match &self.info {
+            Some(info) => writeln!(f, "info : {} ", info)?,
+            None => writeln!(f, "info : in memory ")?
+        }
+        write!(f, "data : {:?}", self.data)
     }
 }
 
@@ -165,18 +266,33 @@ impl Display for LythologyInformation {
 
 #[cfg(test)]
 mod tests {
-    use crate::excels::lytho_reader::{LythologyInformation, LythologyObject};
+    use crate::excels::lytho_reader::{Lythology, LythologyCoordinate, LythologyInformation, LythologyObject};
     use lego_config::read::LegoConfig;
 
-    const TEST_CONFIG_PATH: &str = "/home/umut/CLionProjects/LegoRust/lego_config/test_settings.toml";
+    const TEST_CONFIG_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../lego_config/test_settings.toml");
 
     #[test]
     fn read_lythology_from_config() {
-        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH));
+        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
+
+        let lytho_info = LythologyInformation::new_from_config(&config_object).unwrap();
+        let l_object = LythologyObject::new(lytho_info).unwrap();
+        println!("lytology : {}", l_object);
 
-        let lytho_info = LythologyInformation::new_from_config(&config_object);
-        let l_object = LythologyObject::new(lytho_info);
+    }
+
+    #[test]
+    fn lythology_from_records() {
+        let l_object = LythologyObject::from_records(vec![
+            Lythology::new(String::from("SK-01"), LythologyCoordinate::new(0.0, 12.5, String::from("andezit"))),
+            Lythology::new(String::from("SK-01"), LythologyCoordinate::new(12.5, 40.0, String::from("bazalt"))),
+        ]);
         println!("lytology : {}", l_object);
 
+        let intervals = l_object.get_hole_lythology("SK-01");
+        assert_eq!(intervals.len(), 2);
+        assert_eq!(intervals[1].coordinate().lytho(), "bazalt");
+        assert_eq!((intervals[1].coordinate().start(), intervals[1].coordinate().end()), (12.5, 40.0));
+        assert_eq!(l_object.iter().filter(|l| l.coordinate().lytho() == "andezit").count(), 1);
     }
 }
\ No newline at end of file
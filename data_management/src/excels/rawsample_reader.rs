@@ -1,37 +1,72 @@
-use std::collections::HashMap;
-use std::error::Error;
-use csv::{Reader, StringRecord};
 use std::hash::Hash;
 use std::fmt::{Display, Formatter};
 use std::fmt;
-use std::string::ToString;
+use lego_config::error::LegoError;
 use lego_config::read::{LegoConfig, DataManagementObjects};
+use lego_config::settings::RawSampleHeaders;
 
+use crate::excels::excel_traits::{csv_error, open_csv, CsvColumn, CsvColumns};
+
+#[derive(Clone)]
 pub struct RawSampleObject {
-    info: RawSampleInformation,
-    data: Vec<RawSample>,
+    // None for the samples which are not read from a file
+    info: Option<RawSampleInformation>,
+    pub(crate) data: Vec<RawSample>,
 }
 
 impl RawSampleObject {
-    pub(crate) fn new(info: RawSampleInformation) -> RawSampleObject {
-        let data = info.read().expect("Error occured while reading raw sample !");
+    pub fn new(info: RawSampleInformation) -> Result<RawSampleObject, LegoError> {
+        let data = info.read()?;
+        Ok(RawSampleObject {
+            info: Some(info),
+            data,
+        })
+    }
+
+    pub fn from_records(data: Vec<RawSample>) -> RawSampleObject {
         RawSampleObject {
-            info,
+            info: None,
             data,
         }
     }
+
+    pub fn info(&self) -> Option<&RawSampleInformation> {
+        self.info.as_ref()
+    }
+
+    pub fn data(&self) -> &[RawSample] {
+        &self.data
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RawSample> {
+        self.data.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    // samples of a hole in the order of the file
+    pub fn get_hole_raw_samples(&self, drill_no: &str) -> Vec<&RawSample> {
+        self.data.iter().filter(|r| r.drill_no == drill_no).collect()
+    }
 }
 
+#[derive(Clone)]
 pub struct RawSampleInformation {
     path: String,
     mining_type: String,
     seperator: char,
-    columns: HashMap<String, String>,  // we'l change here
+    columns: RawSampleHeaders,
 }
 
 impl RawSampleInformation {
-    fn new(path: String, mining_type: String, seperator: char,
-           columns: HashMap<String, String>) -> RawSampleInformation {
+    pub fn new(path: String, mining_type: String, seperator: char,
+               columns: RawSampleHeaders) -> RawSampleInformation {
         RawSampleInformation {
             path,
             mining_type,
@@ -40,47 +75,50 @@ impl RawSampleInformation {
         }
     }
 
-    pub(crate) fn new_from_config(config: &LegoConfig) -> RawSampleInformation {
+    pub fn new_from_config(config: &LegoConfig) -> Result<RawSampleInformation, LegoError> {
         // getting mining information
-        let path = config.get_rawsample_csv_path();
-        let mining_type = config.get_mining_type();
-        let seperator = config.get_x_seperator("rawsample_csv_seperator");
-        let columns = config.get_x_columns("rawsample_columns");
+        let path = config.get_rawsample_csv_path()?;
+        let mining_type = config.get_mining_type().to_string();
+        let seperator = config.get_mining_information().rawsample_csv_seperator;
+        let columns = config.get_settings().rawsample_columns.clone();
 
-        RawSampleInformation {
+        Ok(RawSampleInformation {
             path,
             mining_type,
             seperator,
             columns,
-        }
+        })
     }
 
-    fn read(&self) -> Result<Vec<RawSample>, Box<dyn Error>> {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn mining_type(&self) -> &str {
+        &self.mining_type
+    }
+
+    fn read(&self) -> Result<Vec<RawSample>, LegoError> {
         // our data
         let mut raw_sample_objects: Vec<RawSample> = vec![];
 
         let raw_sample_csv_path: &String = &self.path;
-        let mut reader = Reader::from_path(raw_sample_csv_path)?;
-
-        // columns
-        reader.set_headers(StringRecord::from(vec!["SONDAJNO", "FROM", "TO", "PERCENT"]));
-
-        let mut results = reader.records();
+        let mut reader = open_csv(raw_sample_csv_path, self.seperator)?;
 
-        // skip first row
-        results.next();
+        // columns are found by their configured headers
+        let headers = reader.headers().map_err(|e| csv_error(raw_sample_csv_path, e))?.clone();
+        let columns: CsvColumns<RawSampleColumns> = CsvColumns::new(raw_sample_csv_path, &headers, &self.columns)?;
 
-        for result in results {
-            let record = result?;
-            let record: Vec<String> = record[0].to_string().split(";").map(|s| s.to_string()).collect();
+        for result in reader.records() {
+            let record = result.map_err(|e| csv_error(raw_sample_csv_path, e))?;
 
-            let drill_no = record[0].to_owned();
-            let start: f64 = record[1].parse().expect("numerik deger çevirilemedi");
-            let end: f64 = record[2].parse().expect("numerik deger çevirilemedi");
-            let percent: f64 = record[3].parse().expect("numerik deger çevirilemedi");
+            let drill_no = columns.text(&record, RawSampleColumns::DRILLNO);
+            let start: f64 = columns.field(&record, RawSampleColumns::FROM)?;
+            let end: f64 = columns.field(&record, RawSampleColumns::TO)?;
+            let percent: f64 = columns.field(&record, RawSampleColumns::PERCENT)?;
 
             let rawsample_coordinate = RawSampleCoordinate::new(start, end, percent);
-            let d_row = RawSample::new(drill_no, rawsample_coordinate);
+            let d_row = RawSample::new(drill_no, rawsample_coordinate).with_extra(columns.extra(&record));
 
             raw_sample_objects.push(d_row);
         }
@@ -88,8 +126,7 @@ impl RawSampleInformation {
     }
 }
 
-#[allow(unused)]
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum RawSampleColumns {
     DRILLNO,
     FROM,
@@ -97,36 +134,97 @@ enum RawSampleColumns {
     PERCENT,
 }
 
-#[derive(Debug)]
-struct RawSample {
-    drill_no: String,
-    coordinate: RawSampleCoordinate,
+impl CsvColumn for RawSampleColumns {
+    type Headers = RawSampleHeaders;
+
+    const ALL: &'static [RawSampleColumns] = &[RawSampleColumns::DRILLNO, RawSampleColumns::FROM,
+        RawSampleColumns::TO, RawSampleColumns::PERCENT];
+
+    fn key(&self) -> &'static str {
+        match self {
+            RawSampleColumns::DRILLNO => "DRILLNO",
+            RawSampleColumns::FROM => "FROM",
+            RawSampleColumns::TO => "TO",
+            RawSampleColumns::PERCENT => "PERCENT",
+        }
+    }
+
+    fn header<'a>(&self, headers: &'a RawSampleHeaders) -> &'a str {
+        match self {
+            RawSampleColumns::DRILLNO => &headers.drill_no,
+            RawSampleColumns::FROM => &headers.from,
+            RawSampleColumns::TO => &headers.to,
+            RawSampleColumns::PERCENT => &headers.percent,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawSample {
+    pub(crate) drill_no: String,
+    pub(crate) coordinate: RawSampleCoordinate,
+    // columns of the file which are not mapped, header and value
+    pub(crate) extra: Vec<(String, String)>,
 }
 
 impl RawSample {
-    fn new(drill_no: String, coordinate: RawSampleCoordinate) -> RawSample {
+    pub fn new(drill_no: String, coordinate: RawSampleCoordinate) -> RawSample {
         RawSample {
             drill_no,
             coordinate,
+            extra: vec![],
         }
     }
+
+    pub fn with_extra(mut self, extra: Vec<(String, String)>) -> RawSample {
+        self.extra = extra;
+        self
+    }
+
+    pub fn drill_no(&self) -> &str {
+        &self.drill_no
+    }
+
+    pub fn coordinate(&self) -> &RawSampleCoordinate {
+        &self.coordinate
+    }
+
+    pub fn extra(&self) -> &[(String, String)] {
+        &self.extra
+    }
+
+    pub fn get_extra(&self, header: &str) -> Option<&str> {
+        self.extra.iter().find(|(h, _)| h == header).map(|(_, v)| v.as_str())
+    }
 }
 
-#[derive(Debug)]
-struct RawSampleCoordinate {
-    start: f64,
-    end: f64,
-    percent: f64,
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawSampleCoordinate {
+    pub(crate) start: f64,
+    pub(crate) end: f64,
+    pub(crate) percent: f64,
 }
 
 impl RawSampleCoordinate {
-    fn new(start: f64, end: f64, percent: f64) -> RawSampleCoordinate {
+    pub fn new(start: f64, end: f64, percent: f64) -> RawSampleCoordinate {
         RawSampleCoordinate {
             start,
             end,
             percent,
         }
     }
+
+    pub fn start(&self) -> f64 {
+        self.start
+    }
+
+    pub fn end(&self) -> f64 {
+        self.end
+    }
+
+    pub fn percent(&self) -> f64 {
+        self.percent
+    }
 }
 
 impl Display for RawSampleCoordinate {
@@ -149,7 +247,7 @@ impl Display for RawSampleInformation {
     path: String,
     mining_type: String,
     seperator: char,
-    columns: HashMap<String, String>,
+    columns: RawSampleHeaders,
     */
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "path : {} \n\
@@ -161,24 +259,42 @@ impl Display for RawSampleInformation {
 
 impl Display for RawSampleObject {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "info: {} \n\
-                   data : {:?}", self.info, self.data)
+        match &self.info {
+            Some(info) => writeln!(f, "info: {} ", info)?,
+            None => writeln!(f, "info: in memory ")?
+        }
+        write!(f, "data : {:?}", self.data)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::excels::rawsample_reader::{RawSampleInformation, RawSampleObject};
+    use crate::excels::rawsample_reader::{RawSample, RawSampleCoordinate, RawSampleInformation, RawSampleObject};
     use lego_config::read::LegoConfig;
 
-    const TEST_CONFIG_PATH: &str = "/home/umut/CLionProjects/LegoRust/lego_config/test_settings.toml";
+    const TEST_CONFIG_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../lego_config/test_settings.toml");
 
     #[test]
     fn read_rawsample_from_config() {
-        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH));
+        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
 
-        let rawsample_info = RawSampleInformation::new_from_config(&config_object);
-        let l_object = RawSampleObject::new(rawsample_info);
+        let rawsample_info = RawSampleInformation::new_from_config(&config_object).unwrap();
+        let l_object = RawSampleObject::new(rawsample_info).unwrap();
         println!("lytology : {}", l_object);
     }
+
+    #[test]
+    fn raw_samples_from_records() {
+        let r_object = RawSampleObject::from_records(vec![
+            RawSample::new(String::from("SK-01"), RawSampleCoordinate::new(0.0, 1.0, 0.4)),
+            RawSample::new(String::from("SK-02"), RawSampleCoordinate::new(0.0, 2.0, 1.2))
+                .with_extra(vec![(String::from("LAB"), String::from("ALS"))]),
+        ]);
+
+        let samples = r_object.get_hole_raw_samples("SK-02");
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].coordinate().percent(), 1.2);
+        assert_eq!(samples[0].get_extra("LAB"), Some("ALS"));
+        assert_eq!(r_object.iter().map(|r| r.coordinate().end() - r.coordinate().start()).sum::<f64>(), 3.0);
+    }
 }
\ No newline at end of file
@@ -1,39 +1,72 @@
-use std::collections::HashMap;
-use std::error::Error;
-use csv::{Reader, StringRecord};
 use std::hash::Hash;
 use std::fmt::{Display, Formatter};
 use std::fmt;
-use std::string::ToString;
+use lego_config::error::LegoError;
 use lego_config::read::{LegoConfig, DataManagementObjects};
+use lego_config::settings::SlopeHeaders;
 
-#[derive(Debug)]
+use crate::excels::excel_traits::{csv_error, open_csv, CsvColumn, CsvColumns};
+
+#[derive(Debug, Clone)]
 pub struct SlopeObject {
-    info: SlopeInformation,
-    data: Vec<Slope>,
+    // None for the slopes which are not read from a file
+    info: Option<SlopeInformation>,
+    pub(crate) data: Vec<Slope>,
 }
 
 impl SlopeObject {
-    pub(crate) fn new(info: SlopeInformation) -> SlopeObject {
-        let data = info.read().expect("Something went wrong while reading slope csv !");
+    pub fn new(info: SlopeInformation) -> Result<SlopeObject, LegoError> {
+        let data = info.read()?;
+        Ok(SlopeObject {
+            info: Some(info),
+            data,
+        })
+    }
+
+    pub fn from_records(data: Vec<Slope>) -> SlopeObject {
         SlopeObject {
-            info,
+            info: None,
             data,
         }
     }
+
+    pub fn info(&self) -> Option<&SlopeInformation> {
+        self.info.as_ref()
+    }
+
+    pub fn data(&self) -> &[Slope] {
+        &self.data
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Slope> {
+        self.data.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    // survey stations of a hole in the order of the file
+    pub fn get_hole_slopes(&self, drill_no: &str) -> Vec<&Slope> {
+        self.data.iter().filter(|s| s.drill_no == drill_no).collect()
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SlopeInformation {
     path: String,
     mining_type: String,
     seperator: char,
-    columns: HashMap<String, String>,  // we'll fix this later
+    columns: SlopeHeaders,
 }
 
 impl SlopeInformation {
-    fn new(path: String, mining_type: String, seperator: char,
-           columns: HashMap<String, String>) -> SlopeInformation {
+    pub fn new(path: String, mining_type: String, seperator: char,
+               columns: SlopeHeaders) -> SlopeInformation {
         SlopeInformation {
             path,
             mining_type,
@@ -42,46 +75,50 @@ impl SlopeInformation {
         }
     }
 
-    pub(crate) fn new_from_config(config: &LegoConfig) -> SlopeInformation {
-        let path = config.get_slope_csv_path();
-        let mining_type = config.get_mining_type();
-        let seperator = config.get_x_seperator("slope_csv_seperator");
-        let columns = config.get_x_columns("slope_columns");
+    pub fn new_from_config(config: &LegoConfig) -> Result<SlopeInformation, LegoError> {
+        let path = config.get_slope_csv_path()?;
+        let mining_type = config.get_mining_type().to_string();
+        let seperator = config.get_mining_information().slope_csv_seperator;
+        let columns = config.get_settings().slope_columns.clone();
 
-        SlopeInformation {
+        Ok(SlopeInformation {
             path,
             mining_type,
             seperator,
             columns
-        }
+        })
     }
-    fn read(&self) -> Result<Vec<Slope>, Box<dyn Error>> {
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn mining_type(&self) -> &str {
+        &self.mining_type
+    }
+
+    fn read(&self) -> Result<Vec<Slope>, LegoError> {
         // our data
         let mut slope_objects: Vec<Slope> = vec![];
 
         let slope_csv_path: &String = &self.path;
-        let mut reader = Reader::from_path(slope_csv_path)?;
-
-        // columns
-        reader.set_headers(StringRecord::from(vec!["SONDAJNO", "DERINLIK", "DALIM", "AZIMUTH"]));
+        let mut reader = open_csv(slope_csv_path, self.seperator)?;
 
-        let mut results = reader.records();
+        // columns are found by their configured headers
+        let headers = reader.headers().map_err(|e| csv_error(slope_csv_path, e))?.clone();
+        let columns: CsvColumns<SlopeColumns> = CsvColumns::new(slope_csv_path, &headers, &self.columns)?;
 
-        // skip first row
-        results.next();
+        for result in reader.records() {
+            let record = result.map_err(|e| csv_error(slope_csv_path, e))?;
 
-        for result in results {
-            let record = result?;
-            let record: Vec<String> = record[0].to_string().split(";").map(|s| s.to_string()).collect();
-
-            let drill_no = record[0].to_owned();
-            let depth: f64 = record[1].parse().expect("DEPTH numerik deger çevirilemedi");
-            let dip: f64 = record[2].parse().expect("DIP numerik deger çevirilemedi");
-            let azimuth: f64 = record[3].parse().expect("AZIMUTH numerik deger çevirilemedi");
+            let drill_no = columns.text(&record, SlopeColumns::DRILLNO);
+            let depth: f64 = columns.field(&record, SlopeColumns::DEPTH)?;
+            let dip: f64 = columns.field(&record, SlopeColumns::DIP)?;
+            let azimuth: f64 = columns.field(&record, SlopeColumns::AZIMUTH)?;
 
             // todo : bu castinglere bakalım
             let slope_coordinate = DrillSlopeInfo::new(depth, dip as i32, azimuth as i32);
-            let d_row = Slope::new(drill_no, slope_coordinate);
+            let d_row = Slope::new(drill_no, slope_coordinate).with_extra(columns.extra(&record));
 
             slope_objects.push(d_row);
         }
@@ -89,8 +126,7 @@ impl SlopeInformation {
     }
 }
 
-#[allow(unused)]
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum SlopeColumns {
     DRILLNO,
     DEPTH,
@@ -98,21 +134,58 @@ enum SlopeColumns {
     AZIMUTH,
 }
 
-#[derive(Debug)]
-struct DrillSlopeInfo {
-    depth: f64,
-    dalim: i32,
-    azimuth: i32,
+impl CsvColumn for SlopeColumns {
+    type Headers = SlopeHeaders;
+
+    const ALL: &'static [SlopeColumns] = &[SlopeColumns::DRILLNO, SlopeColumns::DEPTH,
+        SlopeColumns::DIP, SlopeColumns::AZIMUTH];
+
+    fn key(&self) -> &'static str {
+        match self {
+            SlopeColumns::DRILLNO => "DRILLNO",
+            SlopeColumns::DEPTH => "DEPTH",
+            SlopeColumns::DIP => "DIP",
+            SlopeColumns::AZIMUTH => "AZIMUTH",
+        }
+    }
+
+    fn header<'a>(&self, headers: &'a SlopeHeaders) -> &'a str {
+        match self {
+            SlopeColumns::DRILLNO => &headers.drill_no,
+            SlopeColumns::DEPTH => &headers.depth,
+            SlopeColumns::DIP => &headers.dip,
+            SlopeColumns::AZIMUTH => &headers.azimuth,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrillSlopeInfo {
+    pub(crate) depth: f64,
+    pub(crate) dalim: i32,
+    pub(crate) azimuth: i32,
 }
 
 impl DrillSlopeInfo {
-    fn new(depth: f64, dalim: i32, azimuth: i32) -> DrillSlopeInfo {
+    pub fn new(depth: f64, dalim: i32, azimuth: i32) -> DrillSlopeInfo {
         DrillSlopeInfo {
             depth,
             dalim,
             azimuth,
         }
     }
+
+    pub fn depth(&self) -> f64 {
+        self.depth
+    }
+
+    pub fn dip(&self) -> i32 {
+        self.dalim
+    }
+
+    pub fn azimuth(&self) -> i32 {
+        self.azimuth
+    }
 }
 
 impl Display for DrillSlopeInfo {
@@ -123,19 +196,43 @@ impl Display for DrillSlopeInfo {
     }
 }
 
-#[derive(Debug)]
-struct Slope {
-    drill_no: String,
-    drill_info: DrillSlopeInfo,
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slope {
+    pub(crate) drill_no: String,
+    pub(crate) drill_info: DrillSlopeInfo,
+    // columns of the file which are not mapped, header and value
+    pub(crate) extra: Vec<(String, String)>,
 }
 
 impl Slope {
-    fn new(drill_no: String, drill_info: DrillSlopeInfo) -> Slope {
+    pub fn new(drill_no: String, drill_info: DrillSlopeInfo) -> Slope {
         Slope {
             drill_no,
             drill_info,
+            extra: vec![],
         }
     }
+
+    pub fn with_extra(mut self, extra: Vec<(String, String)>) -> Slope {
+        self.extra = extra;
+        self
+    }
+
+    pub fn drill_no(&self) -> &str {
+        &self.drill_no
+    }
+
+    pub fn drill_info(&self) -> &DrillSlopeInfo {
+        &self.drill_info
+    }
+
+    pub fn extra(&self) -> &[(String, String)] {
+        &self.extra
+    }
+
+    pub fn get_extra(&self, header: &str) -> Option<&str> {
+        self.extra.iter().find(|(h, _)| h == header).map(|(_, v)| v.as_str())
+    }
 }
 
 impl Display for Slope {
@@ -147,17 +244,33 @@ impl Display for Slope {
 
 #[cfg(test)]
 mod tests {
-    use crate::excels::slope_reader::{SlopeInformation, SlopeObject};
+    use crate::excels::slope_reader::{DrillSlopeInfo, Slope, SlopeInformation, SlopeObject};
     use lego_config::read::LegoConfig;
 
-    const TEST_CONFIG_PATH: &str = "/home/umut/CLionProjects/LegoRust/lego_config/test_settings.toml";
+    const TEST_CONFIG_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../lego_config/test_settings.toml");
 
     #[test]
     fn read_slope_from_config () {
-        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH));
-        let slope_info = SlopeInformation::new_from_config(&config_object);
+        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
+        let slope_info = SlopeInformation::new_from_config(&config_object).unwrap();
 
-        let s_object = SlopeObject::new(slope_info);
+        let s_object = SlopeObject::new(slope_info).unwrap();
         println!("slope object : {:?}", s_object);
     }
+
+    #[test]
+    fn slopes_from_records() {
+        let s_object = SlopeObject::from_records(vec![
+            Slope::new(String::from("SK-01"), DrillSlopeInfo::new(0.0, -90, 0)),
+            Slope::new(String::from("SK-02"), DrillSlopeInfo::new(0.0, -60, 45)),
+            Slope::new(String::from("SK-01"), DrillSlopeInfo::new(120.0, -85, 10)),
+        ]);
+        assert!(s_object.info().is_none());
+        assert_eq!(s_object.len(), 3);
+
+        let stations = s_object.get_hole_slopes("SK-01");
+        assert_eq!(stations.len(), 2);
+        assert_eq!((stations[1].drill_info().depth(), stations[1].drill_info().dip()), (120.0, -85));
+        assert!(s_object.get_hole_slopes("SK-99").is_empty());
+    }
 }
\ No newline at end of file
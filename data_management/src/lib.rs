@@ -25,9 +25,63 @@ pub mod str {
 
     pub mod composite_reader;
 
+    pub mod str_file;
+
     pub mod str_traits;
 }
 
+pub mod drillhole {
+    pub mod database;
+
+    pub mod desurvey;
+
+    pub mod validation;
+
+    pub mod compositing;
+
+    pub mod intercepts;
+
+    pub mod intervals;
+}
+
+pub mod solid {
+    pub mod mesh;
+
+    pub mod loft;
+
+    pub mod obj;
+
+    pub mod report;
+}
+
+pub mod estimation {
+    pub mod sectional;
+
+    pub mod search;
+
+    pub mod idw;
+
+    pub mod variogram;
+
+    pub mod kriging;
+
+    pub mod variography;
+}
+
+pub mod block {
+    pub mod model;
+}
+
+pub mod project;
+
+pub mod statistics {
+    pub mod descriptive;
+
+    pub mod topcut;
+
+    pub mod declustering;
+}
+
 pub mod lego_tests {
     use lego_config::read::LegoConfig;
     use crate::excels::drill_reader::{DrillObject, DrillInformation};
@@ -38,65 +92,69 @@ pub mod lego_tests {
     use crate::str::cross_reader::info::{CrossInformation};
     use crate::str::composite_reader::{CompositeObject, CompositeInformation};
     use crate::str::str_traits::ICrossInformation;
+    use crate::drillhole::database::DrillholeDatabase;
 
-    const TEST_CONFIG_PATH: &str = "/home/umut/CLionProjects/LegoRust/lego_config/test_settings.toml";
+    const TEST_CONFIG_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../lego_config/test_settings.toml");
 
     pub fn give_me_test_drill () -> DrillObject {
-        let legoconfig: LegoConfig =  LegoConfig::new(String::from(TEST_CONFIG_PATH));
+        let legoconfig: LegoConfig =  LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
 
-        let d_info = DrillInformation::new_from_config(&legoconfig);
+        let d_info = DrillInformation::new_from_config(&legoconfig).unwrap();
 
-        let drill_object = DrillObject::new(d_info);
+        let drill_object = DrillObject::new(d_info).unwrap();
         drill_object
     }
 
     pub fn give_me_test_lytho () -> LythologyObject {
-        let legoconfig: LegoConfig =  LegoConfig::new(String::from(TEST_CONFIG_PATH));
+        let legoconfig: LegoConfig =  LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
 
-        let lytho_info = LythologyInformation::new_from_config(&legoconfig);
-        let l_object = LythologyObject::new(lytho_info);
+        let lytho_info = LythologyInformation::new_from_config(&legoconfig).unwrap();
+        let l_object = LythologyObject::new(lytho_info).unwrap();
 
         l_object
     }
 
     pub fn give_me_test_rawsample () -> RawSampleObject {
-        let legoconfig: LegoConfig =  LegoConfig::new(String::from(TEST_CONFIG_PATH));
+        let legoconfig: LegoConfig =  LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
 
-        let rawsample_info = RawSampleInformation::new_from_config(&legoconfig);
-        let r_object = RawSampleObject::new(rawsample_info);
+        let rawsample_info = RawSampleInformation::new_from_config(&legoconfig).unwrap();
+        let r_object = RawSampleObject::new(rawsample_info).unwrap();
 
         r_object
 
     }
 
     pub fn give_me_slope () -> SlopeObject {
-        let legoconfig: LegoConfig =  LegoConfig::new(String::from(TEST_CONFIG_PATH));
+        let legoconfig: LegoConfig =  LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
 
-        let slope_info = SlopeInformation::new_from_config(&legoconfig);
-        let s_object = SlopeObject::new(slope_info);
+        let slope_info = SlopeInformation::new_from_config(&legoconfig).unwrap();
+        let s_object = SlopeObject::new(slope_info).unwrap();
 
         s_object
     }
 
     pub fn give_me_cross () -> CrossObject {
-        let legoconfig: LegoConfig =  LegoConfig::new(String::from(TEST_CONFIG_PATH));
+        let legoconfig: LegoConfig =  LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
 
-        let cross_info = CrossInformation::new_from_config(&legoconfig);
-        let cross_object = CrossObject::new(cross_info, None);
+        let cross_info = CrossInformation::new_from_config(&legoconfig).unwrap();
+        let cross_object = CrossObject::new(cross_info, None).unwrap();
 
         cross_object
     }
 
     pub fn give_me_composite () -> CompositeObject {
-        let legoconfig: LegoConfig =  LegoConfig::new(String::from(TEST_CONFIG_PATH));
+        let legoconfig: LegoConfig =  LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
 
-        let composite_info = CompositeInformation::new_from_config(&legoconfig);
-        let l_object = CompositeObject::new(composite_info);
+        let composite_info = CompositeInformation::new_from_config(&legoconfig).unwrap();
+        let l_object = CompositeObject::new(composite_info).unwrap();
 
         l_object
 
     }
-}
-
 
+    pub fn give_me_drillhole_database () -> DrillholeDatabase {
+        let legoconfig: LegoConfig =  LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
 
+        DrillholeDatabase::new_from_config(&legoconfig).unwrap()
+    }
+}
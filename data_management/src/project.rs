@@ -0,0 +1,295 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::panic;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use lego_config::error::LegoError;
+use lego_config::read::{DataManagementObjects, LegoConfig};
+
+use crate::drillhole::database::DrillholeDatabase;
+use crate::drillhole::validation::ValidationReport;
+use crate::excels::drill_reader::{DrillInformation, DrillObject};
+use crate::excels::lytho_reader::{LythologyInformation, LythologyObject};
+use crate::excels::rawsample_reader::{RawSampleInformation, RawSampleObject};
+use crate::excels::slope_reader::{SlopeInformation, SlopeObject};
+use crate::str::composite_reader::{CompositeInformation, CompositeObject};
+use crate::str::cross_reader::cross_main::CrossObject;
+use crate::str::cross_reader::info::CrossInformation;
+use crate::str::str_traits::ICrossInformation;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetSummary {
+    pub name: &'static str,
+    // None when the dataset is not in the config
+    pub path: Option<String>,
+    pub rows: usize,
+    pub time: Duration,
+}
+
+impl DatasetSummary {
+    pub fn is_loaded(&self) -> bool {
+        self.path.is_some()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadSummary {
+    pub datasets: Vec<DatasetSummary>,
+    // wall time of the whole load, datasets are read at the same time
+    pub total_time: Duration,
+    pub warnings: Vec<String>,
+}
+
+impl LoadSummary {
+    pub fn get_dataset(&self, name: &str) -> Option<&DatasetSummary> {
+        self.datasets.iter().find(|d| d.name == name)
+    }
+}
+
+impl Display for LoadSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<12} {:>8} {:>10}  path", "dataset", "rows", "time (ms)")?;
+        for d in self.datasets.iter() {
+            match &d.path {
+                Some(path) => write!(f, "\n{:<12} {:>8} {:>10.1}  {}", d.name, d.rows,
+                                     d.time.as_secs_f64() * 1000.0, path)?,
+                None => write!(f, "\n{:<12} {:>8} {:>10}  not configured", d.name, "-", "-")?
+            }
+        }
+
+        write!(f, "\ntotal time : {:.1} ms", self.total_time.as_secs_f64() * 1000.0)?;
+        for w in self.warnings.iter() {
+            write!(f, "\nwarning : {}", w)?;
+        }
+        Ok(())
+    }
+}
+
+// Every dataset of a config, read in parallel. Datasets whose paths are left out of the
+// config are skipped.
+pub struct Project {
+    config: LegoConfig,
+    drills: Option<DrillObject>,
+    slopes: Option<SlopeObject>,
+    lythologies: Option<LythologyObject>,
+    raw_samples: Option<RawSampleObject>,
+    composites: Option<CompositeObject>,
+    crosses: Option<CrossObject>,
+    validation: Option<ValidationReport>,
+    database: Option<DrillholeDatabase>,
+    summary: LoadSummary,
+}
+
+impl Project {
+    pub fn open(config_path: &str) -> Result<Project, LegoError> {
+        Project::new(LegoConfig::new(config_path.to_string())?)
+    }
+
+    pub fn new(config: LegoConfig) -> Result<Project, LegoError> {
+        let start = Instant::now();
+        let excel_paths = config.get_excel_paths().clone();
+        let str_paths = config.get_str_paths().clone();
+
+        // information is taken from the config before the threads, only reading is parallel
+        let drill_info = configured(&excel_paths.drill_csv_path, || DrillInformation::new_from_config(&config))?;
+        let slope_info = configured(&excel_paths.slope_csv_path, || SlopeInformation::new_from_config(&config))?;
+        let lythology_info = configured(&excel_paths.lythology_csv_path,
+                                        || LythologyInformation::new_from_config(&config))?;
+        let raw_sample_info = configured(&excel_paths.rawsample_csv_path,
+                                         || RawSampleInformation::new_from_config(&config))?;
+        let composite_info = configured(&str_paths.composite_str_path,
+                                        || CompositeInformation::new_from_config(&config))?;
+        let cross_info = configured(&str_paths.cross_section_str_path,
+                                    || CrossInformation::new_from_config(&config))?;
+
+        let (drills, slopes, lythologies, raw_samples, composites, crosses) = thread::scope(|s| {
+            let drills = drill_info.map(|i| s.spawn(move || timed(|| DrillObject::new(i))));
+            let slopes = slope_info.map(|i| s.spawn(move || timed(|| SlopeObject::new(i))));
+            let lythologies = lythology_info.map(|i| s.spawn(move || timed(|| LythologyObject::new(i))));
+            let raw_samples = raw_sample_info.map(|i| s.spawn(move || timed(|| RawSampleObject::new(i))));
+            let composites = composite_info.map(|i| s.spawn(move || timed(|| CompositeObject::new(i))));
+            let crosses = cross_info.map(|i| s.spawn(move || timed(|| CrossObject::new(i, None))));
+
+            (join(drills), join(slopes), join(lythologies), join(raw_samples), join(composites), join(crosses))
+        });
+
+        let drills = drills.transpose()?;
+        let slopes = slopes.transpose()?;
+        let lythologies = lythologies.transpose()?;
+        let raw_samples = raw_samples.transpose()?;
+        let composites = composites.transpose()?;
+        let crosses = crosses.transpose()?;
+
+        let datasets = vec![
+            dataset("drill", &excel_paths.drill_csv_path, drills.as_ref().map(|(o, t)| (o.data.len(), *t))),
+            dataset("slope", &excel_paths.slope_csv_path, slopes.as_ref().map(|(o, t)| (o.data.len(), *t))),
+            dataset("lythology", &excel_paths.lythology_csv_path,
+                    lythologies.as_ref().map(|(o, t)| (o.data.len(), *t))),
+            dataset("raw sample", &excel_paths.rawsample_csv_path,
+                    raw_samples.as_ref().map(|(o, t)| (o.data.len(), *t))),
+            dataset("composite", &str_paths.composite_str_path,
+                    composites.as_ref().map(|(o, t)| (o.data.len(), *t))),
+            dataset("cross", &str_paths.cross_section_str_path, crosses.as_ref().map(|(o, t)| (o.data.len(), *t))),
+        ];
+
+        let mut warnings: Vec<String> = datasets.iter()
+            .filter(|d| d.is_loaded() && d.rows == 0)
+            .map(|d| format!("{} dataset has no rows", d.name))
+            .collect();
+
+        let drills = drills.map(|(o, _)| o);
+        let slopes = slopes.map(|(o, _)| o);
+        let lythologies = lythologies.map(|(o, _)| o);
+        let raw_samples = raw_samples.map(|(o, _)| o);
+
+        // holes can only be checked and desurveyed when all of the drillhole tables are there
+        let (validation, database) = match (&drills, &slopes, &lythologies, &raw_samples) {
+            (Some(d), Some(s), Some(l), Some(r)) => (Some(ValidationReport::new(d, s, l, r)),
+                Some(DrillholeDatabase::new(d.clone(), s.clone(), l.clone(), r.clone()))),
+            _ => (None, None)
+        };
+        if let Some(report) = &validation {
+            if !report.issues().is_empty() {
+                warnings.push(format!("validation found {} errors and {} warnings", report.errors().len(),
+                                      report.warnings().len()));
+            }
+        }
+
+        Ok(Project {
+            config,
+            drills,
+            slopes,
+            lythologies,
+            raw_samples,
+            composites: composites.map(|(o, _)| o),
+            crosses: crosses.map(|(o, _)| o),
+            validation,
+            database,
+            summary: LoadSummary {
+                datasets,
+                total_time: start.elapsed(),
+                warnings,
+            },
+        })
+    }
+
+    pub fn config(&self) -> &LegoConfig {
+        &self.config
+    }
+
+    pub fn drills(&self) -> Option<&DrillObject> {
+        self.drills.as_ref()
+    }
+
+    pub fn slopes(&self) -> Option<&SlopeObject> {
+        self.slopes.as_ref()
+    }
+
+    pub fn lythologies(&self) -> Option<&LythologyObject> {
+        self.lythologies.as_ref()
+    }
+
+    pub fn raw_samples(&self) -> Option<&RawSampleObject> {
+        self.raw_samples.as_ref()
+    }
+
+    pub fn composites(&self) -> Option<&CompositeObject> {
+        self.composites.as_ref()
+    }
+
+    pub fn crosses(&self) -> Option<&CrossObject> {
+        self.crosses.as_ref()
+    }
+
+    // None unless all of the drillhole tables are configured
+    pub fn validation(&self) -> Option<&ValidationReport> {
+        self.validation.as_ref()
+    }
+
+    // None unless all of the drillhole tables are configured
+    pub fn drillhole_database(&self) -> Option<&DrillholeDatabase> {
+        self.database.as_ref()
+    }
+
+    pub fn summary(&self) -> &LoadSummary {
+        &self.summary
+    }
+}
+
+fn configured<T, F: FnOnce() -> Result<T, LegoError>>(path: &Option<String>, f: F) -> Result<Option<T>, LegoError> {
+    match path {
+        Some(_) => f().map(Some),
+        None => Ok(None)
+    }
+}
+
+fn timed<T, F: FnOnce() -> Result<T, LegoError>>(f: F) -> Result<(T, Duration), LegoError> {
+    let start = Instant::now();
+    let loaded = f()?;
+    Ok((loaded, start.elapsed()))
+}
+
+// panics of the readers are carried to the caller
+fn join<T>(handle: Option<thread::ScopedJoinHandle<'_, T>>) -> Option<T> {
+    handle.map(|h| h.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+}
+
+fn dataset(name: &'static str, path: &Option<String>, loaded: Option<(usize, Duration)>) -> DatasetSummary {
+    let (rows, time) = loaded.unwrap_or((0, Duration::default()));
+    DatasetSummary {
+        name,
+        path: path.clone(),
+        rows,
+        time,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use crate::project::Project;
+
+    const TEST_CONFIG_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../lego_config/test_settings.toml");
+    const MAPPED_CSV_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../tests/data/excels_mapped/collars.csv");
+
+    #[test]
+    fn open_test_project() {
+        let project = Project::open(TEST_CONFIG_PATH).unwrap();
+        println!("{}", project.summary());
+
+        let summary = project.summary();
+        assert_eq!(summary.datasets.len(), 6);
+        assert!(summary.datasets.iter().all(|d| d.is_loaded()));
+        assert_eq!(summary.get_dataset("drill").unwrap().rows, 73);
+        assert_eq!(summary.get_dataset("raw sample").unwrap().rows, 1197);
+        assert_eq!(project.drills().unwrap().data.len(), 73);
+        assert!(project.validation().is_some());
+        assert_eq!(project.drillhole_database().unwrap().drillholes().count(), 73);
+        assert!(std::ptr::eq(project.drillhole_database().unwrap(), project.drillhole_database().unwrap()));
+    }
+
+    #[test]
+    fn skip_absent_datasets() {
+        // only the collars are configured
+        let config = format!("[excel_paths]\ndrill_csv_path = \"{}\"\n\
+                              [mining_information]\nmining_type = \"cu\"\ndrill_csv_seperator = ','\n\
+                              [drill_columns]\nDRILLNO = \"HOLE\"\nX = \"EAST\"\nY = \"NORTH\"\nZ = \"ELEV\"\n\
+                              DEPTH = \"LENGTH\"\n", MAPPED_CSV_PATH);
+        let path = env::temp_dir().join(format!("lego_project_collars_{}.toml", std::process::id()));
+        fs::write(&path, config).unwrap();
+
+        let project = Project::open(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        let project = project.unwrap();
+        println!("{}", project.summary());
+
+        assert_eq!(project.drills().unwrap().data.len(), 2);
+        assert!(project.raw_samples().is_none() && project.crosses().is_none());
+        assert!(project.validation().is_none() && project.drillhole_database().is_none());
+        assert_eq!(project.summary().datasets.iter().filter(|d| d.is_loaded()).count(), 1);
+        assert!(project.summary().warnings.is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+use lego_config::error::LegoError;
+
+use crate::solid::mesh::{Solid, SolidGroup, SolidVertex};
+use crate::str::cross_reader::cross::Cross;
+
+// tolerance in meters while removing repeated vertices
+const VERTEX_TOLERANCE: f64 = 1e-6;
+
+// vertex indices of one level outline inside the solid, counter clockwise seen from above
+struct Ring {
+    indices: Vec<usize>,
+    center: (f64, f64),
+}
+
+// Builds a closed solid from level outlines. Crosses are sorted by their mean Z, every
+// successive pair is connected with a band of triangles and the lowest and highest
+// outlines are closed with caps.
+pub fn loft_crosses(crosses: &[Cross]) -> Result<Solid, LegoError> {
+    if crosses.len() < 2 {
+        return Err(LegoError::geometry(format!("at least two crosses are needed to build a solid, got {}",
+                                     crosses.len())));
+    }
+
+    let mut solid = Solid::new();
+    let mut group = SolidGroup::new(String::from("grp1"));
+    let mut rings: Vec<Ring> = vec![];
+
+    let mut sorted: Vec<(f64, &Cross)> = crosses.iter().map(|c| (level(c), c)).collect();
+    if let Some((_, c)) = sorted.iter().find(|(z, _)| !z.is_finite()) {
+        return Err(LegoError::geometry(format!("cross {} has no finite level", c.group_no)));
+    }
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    for (_, cross) in sorted {
+        let ring = add_ring(&mut solid, cross)?;
+        rings.push(ring);
+    }
+
+    for pair in rings.windows(2) {
+        connect_rings(&solid.vertices, &pair[0], &pair[1], &mut group.faces);
+    }
+
+    // bottom cap looks downwards, so its triangles are reversed
+    for [a, b, c] in triangulate_ring(&solid.vertices, &rings[0]) {
+        group.faces.push([a, c, b]);
+    }
+    group.faces.extend(triangulate_ring(&solid.vertices, rings.last().unwrap()));
+
+    solid.groups.push(group);
+    Ok(solid)
+}
+
+fn level(cross: &Cross) -> f64 {
+    cross.coordinate.iter().map(|c| c.z_coord).sum::<f64>() / cross.coordinate.len() as f64
+}
+
+fn add_ring(solid: &mut Solid, cross: &Cross) -> Result<Ring, LegoError> {
+    let mut points: Vec<SolidVertex> = vec![];
+
+    for c in cross.coordinate.iter() {
+        let vertex = SolidVertex::new(c.x_coord, c.y_coord, c.z_coord);
+        match points.last() {
+            Some(last) if distance_2d(last, &vertex) < VERTEX_TOLERANCE => {}
+            _ => points.push(vertex)
+        }
+    }
+
+    // outlines are closed by repeating the first vertex
+    while points.len() > 1 && distance_2d(&points[0], points.last().unwrap()) < VERTEX_TOLERANCE {
+        points.pop();
+    }
+
+    if points.len() < 3 {
+        return Err(LegoError::geometry(format!("cross {} has less than 3 distinct vertices", cross.group_no)));
+    }
+
+    if signed_area(&points) < 0.0 {
+        points.reverse();
+    }
+
+    let count = points.len() as f64;
+    let center = (points.iter().map(|p| p.x_coord).sum::<f64>() / count,
+                  points.iter().map(|p| p.y_coord).sum::<f64>() / count);
+
+    let first = solid.vertices.len();
+    solid.vertices.extend(points);
+
+    Ok(Ring {
+        indices: (first..solid.vertices.len()).collect(),
+        center,
+    })
+}
+
+fn connect_rings(vertices: &[SolidVertex], lower: &Ring, upper: &Ring, faces: &mut Vec<[usize; 3]>) {
+    // rings are compared after moving both centers to the origin, so shifted levels
+    // are still connected to their corresponding vertices
+    let distance = |l: usize, u: usize| {
+        let (lv, uv) = (&vertices[l], &vertices[u]);
+        let dx = (lv.x_coord - lower.center.0) - (uv.x_coord - upper.center.0);
+        let dy = (lv.y_coord - lower.center.1) - (uv.y_coord - upper.center.1);
+        dx * dx + dy * dy
+    };
+
+    let (n, m) = (lower.indices.len(), upper.indices.len());
+    let l = |i: usize| lower.indices[i % n];
+
+    // upper vertex closest to the first lower one starts the band
+    let offset = (0..m)
+        .min_by(|&a, &b| distance(l(0), upper.indices[a])
+            .partial_cmp(&distance(l(0), upper.indices[b])).unwrap())
+        .unwrap();
+    let u = |j: usize| upper.indices[(j + offset) % m];
+
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        let advance_lower = if i == n {
+            false
+        } else if j == m {
+            true
+        } else {
+            distance(l(i + 1), u(j)) <= distance(l(i), u(j + 1))
+        };
+
+        if advance_lower {
+            faces.push([l(i), l(i + 1), u(j)]);
+            i += 1;
+        } else {
+            faces.push([l(i), u(j + 1), u(j)]);
+            j += 1;
+        }
+    }
+}
+
+// ear clipping on the xy plane
+fn triangulate_ring(vertices: &[SolidVertex], ring: &Ring) -> Vec<[usize; 3]> {
+    let mut remaining: Vec<usize> = ring.indices.clone();
+    let mut triangles: Vec<[usize; 3]> = vec![];
+
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let corner = |k: usize| (remaining[(k + count - 1) % count], remaining[k], remaining[(k + 1) % count]);
+
+        let ear = (0..count).find(|&k| {
+            let (a, b, c) = corner(k);
+            cross_2d(&vertices[a], &vertices[b], &vertices[c]) > 0.0 &&
+                remaining.iter()
+                    .filter(|&&p| p != a && p != b && p != c)
+                    .all(|&p| !inside_triangle(&vertices[p], &vertices[a], &vertices[b], &vertices[c]))
+        });
+
+        // degenerated outlines may not have a proper ear, taking the most convex corner then
+        let k = ear.unwrap_or_else(|| {
+            (0..count)
+                .max_by(|&x, &y| {
+                    let (a1, b1, c1) = corner(x);
+                    let (a2, b2, c2) = corner(y);
+                    cross_2d(&vertices[a1], &vertices[b1], &vertices[c1])
+                        .partial_cmp(&cross_2d(&vertices[a2], &vertices[b2], &vertices[c2])).unwrap()
+                })
+                .unwrap()
+        });
+
+        let (a, b, c) = corner(k);
+        triangles.push([a, b, c]);
+        remaining.remove(k);
+    }
+
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+fn signed_area(points: &[SolidVertex]) -> f64 {
+    let mut area = 0.0;
+    for k in 0..points.len() {
+        let (p, q) = (&points[k], &points[(k + 1) % points.len()]);
+        area += p.x_coord * q.y_coord - q.x_coord * p.y_coord;
+    }
+    area / 2.0
+}
+
+fn cross_2d(a: &SolidVertex, b: &SolidVertex, c: &SolidVertex) -> f64 {
+    (b.x_coord - a.x_coord) * (c.y_coord - b.y_coord) - (b.y_coord - a.y_coord) * (c.x_coord - b.x_coord)
+}
+
+fn inside_triangle(p: &SolidVertex, a: &SolidVertex, b: &SolidVertex, c: &SolidVertex) -> bool {
+    cross_2d(a, b, p) >= 0.0 && cross_2d(b, c, p) >= 0.0 && cross_2d(c, a, p) >= 0.0
+}
+
+fn distance_2d(a: &SolidVertex, b: &SolidVertex) -> f64 {
+    ((a.x_coord - b.x_coord).powi(2) + (a.y_coord - b.y_coord).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lego_tests::give_me_cross;
+    use crate::solid::loft::loft_crosses;
+    use crate::str::cross_reader::coordinate::CrossCoordinate3d;
+    use crate::str::cross_reader::cross::Cross;
+
+    fn square(group_no: i32, z: f64, size: f64) -> Cross {
+        let corners = [(0.0, 0.0), (size, 0.0), (size, size), (0.0, size), (0.0, 0.0)];
+        let coordinates = corners.iter().enumerate()
+            .map(|(i, &(x, y))| CrossCoordinate3d::new(x, y, z, i as i32 + 1))
+            .collect();
+        Cross::new(group_no, coordinates)
+    }
+
+    #[test]
+    fn loft_two_squares() {
+        let solid = loft_crosses(&[square(1, 0.0, 1.0), square(2, 1.0, 2.0)]).unwrap();
+
+        assert_eq!(solid.vertex_count(), 8);
+        assert_eq!(solid.face_count(), 12);
+        assert!(solid.is_watertight());
+    }
+
+    #[test]
+    fn loft_test_crosses() {
+        let cross_object = give_me_cross();
+        let solid = loft_crosses(&cross_object.data).unwrap();
+        println!("solid : {}", solid);
+
+        assert!(solid.is_watertight());
+        assert_eq!(solid.face_count(), 2 * solid.vertex_count() - 4);
+    }
+
+    #[test]
+    fn loft_unsorted_crosses() {
+        let sorted = loft_crosses(&[square(1, 0.0, 1.0), square(2, 1.0, 2.0), square(3, 2.0, 1.0)]).unwrap();
+        let unsorted = loft_crosses(&[square(3, 2.0, 1.0), square(1, 0.0, 1.0), square(2, 1.0, 2.0)]).unwrap();
+
+        assert!(unsorted.is_watertight());
+        assert_eq!(unsorted.vertices, sorted.vertices);
+        assert_eq!(unsorted.groups[0].faces, sorted.groups[0].faces);
+    }
+
+    #[test]
+    fn need_two_crosses() {
+        assert!(loft_crosses(&[square(1, 0.0, 1.0)]).is_err());
+    }
+}
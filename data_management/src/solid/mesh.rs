@@ -0,0 +1,262 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::str::cross_reader::common::Extent;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolidVertex {
+    pub x_coord: f64,
+    pub y_coord: f64,
+    pub z_coord: f64,
+}
+
+impl SolidVertex {
+    pub fn new(x: f64, y: f64, z: f64) -> SolidVertex {
+        SolidVertex {
+            x_coord: x,
+            y_coord: y,
+            z_coord: z,
+        }
+    }
+}
+
+impl Display for SolidVertex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "x : {} \n\
+                   y : {} \n\
+                   z : {}", self.x_coord, self.y_coord, self.z_coord)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolidMaterial {
+    pub name: String,
+    pub ambient: [f64; 3],
+    pub diffuse: [f64; 3],
+    pub specular: [f64; 3],
+    pub shininess: Option<f64>,
+    pub transparency: Option<f64>,
+    pub illumination: Option<i32>,
+}
+
+impl SolidMaterial {
+    pub fn new(name: String) -> SolidMaterial {
+        SolidMaterial {
+            name,
+            ambient: [1.0, 1.0, 1.0],
+            diffuse: [1.0, 1.0, 1.0],
+            specular: [1.0, 1.0, 1.0],
+            shininess: None,
+            transparency: None,
+            illumination: None,
+        }
+    }
+}
+
+// faces are triangles of 0 based vertex indices, counter clockwise seen from outside.
+// face_normals is either empty or has one normal index triple for each face.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolidGroup {
+    pub name: String,
+    pub material: Option<String>,
+    pub points: Vec<usize>,
+    pub faces: Vec<[usize; 3]>,
+    pub face_normals: Vec<[usize; 3]>,
+}
+
+impl SolidGroup {
+    pub fn new(name: String) -> SolidGroup {
+        SolidGroup {
+            name,
+            material: None,
+            points: vec![],
+            faces: vec![],
+            face_normals: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Solid {
+    pub vertices: Vec<SolidVertex>,
+    pub normals: Vec<[f64; 3]>,
+    pub groups: Vec<SolidGroup>,
+    pub materials: Vec<SolidMaterial>,
+    // mtllib name of the obj file
+    pub material_library: Option<String>,
+}
+
+impl Solid {
+    pub fn new() -> Solid {
+        Solid {
+            vertices: vec![],
+            normals: vec![],
+            groups: vec![],
+            materials: vec![],
+            material_library: None,
+        }
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.groups.iter().map(|g| g.faces.len()).sum()
+    }
+
+    pub fn get_group(&self, name: &str) -> Option<&SolidGroup> {
+        self.groups.iter().find(|g| g.name == name)
+    }
+
+    pub fn get_material(&self, name: &str) -> Option<&SolidMaterial> {
+        self.materials.iter().find(|m| m.name == name)
+    }
+
+    pub fn faces(&self) -> impl Iterator<Item=&[usize; 3]> {
+        self.groups.iter().flat_map(|g| g.faces.iter())
+    }
+
+    pub fn is_watertight(&self) -> bool {
+        // closed and consistently oriented: every edge is used once in each direction
+        let mut edges: HashMap<(usize, usize), i32> = HashMap::new();
+
+        for face in self.faces() {
+            for k in 0..3 {
+                let (a, b) = (face[k], face[(k + 1) % 3]);
+                *edges.entry((a, b)).or_insert(0) += 1;
+            }
+        }
+
+        edges.iter().all(|(&(a, b), &count)| count == 1 && edges.get(&(b, a)) == Some(&1))
+    }
+
+    pub fn extent(&self) -> Option<Extent> {
+        vertex_extent(self.vertices.iter())
+    }
+
+    // Vertices closer than the tolerance are merged, faces which collapse or repeat an earlier
+    // face are dropped. LegoVis writes every group with its own vertices and repeats the faces
+    // of earlier groups.
+    pub fn welded(&self, tolerance: f64) -> Solid {
+        let mut welded = Solid {
+            vertices: vec![],
+            normals: self.normals.clone(),
+            groups: vec![],
+            materials: self.materials.clone(),
+            material_library: self.material_library.clone(),
+        };
+
+        let mut keys: HashMap<(i64, i64, i64), usize> = HashMap::new();
+        let index: Vec<usize> = self.vertices.iter()
+            .map(|v| {
+                let key = ((v.x_coord / tolerance).round() as i64, (v.y_coord / tolerance).round() as i64,
+                           (v.z_coord / tolerance).round() as i64);
+                *keys.entry(key).or_insert_with(|| {
+                    welded.vertices.push(*v);
+                    welded.vertices.len() - 1
+                })
+            })
+            .collect();
+
+        let mut seen: HashSet<[usize; 3]> = HashSet::new();
+        for group in self.groups.iter() {
+            let mut g = SolidGroup::new(group.name.clone());
+            g.material = group.material.clone();
+            g.points = group.points.iter().map(|&p| index[p]).collect();
+
+            let with_normals = group.face_normals.len() == group.faces.len();
+            for (k, face) in group.faces.iter().enumerate() {
+                let face = [index[face[0]], index[face[1]], index[face[2]]];
+                let mut sorted = face;
+                sorted.sort_unstable();
+
+                if sorted[0] == sorted[1] || sorted[1] == sorted[2] || !seen.insert(sorted) {
+                    continue;
+                }
+
+                g.faces.push(face);
+                if with_normals {
+                    g.face_normals.push(group.face_normals[k]);
+                }
+            }
+            welded.groups.push(g);
+        }
+
+        welded
+    }
+
+    // counts the faces crossed by a ray leaving the point, odd means inside. meaningful
+    // only for closed solids. the ray is slightly skewed not to run along edges.
+    pub fn contains_point(&self, x: f64, y: f64, z: f64) -> bool {
+        let direction = [1.0, 1.234e-3, 2.345e-3];
+        let mut crossings = 0;
+
+        for face in self.faces() {
+            let [a, b, c] = [&self.vertices[face[0]], &self.vertices[face[1]], &self.vertices[face[2]]];
+            let ab = [b.x_coord - a.x_coord, b.y_coord - a.y_coord, b.z_coord - a.z_coord];
+            let ac = [c.x_coord - a.x_coord, c.y_coord - a.y_coord, c.z_coord - a.z_coord];
+
+            // moller trumbore
+            let p = cross(&direction, &ac);
+            let det = dot(&ab, &p);
+            if det.abs() < 1e-12 {
+                continue;
+            }
+
+            let t = [x - a.x_coord, y - a.y_coord, z - a.z_coord];
+            let u = dot(&t, &p) / det;
+            if !(0.0..=1.0).contains(&u) {
+                continue;
+            }
+
+            let q = cross(&t, &ab);
+            let v = dot(&direction, &q) / det;
+            if v < 0.0 || u + v > 1.0 {
+                continue;
+            }
+
+            if dot(&ac, &q) / det > 0.0 {
+                crossings += 1;
+            }
+        }
+
+        crossings % 2 == 1
+    }
+}
+
+pub(crate) fn vertex_extent<'a>(mut vertices: impl Iterator<Item=&'a SolidVertex>) -> Option<Extent> {
+    let first = vertices.next()?;
+    let mut extent = Extent::new(first.x_coord, first.y_coord, first.z_coord,
+                                 first.x_coord, first.y_coord, first.z_coord);
+
+    for v in vertices {
+        extent.min_x = extent.min_x.min(v.x_coord);
+        extent.min_y = extent.min_y.min(v.y_coord);
+        extent.min_z = extent.min_z.min(v.z_coord);
+        extent.max_x = extent.max_x.max(v.x_coord);
+        extent.max_y = extent.max_y.max(v.y_coord);
+        extent.max_z = extent.max_z.max(v.z_coord);
+    }
+
+    Some(extent)
+}
+
+pub(crate) fn dot(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+impl Display for Solid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let names: Vec<&String> = self.groups.iter().map(|g| &g.name).collect();
+
+        write!(f, "vertices : {} \n\
+                   faces : {} \n\
+                   groups : {:?}", self.vertex_count(), self.face_count(), names)
+    }
+}
@@ -0,0 +1,370 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use lego_config::error::LegoError;
+
+use crate::solid::mesh::{Solid, SolidGroup, SolidMaterial, SolidVertex};
+
+impl Solid {
+    // Reads a wavefront obj file with its mtl library. Legacy LegoVis files refer to the
+    // library with an absolute windows path, so it is also searched next to the obj file.
+    pub fn read_obj(path: &str) -> Result<Solid, LegoError> {
+        let file = File::open(path).map_err(|e| LegoError::io(path, e))?;
+        let reader = BufReader::new(file);
+
+        let mut solid = Solid::new();
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| LegoError::io(path, e))?;
+            let line_no = index + 1;
+            let record = line.trim();
+
+            if record.is_empty() || record.starts_with('#') {
+                continue;
+            }
+
+            let (keyword, rest) = match record.find(char::is_whitespace) {
+                Some(i) => (&record[..i], record[i..].trim()),
+                None => (record, "")
+            };
+
+            match keyword {
+                "mtllib" => solid.material_library = Some(rest.to_string()),
+                "v" => {
+                    let [x, y, z] = parse_triple(rest, path, line_no)?;
+                    solid.vertices.push(SolidVertex::new(x, y, z));
+                }
+                "vn" => solid.normals.push(parse_triple(rest, path, line_no)?),
+                "g" => {
+                    let name = if rest.is_empty() { "default" } else { rest };
+                    solid.groups.push(SolidGroup::new(name.to_string()));
+                }
+                "usemtl" => current_group(&mut solid).material = Some(rest.to_string()),
+                "p" => {
+                    let vertex_count = solid.vertices.len();
+                    let mut points: Vec<usize> = vec![];
+                    for token in rest.split_whitespace() {
+                        points.push(parse_index(token, vertex_count, path, line_no)?);
+                    }
+                    current_group(&mut solid).points.extend(points);
+                }
+                "f" => {
+                    let (vertex_count, normal_count) = (solid.vertices.len(), solid.normals.len());
+                    let mut corners: Vec<(usize, Option<usize>)> = vec![];
+
+                    for token in rest.split_whitespace() {
+                        // v, v/vt, v//vn or v/vt/vn
+                        let mut parts = token.split('/');
+                        let vertex = parse_index(parts.next().unwrap_or(""), vertex_count, path, line_no)?;
+                        // vtk refers to normals with vertex numbers, which may point past the
+                        // written normals. such faces are read without normals.
+                        let normal = match parts.nth(1) {
+                            Some(n) if !n.is_empty() => resolve_index(n, normal_count, path, line_no)?,
+                            _ => None
+                        };
+                        corners.push((vertex, normal));
+                    }
+
+                    if corners.len() < 3 {
+                        // a face needs at least 3 vertices
+                        return Err(LegoError::parse(path, line_no, "face", rest));
+                    }
+
+                    let group = current_group(&mut solid);
+                    // polygons are split into a triangle fan
+                    for k in 1..corners.len() - 1 {
+                        let triangle = [corners[0], corners[k], corners[k + 1]];
+
+                        let normals: Option<Vec<usize>> = triangle.iter().map(|c| c.1).collect();
+                        match normals {
+                            Some(n) if group.face_normals.len() == group.faces.len() => {
+                                group.face_normals.push([n[0], n[1], n[2]]);
+                            }
+                            // normals are kept only if every face of the group has them
+                            _ => group.face_normals.clear()
+                        }
+
+                        group.faces.push([triangle[0].0, triangle[1].0, triangle[2].0]);
+                    }
+                }
+                // texture coordinates, smoothing groups etc. are not used
+                _ => {}
+            }
+        }
+
+        if let Some(library) = &solid.material_library {
+            if let Some(mtl_path) = find_material_library(library, path) {
+                solid.materials = read_mtl(&mtl_path)
+                    .map_err(|e| LegoError::io(&mtl_path.to_string_lossy(), e))?;
+            }
+        }
+
+        Ok(solid)
+    }
+
+    // Writes the solid as obj, and its materials as an mtl file next to it.
+    pub fn write_obj(&self, path: &str) -> Result<(), LegoError> {
+        let mut mtl_name = None;
+        if !self.materials.is_empty() {
+            let mtl_path = Path::new(path).with_extension("mtl");
+            write_mtl(&self.materials, &mtl_path).map_err(|e| LegoError::io(&mtl_path.to_string_lossy(), e))?;
+            mtl_name = mtl_path.file_name().map(|n| n.to_string_lossy().to_string());
+        }
+
+        self.write_obj_records(path, mtl_name.as_deref()).map_err(|e| LegoError::io(path, e))
+    }
+
+    fn write_obj_records(&self, path: &str, mtl_name: Option<&str>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "# wavefront obj file written by LegoRust")?;
+        writeln!(writer)?;
+
+        if let Some(mtl_name) = mtl_name {
+            writeln!(writer, "mtllib {}", mtl_name)?;
+            writeln!(writer)?;
+        }
+
+        for v in self.vertices.iter() {
+            writeln!(writer, "v {} {} {}", v.x_coord, v.y_coord, v.z_coord)?;
+        }
+        for n in self.normals.iter() {
+            writeln!(writer, "vn {} {} {}", n[0], n[1], n[2])?;
+        }
+
+        for group in self.groups.iter() {
+            writeln!(writer, "g {}", group.name)?;
+            if let Some(material) = &group.material {
+                writeln!(writer, "usemtl {}", material)?;
+            }
+
+            for p in group.points.iter() {
+                writeln!(writer, "p {}", p + 1)?;
+            }
+
+            let with_normals = !group.face_normals.is_empty() && group.face_normals.len() == group.faces.len();
+            for (k, face) in group.faces.iter().enumerate() {
+                if with_normals {
+                    let n = group.face_normals[k];
+                    writeln!(writer, "f {}//{} {}//{} {}//{}", face[0] + 1, n[0] + 1,
+                             face[1] + 1, n[1] + 1, face[2] + 1, n[2] + 1)?;
+                } else {
+                    writeln!(writer, "f {} {} {}", face[0] + 1, face[1] + 1, face[2] + 1)?;
+                }
+            }
+        }
+
+        writer.flush()
+    }
+}
+
+fn current_group(solid: &mut Solid) -> &mut SolidGroup {
+    // records before the first group statement
+    if solid.groups.is_empty() {
+        solid.groups.push(SolidGroup::new(String::from("default")));
+    }
+    solid.groups.last_mut().unwrap()
+}
+
+fn parse_triple(rest: &str, path: &str, line_no: usize) -> Result<[f64; 3], LegoError> {
+    let values: Vec<&str> = rest.split_whitespace().collect();
+    if values.len() < 3 {
+        return Err(LegoError::parse(path, line_no, "coordinates", rest));
+    }
+
+    let mut triple = [0.0; 3];
+    for k in 0..3 {
+        triple[k] = LegoError::parse_field(values[k], path, line_no, "coordinate")?;
+    }
+    Ok(triple)
+}
+
+fn parse_index(token: &str, count: usize, path: &str, line_no: usize) -> Result<usize, LegoError> {
+    match resolve_index(token, count, path, line_no)? {
+        Some(index) => Ok(index),
+        // out of range
+        None => Err(LegoError::parse(path, line_no, "index", token))
+    }
+}
+
+fn resolve_index(token: &str, count: usize, path: &str, line_no: usize) -> Result<Option<usize>, LegoError> {
+    let index: i64 = LegoError::parse_field(token, path, line_no, "index")?;
+
+    // obj indices are 1 based, negative ones are relative to the end
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+
+    if resolved < 0 || resolved >= count as i64 {
+        Ok(None)
+    } else {
+        Ok(Some(resolved as usize))
+    }
+}
+
+fn find_material_library(library: &str, obj_path: &str) -> Option<PathBuf> {
+    let given = PathBuf::from(library);
+    if given.is_file() {
+        return Some(given);
+    }
+
+    let file_name = library.rsplit(['/', '\\']).next()?;
+    let next_to_obj = Path::new(obj_path).parent()?.join(file_name);
+    if next_to_obj.is_file() {
+        Some(next_to_obj)
+    } else {
+        None
+    }
+}
+
+fn read_mtl(path: &Path) -> Result<Vec<SolidMaterial>, io::Error> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut materials: Vec<SolidMaterial> = vec![];
+
+    for line in reader.lines() {
+        let line = line?;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        if tokens.is_empty() || tokens[0].starts_with('#') {
+            continue;
+        }
+
+        if tokens[0] == "newmtl" {
+            materials.push(SolidMaterial::new(tokens[1..].join(" ")));
+            continue;
+        }
+
+        let material = match materials.last_mut() {
+            Some(m) => m,
+            None => continue
+        };
+
+        // more than one statement may share a line, like "Tr 0.3 illum 3"
+        let mut k = 0;
+        while k < tokens.len() {
+            let keyword = tokens[k];
+            let values: Vec<f64> = tokens[k + 1..].iter()
+                .map_while(|t| t.parse::<f64>().ok())
+                .collect();
+
+            match keyword {
+                "Ka" if values.len() >= 3 => material.ambient = [values[0], values[1], values[2]],
+                "Kd" if values.len() >= 3 => material.diffuse = [values[0], values[1], values[2]],
+                "Ks" if values.len() >= 3 => material.specular = [values[0], values[1], values[2]],
+                "Ns" if !values.is_empty() => material.shininess = Some(values[0]),
+                "Tr" if !values.is_empty() => material.transparency = Some(values[0]),
+                "d" if !values.is_empty() => material.transparency = Some(1.0 - values[0]),
+                "illum" if !values.is_empty() => material.illumination = Some(values[0] as i32),
+                _ => {}
+            }
+
+            k += 1 + values.len();
+        }
+    }
+
+    Ok(materials)
+}
+
+fn write_mtl(materials: &[SolidMaterial], path: &Path) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "# wavefront mtl file written by LegoRust")?;
+
+    for m in materials {
+        writeln!(writer)?;
+        writeln!(writer, "newmtl {}", m.name)?;
+        writeln!(writer, "Ka {} {} {}", m.ambient[0], m.ambient[1], m.ambient[2])?;
+        writeln!(writer, "Kd {} {} {}", m.diffuse[0], m.diffuse[1], m.diffuse[2])?;
+        writeln!(writer, "Ks {} {} {}", m.specular[0], m.specular[1], m.specular[2])?;
+        if let Some(ns) = m.shininess {
+            writeln!(writer, "Ns {}", ns)?;
+        }
+        if let Some(tr) = m.transparency {
+            writeln!(writer, "Tr {}", tr)?;
+        }
+        if let Some(illum) = m.illumination {
+            writeln!(writer, "illum {}", illum)?;
+        }
+    }
+
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use lego_config::error::LegoError;
+
+    use crate::lego_tests::give_me_cross;
+    use crate::solid::mesh::Solid;
+
+    const TEST_OBJ_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../tests/data/3d/solid_11.obj");
+
+    #[test]
+    fn read_legovis_solid() {
+        let solid = Solid::read_obj(TEST_OBJ_PATH).unwrap();
+        println!("solid : {}", solid);
+
+        assert_eq!(solid.vertex_count(), 19778);
+        assert_eq!(solid.normals.len(), 14894);
+        assert_eq!(solid.groups.len(), 180);
+        assert_eq!(solid.face_count(), 5522);
+        assert_eq!(solid.groups.iter().map(|g| g.points.len()).sum::<usize>(), 4884);
+        assert_eq!(solid.materials.len(), 180);
+
+        let group = solid.get_group("grp43").unwrap();
+        assert_eq!(group.material.as_deref(), Some("mtl43"));
+        assert_eq!(group.faces[0], [42, 63, 64]);
+
+        let material = solid.get_material("mtl1").unwrap();
+        assert_eq!(material.transparency, Some(0.3));
+        assert_eq!(material.illumination, Some(3));
+    }
+
+    #[test]
+    fn write_and_read_back() {
+        let solid = Solid::read_obj(TEST_OBJ_PATH).unwrap();
+        let path = std::env::temp_dir().join("lego_solid_test.obj");
+        let path = path.to_str().unwrap();
+
+        solid.write_obj(path).unwrap();
+        let read_back = Solid::read_obj(path).unwrap();
+
+        assert_eq!(read_back.vertices, solid.vertices);
+        assert_eq!(read_back.normals, solid.normals);
+        assert_eq!(read_back.groups, solid.groups);
+        assert_eq!(read_back.materials, solid.materials);
+    }
+
+    #[test]
+    fn export_lofted_solid() {
+        let solid = give_me_cross().build_solid().unwrap();
+        let path = std::env::temp_dir().join("lego_lofted_test.obj");
+        let path = path.to_str().unwrap();
+
+        solid.write_obj(path).unwrap();
+        let read_back = Solid::read_obj(path).unwrap();
+
+        assert_eq!(read_back.face_count(), solid.face_count());
+        assert!(read_back.is_watertight());
+    }
+
+    #[test]
+    fn wrong_obj_records() {
+        let path = std::env::temp_dir().join(format!("lego_wrong_{}.obj", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        fs::write(path, "v 0 0 0\nv 1 0 0\nv 0 1 x\n").unwrap();
+        let coordinate = Solid::read_obj(path).unwrap_err();
+        fs::write(path, "v 0 0 0\nv 1 0 0\n\nf 1 2\n").unwrap();
+        let face = Solid::read_obj(path).unwrap_err();
+        fs::write(path, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n").unwrap();
+        let index = Solid::read_obj(path).unwrap_err();
+        fs::remove_file(path).unwrap();
+
+        assert!(matches!(coordinate, LegoError::Parse { line: 3, .. }));
+        assert!(matches!(face, LegoError::Parse { line: 4, .. }));
+        assert!(matches!(index, LegoError::Parse { line: 4, ref value, .. } if value == "4"));
+        assert!(matches!(Solid::read_obj("missing.obj"), Err(LegoError::Io { .. })));
+    }
+}
@@ -0,0 +1,480 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::solid::mesh::{Solid, SolidGroup, SolidVertex, vertex_extent, dot, cross};
+use crate::str::cross_reader::common::Extent;
+
+// vertices closer than this are merged before measuring, in meters
+const WELD_TOLERANCE: f64 = 1e-6;
+// columns along the longer side while estimating the volume of open solids
+const COLUMN_COUNT: usize = 256;
+
+// densities in t/m3. groups are assigned to lythologies, lythologies to densities.
+#[derive(Debug, Clone)]
+pub struct DensityTable {
+    default_density: f64,
+    lythology_densities: HashMap<String, f64>,
+    group_lythologies: HashMap<String, String>,
+}
+
+impl DensityTable {
+    pub fn new(default_density: f64) -> DensityTable {
+        DensityTable {
+            default_density,
+            lythology_densities: HashMap::new(),
+            group_lythologies: HashMap::new(),
+        }
+    }
+
+    pub fn set_lythology_density(&mut self, lytho: &str, density: f64) {
+        self.lythology_densities.insert(lytho.to_string(), density);
+    }
+
+    pub fn assign_group(&mut self, group_name: &str, lytho: &str) {
+        self.group_lythologies.insert(group_name.to_string(), lytho.to_string());
+    }
+
+    pub fn get_group_lythology(&self, group_name: &str) -> Option<&String> {
+        self.group_lythologies.get(group_name)
+    }
+
+    pub fn get_group_density(&self, group_name: &str) -> f64 {
+        self.group_lythologies.get(group_name)
+            .and_then(|l| self.lythology_densities.get(l))
+            .cloned()
+            .unwrap_or(self.default_density)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupReport {
+    pub name: String,
+    pub lytho: Option<String>,
+    pub face_count: usize,
+    pub closed: bool,
+    // groups of a closed solid get their signed share of its volume. open groups of an open
+    // solid have no volume and tonnage, only the groups closed on their own have a centroid.
+    pub volume: Option<f64>,
+    pub surface_area: f64,
+    pub centroid: Option<[f64; 3]>,
+    pub extent: Option<Extent>,
+    pub density: f64,
+    pub tonnage: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SolidReport {
+    pub groups: Vec<GroupReport>,
+    // whole solid is watertight after welding its vertices
+    pub closed: bool,
+    // volume of an open solid is estimated on vertical columns
+    pub estimated: bool,
+    pub volume: Option<f64>,
+    pub surface_area: f64,
+    pub centroid: Option<[f64; 3]>,
+    pub extent: Option<Extent>,
+    // None for the estimated volumes of groups with different densities
+    pub tonnage: Option<f64>,
+}
+
+impl SolidReport {
+    pub fn new(solid: &Solid, densities: &DensityTable) -> SolidReport {
+        // legovis groups have their own copies of the shared vertices
+        let welded = solid.welded(WELD_TOLERANCE);
+        let closed = welded.face_count() > 0 && welded.is_watertight();
+
+        // tetrahedrons are built on a point near the solid, far away origins (like utm
+        // coordinates) loses precision
+        let reference = welded.vertices.first().cloned().unwrap_or_else(|| SolidVertex::new(0.0, 0.0, 0.0));
+
+        let group_closed: Vec<bool> = welded.groups.iter().map(is_group_closed).collect();
+        let mut integrals: Vec<Integral> = welded.groups.iter()
+            .map(|g| Integral::of_faces(&welded.vertices, g.faces.iter(), &reference))
+            .collect();
+
+        // groups whose faces look inwards
+        for (integral, _) in integrals.iter_mut().zip(group_closed.iter()).filter(|(_, &c)| c) {
+            integral.make_positive();
+        }
+
+        // open groups of a closed solid enclose the rest of the volume together, their signed
+        // volumes do not depend on the reference point once they are added up
+        if closed {
+            let open_volume: f64 = integrals.iter().zip(group_closed.iter())
+                .filter(|(_, &c)| !c)
+                .map(|(i, _)| i.volume)
+                .sum();
+            if open_volume < 0.0 {
+                for (integral, _) in integrals.iter_mut().zip(group_closed.iter()).filter(|(_, &c)| !c) {
+                    integral.flip();
+                }
+            }
+        }
+
+        let mut groups: Vec<GroupReport> = vec![];
+        for (k, group) in solid.groups.iter().enumerate() {
+            let integral = &integrals[k];
+            let density = densities.get_group_density(&group.name);
+            let volume = if closed || group_closed[k] { Some(integral.volume) } else { None };
+
+            groups.push(GroupReport {
+                name: group.name.clone(),
+                lytho: densities.get_group_lythology(&group.name).cloned(),
+                face_count: group.faces.len(),
+                closed: group_closed[k],
+                volume,
+                surface_area: integral.area,
+                centroid: if group_closed[k] { integral.centroid(&reference) } else { None },
+                extent: group_extent(&solid.vertices, group),
+                density,
+                tonnage: volume.map(|v| v * density),
+            });
+        }
+
+        let mut total = Integral::default();
+        for integral in integrals.iter() {
+            total.add(integral);
+        }
+
+        // solids made of groups closed on their own are measured exactly as well
+        let exact = closed || (group_closed.iter().any(|&c| c) &&
+            welded.groups.iter().zip(group_closed.iter()).all(|(g, &c)| c || g.faces.is_empty()));
+
+        let (volume, centroid, tonnage, estimated) = if exact {
+            (Some(total.volume), total.centroid(&reference), Some(groups.iter().filter_map(|g| g.tonnage).sum()),
+             false)
+        } else {
+            match estimate_volume(&welded) {
+                Some((volume, centroid)) => (Some(volume), Some(centroid),
+                                             common_density(&groups).map(|d| volume * d), true),
+                None => (None, None, None, false)
+            }
+        };
+
+        SolidReport {
+            groups,
+            closed,
+            estimated,
+            volume,
+            surface_area: total.area,
+            centroid,
+            extent: solid.extent(),
+            tonnage,
+        }
+    }
+
+    pub fn get_group_report(&self, name: &str) -> Option<&GroupReport> {
+        self.groups.iter().find(|g| g.name == name)
+    }
+}
+
+impl Display for SolidReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<12} {:<12} {:>8} {:>8} {:>16} {:>14} {:>8} {:>16}", "group", "lytho", "faces",
+                 "closed", "volume", "area", "density", "tonnage")?;
+
+        for g in self.groups.iter() {
+            writeln!(f, "{:<12} {:<12} {:>8} {:>8} {:>16} {:>14.2} {:>8.2} {:>16}", g.name,
+                     g.lytho.as_deref().unwrap_or("-"), g.face_count, g.closed, optional(g.volume),
+                     g.surface_area, g.density, optional(g.tonnage))?;
+        }
+
+        write!(f, "{:<12} {:<12} {:>8} {:>8} {:>16} {:>14.2} {:>8} {:>16}", "total", "-",
+               self.groups.iter().map(|g| g.face_count).sum::<usize>(), self.closed, optional(self.volume),
+               self.surface_area, "-", optional(self.tonnage))?;
+
+        if self.estimated {
+            write!(f, "\nvolume of the open solid is estimated on vertical columns")?;
+        }
+        Ok(())
+    }
+}
+
+fn optional(value: Option<f64>) -> String {
+    match value {
+        Some(v) => format!("{:.2}", v),
+        None => String::from("-")
+    }
+}
+
+// volume, area and first moments of a set of triangles
+#[derive(Debug, Default)]
+struct Integral {
+    volume: f64,
+    area: f64,
+    moment: [f64; 3],
+}
+
+impl Integral {
+    fn of_faces<'a>(vertices: &[SolidVertex], faces: impl Iterator<Item=&'a [usize; 3]>,
+                    reference: &SolidVertex) -> Integral {
+        let mut integral = Integral::default();
+        let relative = |i: usize| {
+            let v = &vertices[i];
+            [v.x_coord - reference.x_coord, v.y_coord - reference.y_coord, v.z_coord - reference.z_coord]
+        };
+
+        for face in faces {
+            let (a, b, c) = (relative(face[0]), relative(face[1]), relative(face[2]));
+
+            // signed volume of the tetrahedron with the reference point
+            let volume = dot(&a, &cross(&b, &c)) / 6.0;
+            integral.volume += volume;
+            for k in 0..3 {
+                integral.moment[k] += volume * (a[k] + b[k] + c[k]) / 4.0;
+            }
+
+            let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+            let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+            integral.area += dot(&cross(&ab, &ac), &cross(&ab, &ac)).sqrt() / 2.0;
+        }
+
+        integral
+    }
+
+    fn make_positive(&mut self) {
+        if self.volume < 0.0 {
+            self.flip();
+        }
+    }
+
+    fn flip(&mut self) {
+        self.volume = -self.volume;
+        for k in 0..3 {
+            self.moment[k] = -self.moment[k];
+        }
+    }
+
+    fn add(&mut self, other: &Integral) {
+        self.volume += other.volume;
+        self.area += other.area;
+        for k in 0..3 {
+            self.moment[k] += other.moment[k];
+        }
+    }
+
+    fn centroid(&self, reference: &SolidVertex) -> Option<[f64; 3]> {
+        if self.volume.abs() < 1e-12 {
+            return None;
+        }
+
+        Some([reference.x_coord + self.moment[0] / self.volume,
+            reference.y_coord + self.moment[1] / self.volume,
+            reference.z_coord + self.moment[2] / self.volume])
+    }
+}
+
+fn is_group_closed(group: &SolidGroup) -> bool {
+    let mut solid = Solid::new();
+    solid.groups.push(group.clone());
+    !group.faces.is_empty() && solid.is_watertight()
+}
+
+// Open solids are measured on vertical columns, every column is inside between its lowest
+// and highest crossing. Solids lofted from level outlines are crossed once at the bottom and
+// once at the top, so the level caps between the slabs and the repeated patches do not matter.
+fn estimate_volume(solid: &Solid) -> Option<(f64, [f64; 3])> {
+    let extent = solid.extent()?;
+    let (width, height) = (extent.max_x - extent.min_x, extent.max_y - extent.min_y);
+    let cell = width.max(height) / COLUMN_COUNT as f64;
+    if !cell.is_finite() || cell <= 0.0 {
+        return None;
+    }
+
+    let nx = (width / cell).ceil().max(1.0) as usize;
+    let ny = (height / cell).ceil().max(1.0) as usize;
+    let mut columns: Vec<Option<(f64, f64)>> = vec![None; nx * ny];
+
+    // column i is centered on min_x + (i + 0.5) * cell
+    let first = |min: f64, origin: f64| ((min - origin) / cell - 0.5).ceil().max(0.0) as usize;
+    let end = |max: f64, origin: f64, count: usize| ((max - origin) / cell + 0.5).floor()
+        .clamp(0.0, count as f64) as usize;
+
+    for face in solid.faces() {
+        let [a, b, c] = [&solid.vertices[face[0]], &solid.vertices[face[1]], &solid.vertices[face[2]]];
+        let det = (b.y_coord - c.y_coord) * (a.x_coord - c.x_coord) + (c.x_coord - b.x_coord) * (a.y_coord - c.y_coord);
+        // vertical faces are not crossed by the columns
+        if det.abs() < 1e-12 {
+            continue;
+        }
+
+        let (min_x, max_x) = (a.x_coord.min(b.x_coord).min(c.x_coord), a.x_coord.max(b.x_coord).max(c.x_coord));
+        let (min_y, max_y) = (a.y_coord.min(b.y_coord).min(c.y_coord), a.y_coord.max(b.y_coord).max(c.y_coord));
+
+        for j in first(min_y, extent.min_y)..end(max_y, extent.min_y, ny) {
+            for i in first(min_x, extent.min_x)..end(max_x, extent.min_x, nx) {
+                let x = extent.min_x + (i as f64 + 0.5) * cell;
+                let y = extent.min_y + (j as f64 + 0.5) * cell;
+
+                let l1 = ((b.y_coord - c.y_coord) * (x - c.x_coord) + (c.x_coord - b.x_coord) * (y - c.y_coord)) / det;
+                let l2 = ((c.y_coord - a.y_coord) * (x - c.x_coord) + (a.x_coord - c.x_coord) * (y - c.y_coord)) / det;
+                let l3 = 1.0 - l1 - l2;
+                if l1 < 0.0 || l2 < 0.0 || l3 < 0.0 {
+                    continue;
+                }
+
+                let z = l1 * a.z_coord + l2 * b.z_coord + l3 * c.z_coord;
+                let column = &mut columns[j * nx + i];
+                *column = Some(match column {
+                    Some((low, high)) => (low.min(z), high.max(z)),
+                    None => (z, z)
+                });
+            }
+        }
+    }
+
+    let mut volume = 0.0;
+    let mut moment = [0.0; 3];
+    for (k, (low, high)) in columns.iter().enumerate().filter_map(|(k, c)| c.map(|c| (k, c))) {
+        let v = (high - low) * cell * cell;
+        volume += v;
+        moment[0] += v * ((k % nx) as f64 + 0.5) * cell;
+        moment[1] += v * ((k / nx) as f64 + 0.5) * cell;
+        moment[2] += v * ((low + high) / 2.0 - extent.min_z);
+    }
+
+    if volume <= 0.0 {
+        return None;
+    }
+
+    Some((volume, [extent.min_x + moment[0] / volume, extent.min_y + moment[1] / volume,
+        extent.min_z + moment[2] / volume]))
+}
+
+// density of every group with faces, None when they differ
+fn common_density(groups: &[GroupReport]) -> Option<f64> {
+    let mut densities = groups.iter().filter(|g| g.face_count > 0).map(|g| g.density);
+    let first = densities.next()?;
+    if densities.all(|d| d == first) {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+fn group_extent(vertices: &[SolidVertex], group: &SolidGroup) -> Option<Extent> {
+    vertex_extent(group.faces.iter()
+        .flat_map(|f| f.iter())
+        .chain(group.points.iter())
+        .map(|&i| &vertices[i]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lego_tests::give_me_cross;
+    use crate::solid::loft::loft_crosses;
+    use crate::solid::mesh::{Solid, SolidGroup};
+    use crate::solid::report::{DensityTable, SolidReport};
+    use crate::str::cross_reader::coordinate::CrossCoordinate3d;
+    use crate::str::cross_reader::cross::Cross;
+
+    const TEST_OBJ_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../tests/data/3d/solid_11.obj");
+
+    fn square(group_no: i32, z: f64) -> Cross {
+        let corners = [(10.0, 10.0), (12.0, 10.0), (12.0, 12.0), (10.0, 12.0)];
+        let coordinates = corners.iter().enumerate()
+            .map(|(i, &(x, y))| CrossCoordinate3d::new(x, y, z, i as i32 + 1))
+            .collect();
+        Cross::new(group_no, coordinates)
+    }
+
+    #[test]
+    fn report_of_box() {
+        let solid = loft_crosses(&[square(1, 100.0), square(2, 103.0)]).unwrap();
+
+        let mut densities = DensityTable::new(1.0);
+        densities.set_lythology_density("kalkopirit", 2.5);
+        densities.assign_group("grp1", "kalkopirit");
+
+        let report = SolidReport::new(&solid, &densities);
+        println!("{}", report);
+
+        assert!(report.closed);
+        assert!((report.volume.unwrap() - 12.0).abs() < 1e-9);
+        assert!((report.surface_area - 32.0).abs() < 1e-9);
+        assert!((report.tonnage.unwrap() - 30.0).abs() < 1e-9);
+
+        let centroid = report.centroid.unwrap();
+        assert!((centroid[0] - 11.0).abs() < 1e-9);
+        assert!((centroid[2] - 101.5).abs() < 1e-9);
+
+        let extent = report.extent.unwrap();
+        assert_eq!((extent.min_z, extent.max_z), (100.0, 103.0));
+    }
+
+    #[test]
+    fn report_of_lofted_crosses() {
+        let solid = give_me_cross().build_solid().unwrap();
+        let report = SolidReport::new(&solid, &DensityTable::new(2.7));
+        println!("{}", report);
+
+        assert!(report.closed);
+        let volume = report.volume.unwrap();
+        assert!(volume > 0.0);
+        assert!((report.tonnage.unwrap() - volume * 2.7).abs() < 1e-6);
+    }
+
+    #[test]
+    fn report_of_welded_patches() {
+        let lofted = loft_crosses(&[square(1, 100.0), square(2, 103.0)]).unwrap();
+
+        // every face with its own vertices, split into two patches and a repeated first patch
+        let mut solid = Solid::new();
+        for (k, name) in ["bottom", "top", "snapshot"].iter().enumerate() {
+            let faces = &lofted.groups[0].faces;
+            let half = if k == 1 { &faces[faces.len() / 2..] } else { &faces[..faces.len() / 2] };
+
+            let mut group = SolidGroup::new(name.to_string());
+            for face in half {
+                let first = solid.vertices.len();
+                solid.vertices.extend(face.iter().map(|&i| lofted.vertices[i]));
+                group.faces.push([first, first + 1, first + 2]);
+            }
+            solid.groups.push(group);
+        }
+        assert!(!solid.is_watertight());
+
+        let mut densities = DensityTable::new(1.0);
+        densities.set_lythology_density("kalkopirit", 2.5);
+        densities.assign_group("top", "kalkopirit");
+
+        let report = SolidReport::new(&solid, &densities);
+        println!("{}", report);
+
+        assert!(report.closed && !report.estimated);
+        assert!((report.volume.unwrap() - 12.0).abs() < 1e-9);
+        assert!((report.surface_area - 32.0).abs() < 1e-9);
+        assert_eq!(report.get_group_report("snapshot").unwrap().volume, Some(0.0));
+
+        let shares: f64 = report.groups.iter().map(|g| g.volume.unwrap()).sum();
+        assert!((shares - 12.0).abs() < 1e-9);
+        let top = report.get_group_report("top").unwrap();
+        assert!((report.tonnage.unwrap() - (12.0 + 1.5 * top.volume.unwrap())).abs() < 1e-9);
+        assert!((report.centroid.unwrap()[2] - 101.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn report_of_legovis_solid() {
+        let solid = Solid::read_obj(TEST_OBJ_PATH).unwrap();
+        let report = SolidReport::new(&solid, &DensityTable::new(2.7));
+        println!("{}", report);
+
+        assert_eq!(report.groups.len(), 180);
+        assert!(report.groups.iter().all(|g| !g.closed && g.volume.is_none() && g.tonnage.is_none()));
+
+        // the groups are repeated patches of slabs lofted between levels, they stay open after
+        // welding and the volume is estimated
+        assert!(!report.closed && report.estimated);
+        let volume = report.volume.unwrap();
+        assert!((report.tonnage.unwrap() - volume * 2.7).abs() < 1e-6 * volume);
+
+        // legovis lofted the same crosses
+        let lofted = SolidReport::new(&give_me_cross().build_solid().unwrap(), &DensityTable::new(2.7));
+        assert!((volume / lofted.volume.unwrap() - 1.0).abs() < 0.1, "{} {}", volume, lofted.volume.unwrap());
+
+        let centroid = report.centroid.unwrap();
+        let extent = report.extent.unwrap();
+        assert!(centroid[2] > extent.min_z && centroid[2] < extent.max_z);
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use lego_config::error::LegoError;
+
+use crate::estimation::search::Sample;
+use crate::excels::excel_traits::{report_error, report_text, report_writer, write_report};
+use crate::statistics::descriptive::DomainValue;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeclusteringParameters {
+    // cell sizes along x, tried from min_cell to max_cell in cell_count steps
+    pub min_cell: f64,
+    pub max_cell: f64,
+    pub cell_count: usize,
+    // grid origins tried for every cell size, weights are averaged over them
+    pub offsets: usize,
+    // y and z sizes of the cells relative to the x size
+    pub anisotropy: [f64; 2],
+    // true when the data is clustered in the high grades, so the lowest mean is taken
+    pub minimise: bool,
+}
+
+impl DeclusteringParameters {
+    pub fn new(min_cell: f64, max_cell: f64, cell_count: usize) -> DeclusteringParameters {
+        DeclusteringParameters {
+            min_cell,
+            max_cell,
+            cell_count,
+            offsets: 4,
+            anisotropy: [1.0, 1.0],
+            minimise: true,
+        }
+    }
+
+    pub fn cell_sizes(&self) -> Vec<f64> {
+        if self.cell_count == 1 {
+            return vec![self.min_cell];
+        }
+
+        let step = (self.max_cell - self.min_cell) / (self.cell_count - 1) as f64;
+        (0..self.cell_count).map(|i| self.min_cell + step * i as f64).collect()
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), LegoError> {
+        if self.min_cell <= 0.0 || self.max_cell < self.min_cell || self.cell_count == 0 {
+            return Err(LegoError::parameter(format!("cell sizes from {} to {} in {} steps are not valid",
+                                                    self.min_cell, self.max_cell, self.cell_count)));
+        }
+
+        if self.offsets == 0 || self.anisotropy.iter().any(|a| *a <= 0.0) {
+            return Err(LegoError::parameter("offset count and cell anisotropy have to be positive"));
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for DeclusteringParameters {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "cell sizes : {} - {} ({} steps) \n\
+                   offsets : {} \n\
+                   anisotropy : {} {} \n\
+                   minimise : {}", self.min_cell, self.max_cell, self.cell_count, self.offsets,
+               self.anisotropy[0], self.anisotropy[1], self.minimise)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeclusteringPoint {
+    pub cell_size: f64,
+    pub mean: f64,
+}
+
+// weight of one sample with the hole and interval it is given to
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleWeight {
+    pub hole: String,
+    pub interval: Option<(f64, f64)>,
+    pub weight: f64,
+}
+
+// Cell declustering of the samples. Weights are in the order of the samples and their mean is 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declustering {
+    pub parameters: DeclusteringParameters,
+    pub naive_mean: f64,
+    pub curve: Vec<DeclusteringPoint>,
+    pub cell_size: f64,
+    pub mean: f64,
+    pub weights: Vec<SampleWeight>,
+}
+
+impl Declustering {
+    pub fn new(samples: &[Sample], parameters: DeclusteringParameters) -> Result<Declustering, LegoError> {
+        parameters.validate()?;
+        if samples.is_empty() {
+            return Err(LegoError::parameter("there are no samples to decluster"));
+        }
+
+        let naive_mean = samples.iter().map(|s| s.value).sum::<f64>() / samples.len() as f64;
+
+        let mut curve: Vec<DeclusteringPoint> = vec![];
+        let mut best: Option<(f64, f64, Vec<f64>)> = None;
+
+        for cell_size in parameters.cell_sizes() {
+            let weights = cell_weights(samples, cell_size, &parameters);
+            let mean = weighted_mean(samples, &weights);
+            curve.push(DeclusteringPoint { cell_size, mean });
+
+            let better = match &best {
+                None => true,
+                Some((_, best_mean, _)) => if parameters.minimise { mean < *best_mean } else { mean > *best_mean }
+            };
+            if better {
+                best = Some((cell_size, mean, weights));
+            }
+        }
+
+        let (cell_size, mean, weights) = best.unwrap();
+        let weights = samples.iter().zip(weights)
+            .map(|(s, weight)| SampleWeight { hole: s.hole.clone(), interval: s.interval, weight })
+            .collect();
+
+        Ok(Declustering {
+            parameters,
+            naive_mean,
+            curve,
+            cell_size,
+            mean,
+            weights,
+        })
+    }
+
+    // copies the weights to the values taken from the same hole and interval, every value
+    // has to have a weight
+    pub fn apply(&self, values: &mut [DomainValue]) -> Result<(), LegoError> {
+        let weights: HashMap<(&str, u64, u64), f64> = self.weights.iter()
+            .filter_map(|w| w.interval
+                .map(|(from, to)| ((w.hole.as_str(), from.to_bits(), to.to_bits()), w.weight)))
+            .collect();
+
+        for v in values.iter_mut() {
+            let weight = v.source.as_ref()
+                .and_then(|(hole, from, to)| weights.get(&(hole.as_str(), from.to_bits(), to.to_bits())));
+
+            match (weight, &v.source) {
+                (Some(w), _) => v.weight = *w,
+                (None, Some((hole, from, to))) =>
+                    return Err(LegoError::parameter(format!("value of {} {}-{} has no declustering weight",
+                                                            hole, from, to))),
+                (None, None) =>
+                    return Err(LegoError::parameter("values without a hole and interval cannot be weighted"))
+            }
+        }
+        Ok(())
+    }
+
+    // declustered mean vs cell size
+    pub fn to_csv(&self, seperator: char) -> Result<String, LegoError> {
+        let mut writer = report_writer(seperator);
+
+        writer.write_record(["cell_size", "mean"]).map_err(report_error)?;
+        for p in self.curve.iter() {
+            writer.write_record(&[p.cell_size.to_string(), p.mean.to_string()]).map_err(report_error)?;
+        }
+
+        report_text(writer)
+    }
+
+    pub fn write_csv(&self, path: &str, seperator: char) -> Result<(), LegoError> {
+        write_report(path, &self.to_csv(seperator)?)
+    }
+}
+
+impl Display for Declustering {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "naive mean : {:.4} \n\
+                   cell size : {:.2} \n\
+                   declustered mean : {:.4} \n\
+                   {:>10} {:>10}", self.naive_mean, self.cell_size, self.mean, "cell", "mean")?;
+
+        for p in self.curve.iter() {
+            write!(f, "\n{:>10.2} {:>10.4}", p.cell_size, p.mean)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Every occupied cell gets the same total weight, shared by the samples in it. The grid origin
+// is shifted diagonally for every offset and the weights are averaged.
+pub(crate) fn cell_weights(samples: &[Sample], cell_size: f64, parameters: &DeclusteringParameters) -> Vec<f64> {
+    let size = [cell_size, cell_size * parameters.anisotropy[0], cell_size * parameters.anisotropy[1]];
+    let min = samples.iter().fold([f64::INFINITY; 3], |m, s| [m[0].min(s.x), m[1].min(s.y), m[2].min(s.z)]);
+
+    let mut weights = vec![0.0; samples.len()];
+
+    for offset in 0..parameters.offsets {
+        let shift = offset as f64 / parameters.offsets as f64;
+        let cells: Vec<[i64; 3]> = samples.iter()
+            .map(|s| {
+                let cell = |i: usize, c: f64| ((c - min[i] + shift * size[i]) / size[i]).floor() as i64;
+                [cell(0, s.x), cell(1, s.y), cell(2, s.z)]
+            })
+            .collect();
+
+        let mut counts: HashMap<[i64; 3], usize> = HashMap::new();
+        for c in cells.iter() {
+            *counts.entry(*c).or_insert(0) += 1;
+        }
+
+        for (w, c) in weights.iter_mut().zip(cells.iter()) {
+            *w += 1.0 / counts[c] as f64;
+        }
+    }
+
+    let total: f64 = weights.iter().sum();
+    weights.iter().map(|w| w * samples.len() as f64 / total).collect()
+}
+
+fn weighted_mean(samples: &[Sample], weights: &[f64]) -> f64 {
+    samples.iter().zip(weights.iter()).map(|(s, w)| s.value * w).sum::<f64>() / weights.iter().sum::<f64>()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::estimation::search::Sample;
+    use crate::lego_tests::{give_me_composite, give_me_drillhole_database};
+    use crate::statistics::declustering::{Declustering, DeclusteringParameters};
+    use crate::statistics::descriptive::{DomainStatistics, DomainValue};
+
+    #[test]
+    fn clustered_high_grades() {
+        // regular 100 m grid of low grades and a tight cluster of high grades around one of them
+        let mut samples: Vec<Sample> = vec![];
+        for i in 0..5 {
+            for j in 0..5 {
+                samples.push(Sample::new(i as f64 * 100.0, j as f64 * 100.0, 0.0, 1.0, format!("{}{}", i, j)));
+            }
+        }
+        for k in 0..5 {
+            samples.push(Sample::new(200.0 + k as f64, 200.0, 0.0, 10.0, format!("C{}", k)));
+        }
+
+        let declustering = Declustering::new(&samples, DeclusteringParameters::new(10.0, 200.0, 20)).unwrap();
+        println!("{}", declustering);
+
+        assert!((declustering.naive_mean - 2.5).abs() < 1e-12);
+        assert!(declustering.mean < declustering.naive_mean);
+        assert_eq!(declustering.curve.len(), 20);
+        assert!((declustering.weights.iter().map(|w| w.weight).sum::<f64>() - samples.len() as f64).abs() < 1e-9);
+        assert!(declustering.weights[25].weight < 1.0 && declustering.weights[0].weight > 1.0);
+        assert_eq!(declustering.weights[25].hole, "C0");
+
+        assert!(Declustering::new(&samples, DeclusteringParameters::new(0.0, 200.0, 20)).is_err());
+    }
+
+    #[test]
+    fn weighted_composite_statistics() {
+        let composites = give_me_composite();
+        let samples = Sample::from_composites(&composites);
+        let declustering = Declustering::new(&samples, DeclusteringParameters::new(10.0, 100.0, 10)).unwrap();
+        println!("{}", declustering);
+        assert_eq!(declustering.to_csv(';').unwrap().lines().count(), 11);
+
+        // lowest mean of the curve is taken
+        let sizes: Vec<f64> = declustering.curve.iter().map(|p| p.cell_size).collect();
+        assert_eq!(sizes, vec![10.0, 20.0, 30.0, 40.0, 50.0, 60.0, 70.0, 80.0, 90.0, 100.0]);
+        assert_eq!(declustering.cell_size, 50.0);
+        assert!((declustering.mean - 3.410731).abs() < 1e-6);
+        assert!(declustering.curve.iter().all(|p| p.mean >= declustering.mean));
+        assert!(declustering.mean < declustering.naive_mean);
+
+        let mut values = DomainValue::from_composites(&composites, &give_me_drillhole_database());
+        declustering.apply(&mut values).unwrap();
+
+        // weights follow the hole and interval, not the order
+        let mut reversed = values.clone();
+        reversed.reverse();
+        for v in reversed.iter_mut() {
+            v.weight = 1.0;
+        }
+        declustering.apply(&mut reversed).unwrap();
+        reversed.reverse();
+        assert_eq!(reversed, values);
+
+        let mut unknown = vec![values[0].clone().with_source("SK-99", 0.0, 4.0)];
+        assert!(declustering.apply(&mut unknown).is_err());
+
+        // lengths and weights together give the declustered mean
+        let statistics = DomainStatistics::new("tenor", &values);
+        let total = statistics.total.unwrap();
+        let expected = values.iter().map(|v| v.value * v.length * v.weight).sum::<f64>() /
+            values.iter().map(|v| v.length * v.weight).sum::<f64>();
+        assert!((total.mean - expected).abs() < 1e-9);
+    }
+}
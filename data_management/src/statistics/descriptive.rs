@@ -0,0 +1,324 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use lego_config::error::LegoError;
+
+use crate::drillhole::compositing::CompositingObject;
+use crate::drillhole::database::DrillholeDatabase;
+use crate::excels::excel_traits::{report_error, report_text, report_writer, write_report};
+use crate::str::composite_reader::{Composite, CompositeObject};
+
+// domain of the values lying outside of every lythology interval
+pub const UNDEFINED_DOMAIN: &str = "undefined";
+
+// quantiles given in the reports
+pub const REPORT_QUANTILES: [f64; 7] = [0.1, 0.25, 0.5, 0.75, 0.9, 0.95, 0.99];
+
+// a grade with the length it represents. weight is 1 unless declustering weights are given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomainValue {
+    pub domain: String,
+    pub value: f64,
+    pub length: f64,
+    pub weight: f64,
+    // hole and from, to depths the value is taken from, declustering weights are matched on them
+    pub source: Option<(String, f64, f64)>,
+}
+
+impl DomainValue {
+    pub fn new(domain: String, value: f64, length: f64) -> DomainValue {
+        DomainValue { domain, value, length, weight: 1.0, source: None }
+    }
+
+    pub fn with_source(mut self, hole: &str, from: f64, to: f64) -> DomainValue {
+        self.source = Some((hole.to_string(), from, to));
+        self
+    }
+
+    // used by the statistics instead of the length
+    pub fn weighted_length(&self) -> f64 {
+        self.length * self.weight
+    }
+
+    // raw samples take the lythology on the middle of their intervals
+    pub fn from_raw_samples(database: &DrillholeDatabase) -> Vec<DomainValue> {
+        let mut values: Vec<DomainValue> = vec![];
+
+        for hole in database.drillholes() {
+            for r in hole.raw_samples.iter() {
+                let domain = hole.give_lytho_at((r.start + r.end) / 2.0).unwrap_or(UNDEFINED_DOMAIN);
+                values.push(DomainValue::new(domain.to_string(), r.percent, r.end - r.start)
+                    .with_source(&hole.hole_id, r.start, r.end));
+            }
+        }
+
+        values
+    }
+
+    // composites of a str file, their lythology is looked up in the database
+    pub fn from_composites(composites: &CompositeObject, database: &DrillholeDatabase) -> Vec<DomainValue> {
+        composite_values(&composites.data, database)
+    }
+
+    pub fn from_compositing(composites: &CompositingObject, database: &DrillholeDatabase) -> Vec<DomainValue> {
+        composite_values(&composites.data, database)
+    }
+}
+
+// domain bounded composites already know their lythology. composites below the minimum
+// coverage and the ones without any sampled length are left out.
+fn composite_values(composites: &[Composite], database: &DrillholeDatabase) -> Vec<DomainValue> {
+    composites.iter()
+        .filter(|c| c.is_accepted() && c.cut_taken > 0.0)
+        .map(|c| DomainValue::new(composite_domain(c, database), c.tenor, c.cut_taken)
+            .with_source(&c.drill_no, c.cut_from, c.cut_end))
+        .collect()
+}
+
+pub(crate) fn composite_domain(composite: &Composite, database: &DrillholeDatabase) -> String {
+    match &composite.domain {
+        Some(d) => d.clone(),
+        None => database.get_drillhole(&composite.drill_no)
+            .and_then(|h| h.give_lytho_at((composite.cut_from + composite.cut_end) / 2.0))
+            .unwrap_or(UNDEFINED_DOMAIN)
+            .to_string()
+    }
+}
+
+// (value, weighted length) pairs of every domain ordered by the domain name
+pub(crate) fn group_by_domain(values: &[DomainValue]) -> Vec<(&str, Vec<(f64, f64)>)> {
+    let mut names: Vec<&str> = values.iter().map(|v| v.domain.as_str()).collect();
+    names.sort_unstable();
+    names.dedup();
+
+    names.into_iter()
+        .map(|name| {
+            let pairs = values.iter()
+                .filter(|v| v.domain == name)
+                .map(|v| (v.value, v.weighted_length()))
+                .collect();
+            (name, pairs)
+        })
+        .collect()
+}
+
+// length weighted statistics of one domain
+#[derive(Debug, Clone, PartialEq)]
+pub struct SummaryStatistics {
+    pub domain: String,
+    pub count: usize,
+    pub length: f64,
+    pub mean: f64,
+    pub variance: f64,
+    pub std_dev: f64,
+    // not defined for a zero mean
+    pub cv: Option<f64>,
+    pub min: f64,
+    pub max: f64,
+    // (probability, value) pairs of REPORT_QUANTILES
+    pub quantiles: Vec<(f64, f64)>,
+    // values ordered with their lengths, kept for the other quantiles
+    pub(crate) sorted: Vec<(f64, f64)>,
+}
+
+impl SummaryStatistics {
+    // values are (value, length) pairs. values without a positive length or with a NaN are
+    // left out, None is returned when nothing is left.
+    pub fn new(domain: &str, values: &[(f64, f64)]) -> Option<SummaryStatistics> {
+        let mut sorted: Vec<(f64, f64)> = values.iter()
+            .filter(|(v, l)| !v.is_nan() && *l > 0.0)
+            .copied()
+            .collect();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        let length: f64 = sorted.iter().map(|(_, l)| l).sum();
+        let mean = sorted.iter().map(|(v, l)| v * l).sum::<f64>() / length;
+        let variance = sorted.iter().map(|(v, l)| l * (v - mean).powi(2)).sum::<f64>() / length;
+        let std_dev = variance.sqrt();
+
+        let mut statistics = SummaryStatistics {
+            domain: domain.to_string(),
+            count: sorted.len(),
+            length,
+            mean,
+            variance,
+            std_dev,
+            cv: if mean != 0.0 { Some(std_dev / mean) } else { None },
+            min: sorted[0].0,
+            max: sorted[sorted.len() - 1].0,
+            quantiles: vec![],
+            sorted,
+        };
+        statistics.quantiles = REPORT_QUANTILES.iter()
+            .map(|p| (*p, statistics.quantile(*p)))
+            .collect();
+
+        Some(statistics)
+    }
+
+    // Length weighted quantile. Every value stands on the middle of its own length along the
+    // cumulative length, the quantile is interpolated between them.
+    pub fn quantile(&self, probability: f64) -> f64 {
+        let target = probability.clamp(0.0, 1.0) * self.length;
+
+        let mut cumulative = 0.0;
+        let mut previous: Option<(f64, f64)> = None;
+
+        for (v, l) in self.sorted.iter() {
+            let position = cumulative + l / 2.0;
+            if target <= position {
+                return match previous {
+                    None => *v,
+                    Some((p_position, p_value)) =>
+                        p_value + (v - p_value) * (target - p_position) / (position - p_position)
+                };
+            }
+            previous = Some((position, *v));
+            cumulative += l;
+        }
+
+        self.max
+    }
+}
+
+// statistics of a variable for every domain and for all of them together
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomainStatistics {
+    pub variable: String,
+    // ordered by domain name
+    pub domains: Vec<SummaryStatistics>,
+    pub total: Option<SummaryStatistics>,
+}
+
+impl DomainStatistics {
+    pub fn new(variable: &str, values: &[DomainValue]) -> DomainStatistics {
+        let domains = group_by_domain(values).iter()
+            .filter_map(|(name, pairs)| SummaryStatistics::new(name, pairs))
+            .collect();
+
+        let all: Vec<(f64, f64)> = values.iter().map(|v| (v.value, v.weighted_length())).collect();
+
+        DomainStatistics {
+            variable: variable.to_string(),
+            domains,
+            total: SummaryStatistics::new("total", &all),
+        }
+    }
+
+    pub fn get_domain(&self, domain: &str) -> Option<&SummaryStatistics> {
+        self.domains.iter().find(|s| s.domain == domain)
+    }
+
+    pub fn to_csv(&self, seperator: char) -> Result<String, LegoError> {
+        let mut writer = report_writer(seperator);
+
+        let mut header: Vec<String> = ["variable", "domain", "count", "length", "mean", "variance",
+            "std_dev", "cv", "min", "max"].iter().map(|h| h.to_string()).collect();
+        header.extend(REPORT_QUANTILES.iter().map(|p| format!("p{}", p * 100.0)));
+        writer.write_record(&header).map_err(report_error)?;
+
+        for s in self.domains.iter().chain(self.total.iter()) {
+            let mut record: Vec<String> = vec![self.variable.clone(), s.domain.clone(), s.count.to_string(),
+                                               s.length.to_string(), s.mean.to_string(), s.variance.to_string(),
+                                               s.std_dev.to_string(),
+                                               s.cv.map(|cv| cv.to_string()).unwrap_or_default(),
+                                               s.min.to_string(), s.max.to_string()];
+            record.extend(s.quantiles.iter().map(|(_, q)| q.to_string()));
+            writer.write_record(&record).map_err(report_error)?;
+        }
+
+        report_text(writer)
+    }
+
+    pub fn write_csv(&self, path: &str, seperator: char) -> Result<(), LegoError> {
+        write_report(path, &self.to_csv(seperator)?)
+    }
+}
+
+impl Display for DomainStatistics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "variable : {}", self.variable)?;
+        write!(f, "{:<12} {:>6} {:>10} {:>10} {:>10} {:>8} {:>10} {:>10}", "domain", "count", "length",
+               "mean", "variance", "cv", "min", "max")?;
+        for p in REPORT_QUANTILES.iter() {
+            write!(f, " {:>10}", format!("p{}", p * 100.0))?;
+        }
+
+        for s in self.domains.iter().chain(self.total.iter()) {
+            let cv = s.cv.map(|cv| format!("{:.3}", cv)).unwrap_or_else(|| String::from("-"));
+            write!(f, "\n{:<12} {:>6} {:>10.2} {:>10.4} {:>10.4} {:>8} {:>10.4} {:>10.4}", s.domain, s.count,
+                   s.length, s.mean, s.variance, cv, s.min, s.max)?;
+            for (_, q) in s.quantiles.iter() {
+                write!(f, " {:>10.4}", q)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lego_tests::{give_me_composite, give_me_drillhole_database};
+    use crate::statistics::descriptive::{DomainStatistics, DomainValue, SummaryStatistics};
+
+    #[test]
+    fn weighted_statistics() {
+        let statistics = SummaryStatistics::new("a", &[(1.0, 1.0), (4.0, 2.0), (2.0, 1.0),
+            (f64::NAN, 1.0), (100.0, 0.0)]).unwrap();
+
+        assert_eq!(statistics.count, 3);
+        assert_eq!(statistics.length, 4.0);
+        assert_eq!(statistics.mean, 2.75);
+        // (1.75^2 + 2 * 1.25^2 + 0.75^2) / 4
+        assert!((statistics.variance - 1.6875).abs() < 1e-12);
+        assert!((statistics.cv.unwrap() - 1.6875f64.sqrt() / 2.75).abs() < 1e-12);
+        assert_eq!((statistics.min, statistics.max), (1.0, 4.0));
+
+        // values stand on 0.5, 1.5 and 3.0 along the cumulative length
+        assert_eq!(statistics.quantile(0.0), 1.0);
+        assert_eq!(statistics.quantile(0.25), 1.5);
+        assert!((statistics.quantile(0.5) - (2.0 + 2.0 / 3.0)).abs() < 1e-12);
+        assert_eq!(statistics.quantile(1.0), 4.0);
+
+        assert!(SummaryStatistics::new("empty", &[(1.0, 0.0)]).is_none());
+    }
+
+    #[test]
+    fn raw_sample_statistics() {
+        let database = give_me_drillhole_database();
+        let values = DomainValue::from_raw_samples(&database);
+        let statistics = DomainStatistics::new("percent", &values);
+        println!("{}", statistics);
+
+        let total = statistics.total.as_ref().unwrap();
+        assert_eq!(statistics.domains.iter().map(|s| s.count).sum::<usize>(), total.count);
+        assert!(statistics.get_domain("kalkopirit").unwrap().count > 0);
+        for s in statistics.domains.iter() {
+            assert!(s.min <= s.quantiles[0].1 && s.quantiles[6].1 <= s.max);
+            assert!(s.min <= s.mean && s.mean <= s.max);
+        }
+
+        let csv = statistics.to_csv(';').unwrap();
+        assert_eq!(csv.lines().count(), statistics.domains.len() + 2);
+        assert!(csv.starts_with("variable;domain;count;length;mean"));
+    }
+
+    #[test]
+    fn composite_statistics() {
+        let database = give_me_drillhole_database();
+        let composites = give_me_composite();
+        let values = DomainValue::from_composites(&composites, &database);
+        let statistics = DomainStatistics::new("tenor", &values);
+        println!("{}", statistics);
+
+        // composites below the minimum coverage are left out
+        let accepted = composites.iter().filter(|c| c.is_accepted() && c.cut_taken() > 0.0).count();
+        assert!(accepted < composites.len());
+        assert_eq!(values.len(), accepted);
+        assert!(statistics.get_domain("kalkopirit").is_some());
+    }
+}
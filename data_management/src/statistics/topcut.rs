@@ -0,0 +1,418 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs;
+
+use lego_config::error::LegoError;
+use lego_config::read::{DataManagementObjects, LegoConfig};
+use serde::{Deserialize, Serialize};
+
+use crate::drillhole::compositing::CompositingObject;
+use crate::drillhole::database::DrillholeDatabase;
+use crate::excels::excel_traits::{report_error, report_text, report_writer, write_report};
+use crate::statistics::descriptive::{composite_domain, DomainValue, group_by_domain, SummaryStatistics,
+                                     UNDEFINED_DOMAIN};
+use crate::str::composite_reader::{Composite, CompositeObject};
+
+// percentiles tried as caps in the analysis
+pub const TOPCUT_PERCENTILES: [f64; 6] = [0.9, 0.95, 0.975, 0.99, 0.995, 0.999];
+
+// key of the cap used for the domains without their own one
+const DEFAULT_KEY: &str = "default";
+
+// one point of the log probability plot, value is drawn on a log axis against the normal score
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProbabilityPoint {
+    pub value: f64,
+    // length weighted cumulative probability on the middle of the sample
+    pub cumulative: f64,
+    pub normal_score: f64,
+}
+
+// effect of capping a domain at a value. metal is grade x length, metal at risk is the part
+// of the metal above the cap in percent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TopCutCandidate {
+    pub percentile: Option<f64>,
+    pub cap: f64,
+    pub capped_count: usize,
+    pub capped_length: f64,
+    pub capped_mean: f64,
+    pub capped_cv: Option<f64>,
+    pub metal_removed: f64,
+    pub metal_at_risk: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopCutAnalysis {
+    pub statistics: SummaryStatistics,
+    pub probability_plot: Vec<ProbabilityPoint>,
+    // one candidate for every TOPCUT_PERCENTILES
+    pub candidates: Vec<TopCutCandidate>,
+}
+
+impl TopCutAnalysis {
+    // values are (value, length) pairs as in SummaryStatistics
+    pub fn new(domain: &str, values: &[(f64, f64)]) -> Option<TopCutAnalysis> {
+        let statistics = SummaryStatistics::new(domain, values)?;
+
+        let mut cumulative = 0.0;
+        let probability_plot = statistics.sorted.iter()
+            .map(|(v, l)| {
+                let p = (cumulative + l / 2.0) / statistics.length;
+                cumulative += l;
+                ProbabilityPoint { value: *v, cumulative: p, normal_score: normal_score(p) }
+            })
+            .collect();
+
+        let mut analysis = TopCutAnalysis {
+            statistics,
+            probability_plot,
+            candidates: vec![],
+        };
+        analysis.candidates = TOPCUT_PERCENTILES.iter()
+            .map(|p| {
+                let mut candidate = analysis.candidate(analysis.statistics.quantile(*p));
+                candidate.percentile = Some(*p);
+                candidate
+            })
+            .collect();
+
+        Some(analysis)
+    }
+
+    pub fn domain(&self) -> &str {
+        &self.statistics.domain
+    }
+
+    pub fn candidate(&self, cap: f64) -> TopCutCandidate {
+        let s = &self.statistics;
+        let capped: Vec<(f64, f64)> = s.sorted.iter().map(|(v, l)| (v.min(cap), *l)).collect();
+
+        let capped_mean = capped.iter().map(|(v, l)| v * l).sum::<f64>() / s.length;
+        let capped_variance = capped.iter().map(|(v, l)| l * (v - capped_mean).powi(2)).sum::<f64>() / s.length;
+        let metal = s.mean * s.length;
+        let metal_removed = metal - capped_mean * s.length;
+        let above = s.sorted.iter().filter(|(v, _)| *v > cap);
+
+        TopCutCandidate {
+            percentile: None,
+            cap,
+            capped_count: above.clone().count(),
+            capped_length: above.map(|(_, l)| l).sum(),
+            capped_mean,
+            capped_cv: if capped_mean != 0.0 { Some(capped_variance.sqrt() / capped_mean) } else { None },
+            metal_removed,
+            metal_at_risk: if metal != 0.0 { metal_removed / metal * 100.0 } else { 0.0 },
+        }
+    }
+}
+
+// top cut analysis of a variable for every domain
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopCutReport {
+    pub variable: String,
+    // ordered by domain name
+    pub domains: Vec<TopCutAnalysis>,
+}
+
+impl TopCutReport {
+    pub fn new(variable: &str, values: &[DomainValue]) -> TopCutReport {
+        let domains = group_by_domain(values).iter()
+            .filter_map(|(name, pairs)| TopCutAnalysis::new(name, pairs))
+            .collect();
+
+        TopCutReport {
+            variable: variable.to_string(),
+            domains,
+        }
+    }
+
+    pub fn get_domain(&self, domain: &str) -> Option<&TopCutAnalysis> {
+        self.domains.iter().find(|a| a.domain() == domain)
+    }
+
+    // candidates of every domain
+    pub fn to_csv(&self, seperator: char) -> Result<String, LegoError> {
+        let mut writer = report_writer(seperator);
+
+        writer.write_record(["variable", "domain", "percentile", "cap", "capped_count", "capped_length",
+            "mean", "capped_mean", "capped_cv", "metal_removed", "metal_at_risk"]).map_err(report_error)?;
+
+        for a in self.domains.iter() {
+            for c in a.candidates.iter() {
+                writer.write_record(&[self.variable.clone(), a.domain().to_string(),
+                    c.percentile.map(|p| p.to_string()).unwrap_or_default(), c.cap.to_string(),
+                    c.capped_count.to_string(), c.capped_length.to_string(), a.statistics.mean.to_string(),
+                    c.capped_mean.to_string(), c.capped_cv.map(|cv| cv.to_string()).unwrap_or_default(),
+                    c.metal_removed.to_string(), c.metal_at_risk.to_string()]).map_err(report_error)?;
+            }
+        }
+
+        report_text(writer)
+    }
+
+    pub fn write_csv(&self, path: &str, seperator: char) -> Result<(), LegoError> {
+        write_report(path, &self.to_csv(seperator)?)
+    }
+}
+
+impl Display for TopCutReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "variable : {}", self.variable)?;
+
+        for a in self.domains.iter() {
+            write!(f, "\ndomain : {} (mean {:.4}, cv {}) \n{:>10} {:>10} {:>8} {:>10} {:>12} {:>8} {:>14}",
+                   a.domain(), a.statistics.mean,
+                   a.statistics.cv.map(|cv| format!("{:.3}", cv)).unwrap_or_else(|| String::from("-")),
+                   "percentile", "cap", "capped", "length", "capped mean", "cv", "metal at risk")?;
+            for c in a.candidates.iter() {
+                write!(f, "\n{:>10} {:>10.4} {:>8} {:>10.2} {:>12.4} {:>8} {:>13.2}%",
+                       c.percentile.map(|p| format!("p{}", p * 100.0)).unwrap_or_else(|| String::from("-")),
+                       c.cap, c.capped_count, c.capped_length, c.capped_mean,
+                       c.capped_cv.map(|cv| format!("{:.3}", cv)).unwrap_or_else(|| String::from("-")),
+                       c.metal_at_risk)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// chosen caps per domain. kept in toml files or in the [top_cuts] section of the config,
+// domains are the keys and "default" is used for the ones without a cap.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TopCuts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<f64>,
+    #[serde(flatten)]
+    pub domains: BTreeMap<String, f64>,
+}
+
+impl TopCuts {
+    pub fn new() -> TopCuts {
+        TopCuts::default()
+    }
+
+    pub fn new_from_config(config: &LegoConfig) -> Result<TopCuts, LegoError> {
+        let mut top_cuts = TopCuts::new();
+        for (domain, cap) in config.get_top_cuts() {
+            if domain == DEFAULT_KEY {
+                top_cuts.default = Some(*cap);
+            } else {
+                top_cuts.domains.insert(domain.clone(), *cap);
+            }
+        }
+
+        top_cuts.validate().map_err(|e| match e {
+            LegoError::Parameter(message) => LegoError::Config { path: config.get_path().to_string(), message },
+            e => e
+        })?;
+        Ok(top_cuts)
+    }
+
+    pub fn set_cap(&mut self, domain: &str, cap: f64) {
+        self.domains.insert(domain.to_string(), cap);
+    }
+
+    pub fn get_cap(&self, domain: &str) -> Option<f64> {
+        self.domains.get(domain).copied().or(self.default)
+    }
+
+    pub fn cap(&self, domain: &str, value: f64) -> f64 {
+        match self.get_cap(domain) {
+            Some(cap) => value.min(cap),
+            None => value
+        }
+    }
+
+    pub fn apply(&self, values: &[DomainValue]) -> Vec<DomainValue> {
+        values.iter()
+            .map(|v| DomainValue { value: self.cap(&v.domain, v.value), ..v.clone() })
+            .collect()
+    }
+
+    // copy of the database with capped raw samples, lythology is taken on the middle of the samples
+    pub fn apply_to_database(&self, database: &DrillholeDatabase) -> DrillholeDatabase {
+        let mut capped = database.clone();
+
+        for hole in capped.drillholes_mut() {
+            let domains: Vec<String> = hole.raw_samples.iter()
+                .map(|r| hole.give_lytho_at((r.start + r.end) / 2.0)
+                    .unwrap_or(UNDEFINED_DOMAIN).to_string())
+                .collect();
+
+            for (r, domain) in hole.raw_samples.iter_mut().zip(domains.iter()) {
+                r.percent = self.cap(domain, r.percent);
+            }
+        }
+
+        capped
+    }
+
+    pub fn apply_to_composites(&self, composites: &CompositeObject, database: &DrillholeDatabase) -> CompositeObject {
+        let mut capped = composites.clone();
+        self.cap_composites(&mut capped.data, database);
+        capped
+    }
+
+    pub fn apply_to_compositing(&self, composites: &CompositingObject, database: &DrillholeDatabase) -> CompositingObject {
+        let mut capped = composites.clone();
+        self.cap_composites(&mut capped.data, database);
+        capped
+    }
+
+    fn cap_composites(&self, composites: &mut [Composite], database: &DrillholeDatabase) {
+        for c in composites.iter_mut() {
+            c.tenor = self.cap(&composite_domain(c, database), c.tenor);
+        }
+    }
+
+    pub fn to_toml(&self) -> Result<String, LegoError> {
+        toml::to_string(self).map_err(|e| LegoError::parameter(format!("top cuts cannot be written as toml : {}", e)))
+    }
+
+    pub fn from_toml(text: &str) -> Result<TopCuts, LegoError> {
+        TopCuts::parse_toml(text, "toml text")
+    }
+
+    pub fn write_toml(&self, path: &str) -> Result<(), LegoError> {
+        fs::write(path, self.to_toml()?).map_err(|e| LegoError::io(path, e))
+    }
+
+    pub fn read_toml(path: &str) -> Result<TopCuts, LegoError> {
+        let text = fs::read_to_string(path).map_err(|e| LegoError::io(path, e))?;
+        TopCuts::parse_toml(&text, path)
+    }
+
+    // source is the path of the file, or a name of the text
+    fn parse_toml(text: &str, source: &str) -> Result<TopCuts, LegoError> {
+        let top_cuts: TopCuts = toml::from_str(text)
+            .map_err(|e| LegoError::Config { path: source.to_string(), message: e.to_string() })?;
+        top_cuts.validate()?;
+        Ok(top_cuts)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), LegoError> {
+        let defaults = self.default.iter().map(|c| (DEFAULT_KEY, c));
+        for (domain, cap) in self.domains.iter().map(|(d, c)| (d.as_str(), c)).chain(defaults) {
+            if cap.is_nan() || *cap <= 0.0 {
+                return Err(LegoError::parameter(format!("top cut of {} has to be positive, got {}", domain, cap)));
+            }
+        }
+        Ok(())
+    }
+}
+
+// inverse of the standard normal distribution, rational approximation of P. J. Acklam
+pub(crate) fn normal_score(p: f64) -> f64 {
+    const A: [f64; 6] = [-3.969683028665376e+01, 2.209460984245205e+02, -2.759285104469687e+02,
+        1.38357751867269e+02, -3.066479806614716e+01, 2.506628277459239e+00];
+    const B: [f64; 5] = [-5.447609879822406e+01, 1.615858368580409e+02, -1.556989798598866e+02,
+        6.680131188771972e+01, -1.328068155288572e+01];
+    const C: [f64; 6] = [-7.784894002430293e-03, -3.223964580411365e-01, -2.400758277161838e+00,
+        -2.549732539343734e+00, 4.374664141464968e+00, 2.938163982698783e+00];
+    const D: [f64; 4] = [7.784695709041462e-03, 3.224671290700398e-01, 2.445134137142996e+00,
+        3.754408661907416e+00];
+    const LOW: f64 = 0.02425;
+
+    if p <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p >= 1.0 {
+        return f64::INFINITY;
+    }
+
+    let tail = |q: f64| (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5]) /
+        ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0);
+
+    if p < LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q /
+            (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lego_config::error::LegoError;
+    use lego_config::read::LegoConfig;
+
+    use crate::lego_tests::{give_me_composite, give_me_drillhole_database};
+    use crate::statistics::descriptive::DomainValue;
+    use crate::statistics::topcut::{normal_score, TopCutAnalysis, TopCutReport, TopCuts};
+
+    const TEST_CONFIG_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../lego_config/test_settings.toml");
+
+    #[test]
+    fn normal_scores() {
+        assert!(normal_score(0.5).abs() < 1e-9);
+        assert!((normal_score(0.975) - 1.959964).abs() < 1e-5);
+        assert!((normal_score(0.01) + 2.326348).abs() < 1e-5);
+    }
+
+    #[test]
+    fn cap_candidate() {
+        let analysis = TopCutAnalysis::new("a", &[(1.0, 1.0), (2.0, 1.0), (3.0, 1.0), (10.0, 1.0)]).unwrap();
+        assert_eq!(analysis.probability_plot.len(), 4);
+        assert_eq!(analysis.probability_plot[0].cumulative, 0.125);
+
+        let candidate = analysis.candidate(4.0);
+        assert_eq!(candidate.capped_count, 1);
+        assert_eq!(candidate.capped_mean, 2.5);
+        assert_eq!(candidate.metal_removed, 6.0);
+        assert_eq!(candidate.metal_at_risk, 6.0 / 16.0 * 100.0);
+
+        assert_eq!(analysis.candidates.len(), 6);
+        for pair in analysis.candidates.windows(2) {
+            assert!(pair[0].cap <= pair[1].cap && pair[0].metal_at_risk >= pair[1].metal_at_risk);
+        }
+    }
+
+    #[test]
+    fn analyse_and_apply() {
+        let database = give_me_drillhole_database();
+        let report = TopCutReport::new("percent", &DomainValue::from_raw_samples(&database));
+        println!("{}", report);
+        assert!(report.get_domain("kalkopirit").is_some());
+        assert!(report.to_csv(';').unwrap().lines().count() > 6);
+
+        // kalkopirit is capped at 5.5 in the test config
+        let top_cuts = TopCuts::new_from_config(&LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap()).unwrap();
+        assert_eq!(top_cuts.get_cap("kalkopirit"), Some(5.5));
+        assert_eq!(top_cuts.get_cap("bazalt"), None);
+
+        let overrides = [(String::from("top_cuts.bazalt"), String::from("-1.0"))];
+        let config = LegoConfig::new_with_overrides(String::from(TEST_CONFIG_PATH), &overrides).unwrap();
+        assert!(matches!(TopCuts::new_from_config(&config), Err(LegoError::Config { .. })));
+
+        let capped = top_cuts.apply_to_database(&database);
+        let values = DomainValue::from_raw_samples(&capped);
+        assert!(values.iter().all(|v| v.domain != "kalkopirit" || v.value <= 5.5));
+        assert!(DomainValue::from_raw_samples(&database).iter().any(|v| v.value > 5.5));
+
+        let composites = top_cuts.apply_to_composites(&give_me_composite(), &database);
+        let values = DomainValue::from_composites(&composites, &database);
+        assert!(values.iter().all(|v| v.domain != "kalkopirit" || v.value <= 5.5));
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let mut top_cuts = TopCuts::new();
+        top_cuts.default = Some(8.0);
+        top_cuts.set_cap("kalkopirit", 5.5);
+
+        let text = top_cuts.to_toml().unwrap();
+        println!("{}", text);
+        assert_eq!(TopCuts::from_toml(&text).unwrap(), top_cuts);
+        assert_eq!(top_cuts.cap("bazalt", 9.0), 8.0);
+
+        assert!(matches!(TopCuts::from_toml("kalkopirit = -1.0"), Err(LegoError::Parameter(_))));
+        assert!(matches!(top_cuts.write_toml("/missing/lego/top_cuts.toml"), Err(LegoError::Io { .. })));
+    }
+}
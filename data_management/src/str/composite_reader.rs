@@ -1,29 +1,67 @@
-use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::fmt;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::string::ToString;
 
+use lego_config::error::LegoError;
 use lego_config::read::{DataManagementObjects, LegoConfig};
 
+use crate::excels::excel_traits::record_field;
+use crate::str::str_file::StrFile;
+
+// string numbers of the composite str files, composites below the minimum coverage
+// are written to the second string
+pub(crate) const ACCEPTED_GROUP_NO: i32 = 1;
+pub(crate) const REJECTED_GROUP_NO: i32 = 2;
+
+#[derive(Clone)]
 pub struct CompositeObject {
-    info: CompositeInformation,
-    data: Vec<Composite>,
+    // None for the composites which are not read from a file
+    info: Option<CompositeInformation>,
+    pub(crate) data: Vec<Composite>,
 }
 
 impl CompositeObject {
-    pub(crate) fn new(info: CompositeInformation) -> CompositeObject {
-        let data = info.read().expect(
-            "Error occurs while reading composite !"
-        );
+    pub fn new(info: CompositeInformation) -> Result<CompositeObject, LegoError> {
+        let data = info.read()?;
+        Ok(CompositeObject {
+            info: Some(info),
+            data,
+        })
+    }
+
+    pub fn from_records(data: Vec<Composite>) -> CompositeObject {
         CompositeObject {
-            info,
+            info: None,
             data,
         }
     }
+
+    pub fn info(&self) -> Option<&CompositeInformation> {
+        self.info.as_ref()
+    }
+
+    pub fn data(&self) -> &[Composite] {
+        &self.data
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Composite> {
+        self.data.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    // composites of a hole in the order of the file
+    pub fn get_hole_composites(&self, drill_no: &str) -> Vec<&Composite> {
+        self.data.iter().filter(|c| c.drill_no == drill_no).collect()
+    }
 }
 
+#[derive(Clone)]
 pub struct CompositeInformation {
     path: String,
     mining_type: String,
@@ -31,7 +69,7 @@ pub struct CompositeInformation {
 }
 
 impl CompositeInformation {
-    fn new(path: String, mining_type: String, seperator: char) -> CompositeInformation {
+    pub fn new(path: String, mining_type: String, seperator: char) -> CompositeInformation {
         CompositeInformation {
             path,
             mining_type,
@@ -39,52 +77,51 @@ impl CompositeInformation {
         }
     }
 
-    pub(crate) fn new_from_config(config: &LegoConfig) -> CompositeInformation {
+    pub fn new_from_config(config: &LegoConfig) -> Result<CompositeInformation, LegoError> {
         // getting mining information
-        let path = config.get_composite_str_path();
-        let mining_type = config.get_mining_type();
-        let seperator = config.get_x_seperator("composite_str_seperator");
+        let path = config.get_composite_str_path()?;
+        let mining_type = config.get_mining_type().to_string();
+        let seperator = config.get_mining_information().composite_str_seperator;
 
-        CompositeInformation {
+        Ok(CompositeInformation {
             path,
             mining_type,
             seperator,
-        }
+        })
     }
-    fn read(&self) -> Result<Vec<Composite>, Box<dyn Error>> {
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn mining_type(&self) -> &str {
+        &self.mining_type
+    }
+
+    fn read(&self) -> Result<Vec<Composite>, LegoError> {
         // todo: tabaka kalınlığı ve düzenli grup karşılaştırması yapılacak. python'da yazmış pass demişiz.
 
         // our data
         let mut composite_objects: Vec<Composite> = vec![];
 
         let composite_str_path: &String = &self.path;
-
-        let file = File::open(composite_str_path)?;
-        let reader = BufReader::new(file);
-        let mut lines = reader.lines();
-
-        // skip first rows
-        lines.next();
-
-        for line in lines {
-            let record = line.expect("Failed to reading line of str file");
-
-            let record: Vec<String> = record.split(",").map(|s| s.trim().to_string()).collect();
-
-            let regular_no: i32 = record[0].parse().expect("regular no icin numerik deger cevirilemedi");
-
-            if regular_no != 0 {
-                let x: f64 = record[1].parse().expect("x icin numerik deger çevirilemedi");
-                let y: f64 = record[2].parse().expect("y icin numerik deger çevirilemedi");
-                let z: f64 = record[3].parse().expect("z icin numerik deger çevirilemedi");
-                let tenor: f64 = record[4].parse().expect("tenor orani için numerik deger çevirilemedi");
-                let drill_no: String = record[5].parse().expect("drill no için text değer çevirilemedi");
-                let cut_from: f64 = record[6].parse().expect("kesim başlangıç değeri numeriğe çevirilemedi");
-                let cut_end: f64 = record[7].parse().expect("Kesim bitiş değeri numerik değere çevirilemedi");
-                let cut_taken: f64 = record[8].parse().expect("Son sütun olan kesim alım değeri numerik değere çevirilemedi");
-
-                let coord = CompositeCoordinate::new(x, y, z);
-                let composite = Composite::new(regular_no, tenor, drill_no,
+        let str_file = StrFile::read_with_seperator(composite_str_path, self.seperator)?;
+
+        // every point is a composite, its string number is the group and d1 - d5 are
+        // tenor, drill no, cut from, cut end and cut taken. other d fields are not used.
+        for string in str_file.strings.iter() {
+            for point in string.points() {
+                let d_fields = &point.d_fields;
+                let line = point.line;
+
+                let tenor: f64 = record_field(d_fields, 0, "tenor", composite_str_path, line)?;
+                let drill_no: String = record_field(d_fields, 1, "drill no", composite_str_path, line)?;
+                let cut_from: f64 = record_field(d_fields, 2, "cut from", composite_str_path, line)?;
+                let cut_end: f64 = record_field(d_fields, 3, "cut end", composite_str_path, line)?;
+                let cut_taken: f64 = record_field(d_fields, 4, "cut taken", composite_str_path, line)?;
+
+                let coord = CompositeCoordinate::new(point.x, point.y, point.z);
+                let composite = Composite::new(string.number, tenor, drill_no,
                                                cut_from, cut_end, cut_taken, coord);
 
                 composite_objects.push(composite);
@@ -95,20 +132,22 @@ impl CompositeInformation {
     }
 }
 
-#[derive(Debug)]
-struct Composite {
-    group_no: i32,
-    tenor: f64,
-    drill_no: String,
-    cut_from: f64,
-    cut_end: f64,
-    cut_taken: f64,
-    coordinate: CompositeCoordinate,
+#[derive(Debug, Clone, PartialEq)]
+pub struct Composite {
+    pub(crate) group_no: i32,
+    pub(crate) tenor: f64,
+    pub(crate) drill_no: String,
+    pub(crate) cut_from: f64,
+    pub(crate) cut_end: f64,
+    pub(crate) cut_taken: f64,
+    pub(crate) coordinate: CompositeCoordinate,
+    // lythology of domain bounded composites
+    pub(crate) domain: Option<String>,
 }
 
 impl Composite {
-    fn new(composite_no: i32, tenor: f64, drill_no: String, cut_from: f64, cut_end: f64,
-           cut_taken: f64, coordinate: CompositeCoordinate) -> Composite {
+    pub fn new(composite_no: i32, tenor: f64, drill_no: String, cut_from: f64, cut_end: f64,
+               cut_taken: f64, coordinate: CompositeCoordinate) -> Composite {
         Composite {
             group_no: composite_no,
             tenor,
@@ -117,8 +156,50 @@ impl Composite {
             cut_end,
             cut_taken,
             coordinate,
+            domain: None,
         }
     }
+
+    pub fn with_domain(mut self, domain: String) -> Composite {
+        self.domain = Some(domain);
+        self
+    }
+
+    pub fn group_no(&self) -> i32 {
+        self.group_no
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        self.group_no == ACCEPTED_GROUP_NO
+    }
+
+    pub fn tenor(&self) -> f64 {
+        self.tenor
+    }
+
+    pub fn drill_no(&self) -> &str {
+        &self.drill_no
+    }
+
+    pub fn cut_from(&self) -> f64 {
+        self.cut_from
+    }
+
+    pub fn cut_end(&self) -> f64 {
+        self.cut_end
+    }
+
+    pub fn cut_taken(&self) -> f64 {
+        self.cut_taken
+    }
+
+    pub fn coordinate(&self) -> &CompositeCoordinate {
+        &self.coordinate
+    }
+
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
 }
 
 impl Display for Composite {
@@ -162,26 +243,41 @@ impl Display for CompositeObject {
         //     info: CompositeInformation,
         //     data: Vec<Composite>,
 
-        write!(f, "info: {} \n\
-                   data : {:?}", self.info, self.data)
+        match &self.info {
+            Some(info) => writeln!(f, "info: {} ", info)?,
+            None => writeln!(f, "info: in memory ")?
+        }
+        write!(f, "data : {:?}", self.data)
     }
 }
 
-#[derive(Debug)]
-struct CompositeCoordinate {
-    x_coord: f64,
-    y_coord: f64,
-    z_coord: f64,
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompositeCoordinate {
+    pub(crate) x_coord: f64,
+    pub(crate) y_coord: f64,
+    pub(crate) z_coord: f64,
 }
 
 impl CompositeCoordinate {
-    fn new(x: f64, y: f64, z: f64) -> CompositeCoordinate {
+    pub fn new(x: f64, y: f64, z: f64) -> CompositeCoordinate {
         CompositeCoordinate {
             x_coord: x,
             y_coord: y,
             z_coord: z,
         }
     }
+
+    pub fn x(&self) -> f64 {
+        self.x_coord
+    }
+
+    pub fn y(&self) -> f64 {
+        self.y_coord
+    }
+
+    pub fn z(&self) -> f64 {
+        self.z_coord
+    }
 }
 
 impl Display for CompositeCoordinate {
@@ -197,16 +293,32 @@ impl Display for CompositeCoordinate {
 mod tests {
     use lego_config::read::LegoConfig;
 
-    use crate::str::composite_reader::{CompositeInformation, CompositeObject};
+    use crate::str::composite_reader::{Composite, CompositeCoordinate, CompositeInformation, CompositeObject};
 
-    const TEST_CONFIG_PATH: &str = "/home/umut/CLionProjects/LegoRust/lego_config/test_settings.toml";
+    const TEST_CONFIG_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../lego_config/test_settings.toml");
 
     #[test]
     fn read_composite_from_config() {
-        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH));
+        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
 
-        let composite_info = CompositeInformation::new_from_config(&config_object);
-        let l_object = CompositeObject::new(composite_info);
+        let composite_info = CompositeInformation::new_from_config(&config_object).unwrap();
+        let l_object = CompositeObject::new(composite_info).unwrap();
         println!("composite : {}", l_object);
     }
+
+    #[test]
+    fn composites_from_records() {
+        let c_object = CompositeObject::from_records(vec![
+            Composite::new(1, 0.8, String::from("SK-01"), 10.0, 12.0, 2.0, CompositeCoordinate::new(1.0, 2.0, 500.0)),
+            Composite::new(1, 1.6, String::from("SK-01"), 12.0, 14.0, 2.0, CompositeCoordinate::new(1.0, 2.0, 498.0))
+                .with_domain(String::from("kalkopirit")),
+        ]);
+        println!("composite : {}", c_object);
+
+        let composites = c_object.get_hole_composites("SK-01");
+        assert_eq!(composites.len(), 2);
+        assert_eq!(composites[1].domain(), Some("kalkopirit"));
+        assert_eq!((composites[1].cut_from(), composites[1].coordinate().z()), (12.0, 498.0));
+        assert!(c_object.get_hole_composites("SK-02").is_empty());
+    }
 }
@@ -1,4 +1,3 @@
-use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::fmt;
 use std::fs::File;
@@ -15,7 +14,10 @@ pub mod cross_main {
     use crate::str::cross_reader::info::CrossInformation;
     use crate::str::cross_reader::cross::Cross;
     use crate::str::cross_reader::common::{Extent, Axis};
+    use crate::solid::loft::loft_crosses;
+    use crate::solid::mesh::Solid;
     use plotly::{Plot, Scatter};
+    use lego_config::error::LegoError;
 
     // main object which will be used on everywhere
     #[derive(Debug)]
@@ -103,9 +105,11 @@ pub mod cross_main {
     }
 
     impl CrossObject {
-        pub fn new(info: CrossInformation, min_drill_z: Option<f64>) -> CrossObject {
-            let data = info.read()
-                .expect("Error occured while reading cross section ");
+        pub fn new(info: CrossInformation, min_drill_z: Option<f64>) -> std::result::Result<CrossObject, LegoError> {
+            let data = info.read()?;
+            if data.is_empty() {
+                return Err(LegoError::geometry(format!("no cross section can be read from {}", info.path)));
+            }
 
             // creating object. but we need to do some initial things.
             let mut object = CrossObject {
@@ -134,7 +138,7 @@ pub mod cross_main {
 
             let extent = object.find_model_frame();
             println!("extent : {:?}", extent);
-            object
+            Ok(object)
         }
 
         fn get_cross_by_groupno(&self, group_no: i32) -> Option<&Cross> {
@@ -189,6 +193,11 @@ pub mod cross_main {
             // removing
             self.data.retain(|c| group_number.contains(&&c.group_no));
         }
+
+        pub fn build_solid(&self) -> std::result::Result<Solid, LegoError> {
+            // data is already sorted by Z while creating the object
+            loft_crosses(&self.data)
+        }
     }
 
     impl DrawOnWeb for CrossObject {
@@ -218,78 +227,56 @@ pub mod cross_main {
 }
 
 pub mod info {
-    use std::io::{BufReader, BufRead};
-    use std::fs::File;
+    use lego_config::error::LegoError;
     use lego_config::read::{LegoConfig, DataManagementObjects};
-    use std::error::Error;
     use std::fmt::{Display, Formatter};
     use crate::str::cross_reader::cross::Cross;
+    use crate::str::str_file::StrFile;
     use crate::str::cross_reader::coordinate::CrossCoordinate3d;
     use std::fmt;
     use crate::str::str_traits::ICrossInformation;
 
     #[derive(Debug)]
     pub struct CrossInformation {
-        path: String,
+        pub(crate) path: String,
         mining_type: String,
-        seperator: String,
         pub(crate) duplicate_avoiding: bool,
     }
 
     impl CrossInformation {
-        pub(crate) fn new(path: String, mining_type: String, seperator: String) -> CrossInformation {
+        pub(crate) fn new(path: String, mining_type: String) -> CrossInformation {
             CrossInformation {
                 path,
                 mining_type,
-                seperator,
                 duplicate_avoiding: true,
             }
         }
+
+        pub fn get_mining_type(&self) -> &String {
+            &self.mining_type
+        }
     }
 
     impl ICrossInformation for CrossInformation {
-        fn read(&self) -> Result<Vec<Cross>, Box<dyn Error>> {
+        fn read(&self) -> Result<Vec<Cross>, LegoError> {
             // our data
             let mut cross_objects: Vec<Cross> = vec![];
+            let str_file = StrFile::read(&self.path)?;
 
-            let file = File::open(&self.path)?;
-            let reader = BufReader::new(file);
-            let mut lines = reader.lines();
-
-            // skip first header line
-            lines.next();
-            lines.next();
-
-            let mut group_no = 0;
-            let mut vertex_id = 0;
-            let mut temp_coords: Vec<CrossCoordinate3d> = vec![];
-
-            for line in lines {
-                let record = line.expect("Failed to reading line of str file");
-
-                if record.contains(&self.seperator) {
-                    // cleaning
-                    group_no += 1;
-                    vertex_id = 0;
+            // every segment is a cross, they are numbered in the order of the file
+            for (index, segment) in str_file.segments().enumerate() {
+                if segment.points.len() < 2 {
+                    continue;
+                }
 
-                    if temp_coords.len() > 1 {
-                        let cross = Cross::new(group_no, temp_coords.clone());
+                let coordinates: Vec<CrossCoordinate3d> = segment.points.iter().enumerate()
+                    .map(|(i, p)| CrossCoordinate3d::new(p.x, p.y, p.z, i as i32 + 1))
+                    .collect();
+                let cross = Cross::new(index as i32 + 1, coordinates);
 
-                        if cross_objects.contains(&cross) == false && self.duplicate_avoiding {
-                            // avoiding duplicates
-                            cross_objects.push(cross);
-                        }
-                        temp_coords.clear();
-                    }
-                } else {
-                    vertex_id += 1;
-                    let record: Vec<String> = record.split(",").map(|s| s.trim().to_string()).collect();
-
-                    // println!("record : {:?}", record);
-                    let x: f64 = record[1].parse().expect("x icin numerik deger çevirilemedi");
-                    let y: f64 = record[2].parse().expect("y icin numerik deger çevirilemedi");
-                    let z: f64 = record[3].parse().expect("z icin numerik deger çevirilemedi");
-                    temp_coords.push(CrossCoordinate3d::new(x, y, z, vertex_id));
+                // avoiding duplicates
+                if !(self.duplicate_avoiding && cross_objects.contains(&cross)) {
+                    cross_objects.push(cross);
                 }
             }
 
@@ -300,8 +287,7 @@ pub mod info {
     impl Display for CrossInformation {
         fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
             write!(f, "path: {} \n\
-                   mining type : {} \n\
-                   seperator : {}", self.path, self.mining_type, self.seperator)
+                   mining type : {}", self.path, self.mining_type)
         }
     }
 }
@@ -309,15 +295,15 @@ pub mod info {
 pub mod common {
     use std::fmt::{Display, Formatter, Result};
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
     pub struct Extent {
-        min_x: f64,
-        min_y: f64,
-        min_z: f64,
+        pub min_x: f64,
+        pub min_y: f64,
+        pub min_z: f64,
 
-        max_x: f64,
-        max_y: f64,
-        max_z: f64,
+        pub max_x: f64,
+        pub max_y: f64,
+        pub max_z: f64,
 
     }
 
@@ -376,20 +362,67 @@ pub mod cross {
         }
     }
 
+    impl Cross {
+        // splits the ring between its minimum and maximum vertex along the orient axis.
+        // both lines start from the minimum vertex and end at the maximum one, the line
+        // lying on the greater side of the other axis is the above (hanging wall) one.
+        fn split_by_orient(&self) -> (Vec<&CrossCoordinate3d>, Vec<&CrossCoordinate3d>) {
+            let mut ring: Vec<&CrossCoordinate3d> = self.coordinate.iter().collect();
+
+            // rings are closed by repeating the first vertex
+            while ring.len() > 1 && ring.first().map(|c| (c.x_coord, c.y_coord)) ==
+                ring.last().map(|c| (c.x_coord, c.y_coord)) {
+                ring.pop();
+            }
+
+            if ring.len() < 3 {
+                return (vec![], vec![]);
+            }
+
+            let axis = self.find_orient_2d();
+            let along = |c: &CrossCoordinate3d| if axis == Axis::X { c.x_coord } else { c.y_coord };
+            let across = |c: &CrossCoordinate3d| if axis == Axis::X { c.y_coord } else { c.x_coord };
+
+            let mut min_index = 0;
+            let mut max_index = 0;
+            for (i, c) in ring.iter().enumerate() {
+                if along(c) < along(ring[min_index]) {
+                    min_index = i;
+                }
+                if along(c) > along(ring[max_index]) {
+                    max_index = i;
+                }
+            }
+
+            let count = ring.len();
+            let walk = |from: usize, to: usize| {
+                let steps = (to + count - from) % count;
+                (0..=steps).map(|k| ring[(from + k) % count]).collect::<Vec<&CrossCoordinate3d>>()
+            };
+
+            let forward = walk(min_index, max_index);
+            let mut backward = walk(max_index, min_index);
+            backward.reverse();
+
+            let mean_across = |line: &Vec<&CrossCoordinate3d>| {
+                line.iter().map(|c| across(c)).sum::<f64>() / line.len() as f64
+            };
+
+            if mean_across(&forward) >= mean_across(&backward) {
+                (forward, backward)
+            } else {
+                (backward, forward)
+            }
+        }
+    }
+
     impl ICross for Cross {
         fn give_above_one(&self) -> Vec<&CrossCoordinate3d> {
-            // let min_vertex_id = one_cross
-            //     .find_vertex_id_via_value_of_axis(&one_cross.give_minimum_x_value(), &axis).unwrap();
-            // let max_vertex_id = one_cross
-            //     .find_vertex_id_via_value_of_axis(&one_cross.give_maximum_x_value(), &axis).unwrap();
-
-            // self.coordinate[max_vertex_id as usize..min_vertex_id as usize]
-            //     .to_vec()
-            vec![]
+            self.split_by_orient().0
         }
 
         fn give_below_one(&self) -> Vec<&CrossCoordinate3d> {
-            unimplemented!()
+            self.split_by_orient().1
         }
 
         fn give_minimum_z_value(&self) -> f64 {
@@ -596,15 +629,17 @@ mod tests {
     use plotly::{Scatter, Plot};
     use plotly::common::Mode;
     use crate::str::cross_reader::info::CrossInformation;
+    use crate::str::cross_reader::cross::Cross;
+    use crate::str::cross_reader::coordinate::CrossCoordinate3d;
 
-    const TEST_CONFIG_PATH: &str = "/home/umut/CLionProjects/LegoRust/lego_config/test_settings.toml";
+    const TEST_CONFIG_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../lego_config/test_settings.toml");
 
     #[test]
     fn read_cross_section_from_config() {
-        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH));
+        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
 
-        let cross_info = CrossInformation::new_from_config(&config_object);
-        let cross_object = CrossObject::new(cross_info, None);
+        let cross_info = CrossInformation::new_from_config(&config_object).unwrap();
+        let cross_object = CrossObject::new(cross_info, None).unwrap();
         println!("cross sections info : {:?}", &cross_object.info);
         for c in &cross_object.data {
             println!("section : {:?}", c);
@@ -615,10 +650,10 @@ mod tests {
 
     #[test]
     fn order_crosses_by_z() {
-        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH));
+        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
 
-        let cross_info = CrossInformation::new_from_config(&config_object);
-        let cross_object = CrossObject::new(cross_info, None);
+        let cross_info = CrossInformation::new_from_config(&config_object).unwrap();
+        let cross_object = CrossObject::new(cross_info, None).unwrap();
 
         for i in 1..cross_object.data.len() {
             let c1 = &cross_object.data[i];
@@ -631,6 +666,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn split_cross_above_below() {
+        let corners = [(0.0, 0.0), (2.0, -1.0), (4.0, 0.0), (2.0, 1.0), (0.0, 0.0)];
+        let coordinates = corners.iter().enumerate()
+            .map(|(i, &(x, y))| CrossCoordinate3d::new(x, y, 10.0, i as i32 + 1))
+            .collect();
+        let cross = Cross::new(1, coordinates);
+
+        let above: Vec<i32> = cross.give_above_one().iter().map(|c| c.vertex_id).collect();
+        let below: Vec<i32> = cross.give_below_one().iter().map(|c| c.vertex_id).collect();
+
+        assert_eq!(above, vec![1, 4, 3]);
+        assert_eq!(below, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn split_test_crosses() {
+        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
+        let cross_object = CrossObject::new(CrossInformation::new_from_config(&config_object).unwrap(), None).unwrap();
+
+        for c in cross_object.data.iter() {
+            let (above, below) = (c.give_above_one(), c.give_below_one());
+
+            // min and max vertices are shared, the closing vertex is dropped
+            assert_eq!(above.len() + below.len(), c.coordinate.len() + 1);
+            assert_eq!(above.first(), below.first());
+            assert_eq!(above.last(), below.last());
+        }
+    }
+
     #[test]
     fn draw_plotly_test() {
         let trace1 = Scatter::new(vec![1, 2, 3, 4], vec![10, 15, 13, 17])
@@ -650,10 +715,10 @@ mod tests {
 
     #[test]
     fn draw_cross () {
-        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH));
+        let config_object = LegoConfig::new(String::from(TEST_CONFIG_PATH)).unwrap();
 
-        let cross_info = CrossInformation::new_from_config(&config_object);
-        let cross_object = CrossObject::new(cross_info, None);
+        let cross_info = CrossInformation::new_from_config(&config_object).unwrap();
+        let cross_object = CrossObject::new(cross_info, None).unwrap();
 
         for (index, c) in cross_object.data.iter().enumerate() {
             cross_object.draw_points(index as i32, true);
@@ -0,0 +1,303 @@
+use std::fmt::{Display, Formatter};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use lego_config::error::LegoError;
+
+use crate::excels::excel_traits::record_field;
+
+// marker in the d-fields of the last record
+const END_MARKER: &str = "END";
+
+// Surpac string file. Points are grouped into segments by the records with string number 0,
+// segments are grouped into strings by their string number.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrFile {
+    pub header: StrHeader,
+    pub axis: StrAxis,
+    // in the order the string numbers are first seen in the file
+    pub strings: Vec<StrString>,
+}
+
+impl StrFile {
+    pub fn read(path: &str) -> Result<StrFile, LegoError> {
+        StrFile::read_with_seperator(path, ',')
+    }
+
+    pub fn read_with_seperator(path: &str, seperator: char) -> Result<StrFile, LegoError> {
+        let file = File::open(path).map_err(|e| LegoError::io(path, e))?;
+        StrFile::from_reader(BufReader::new(file), path, seperator)
+    }
+
+    // path is only used for the errors
+    pub fn from_reader<R: BufRead>(reader: R, path: &str, seperator: char) -> Result<StrFile, LegoError> {
+        let mut lines = reader.lines().enumerate();
+
+        let header = match lines.next() {
+            Some((_, line)) => StrHeader::parse(&line.map_err(|e| LegoError::io(path, e))?, seperator),
+            None => return Err(LegoError::parse(path, 1, "header", ""))
+        };
+
+        let axis = match lines.next() {
+            Some((index, line)) => {
+                let record = split_record(&line.map_err(|e| LegoError::io(path, e))?, seperator);
+                StrAxis::parse(&record, path, index + 1)?
+            }
+            None => return Err(LegoError::parse(path, 2, "axis", ""))
+        };
+
+        let mut str_file = StrFile {
+            header,
+            axis,
+            strings: vec![],
+        };
+        let mut segment: Option<(i32, StrSegment)> = None;
+
+        for (index, line) in lines {
+            let line_no = index + 1;
+            let record = line.map_err(|e| LegoError::io(path, e))?;
+            if record.trim().is_empty() {
+                continue;
+            }
+
+            let record = split_record(&record, seperator);
+            let string_no: i32 = record_field(&record, 0, "string number", path, line_no)?;
+
+            if string_no == 0 {
+                // segment seperator, the last one carries the end marker
+                str_file.close_segment(segment.take());
+                if record.iter().skip(4).any(|f| f.eq_ignore_ascii_case(END_MARKER)) {
+                    break;
+                }
+                continue;
+            }
+
+            // a new string number starts a new segment even without a seperator
+            if segment.as_ref().map(|(n, _)| *n != string_no).unwrap_or(false) {
+                str_file.close_segment(segment.take());
+            }
+
+            let point = StrPoint::parse(&record, path, line_no)?;
+            segment.get_or_insert_with(|| (string_no, StrSegment { points: vec![] })).1.points.push(point);
+        }
+
+        // files without an end marker are accepted
+        str_file.close_segment(segment);
+        Ok(str_file)
+    }
+
+    fn close_segment(&mut self, segment: Option<(i32, StrSegment)>) {
+        if let Some((number, segment)) = segment {
+            match self.strings.iter_mut().find(|s| s.number == number) {
+                Some(string) => string.segments.push(segment),
+                None => self.strings.push(StrString { number, segments: vec![segment] })
+            }
+        }
+    }
+
+    pub fn get_string(&self, number: i32) -> Option<&StrString> {
+        self.strings.iter().find(|s| s.number == number)
+    }
+
+    // segments of every string, string by string
+    pub fn segments(&self) -> impl Iterator<Item = &StrSegment> {
+        self.strings.iter().flat_map(|s| s.segments.iter())
+    }
+
+    pub fn point_count(&self) -> usize {
+        self.segments().map(|s| s.points.len()).sum()
+    }
+}
+
+// first line of the file : name, date, purpose and memo
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StrHeader {
+    pub name: String,
+    pub date: String,
+    pub purpose: String,
+    pub memo: String,
+}
+
+impl StrHeader {
+    fn parse(line: &str, seperator: char) -> StrHeader {
+        let mut fields = line.splitn(4, seperator).map(|s| s.trim().to_string());
+        StrHeader {
+            name: fields.next().unwrap_or_default(),
+            date: fields.next().unwrap_or_default(),
+            purpose: fields.next().unwrap_or_default(),
+            memo: fields.next().unwrap_or_default(),
+        }
+    }
+}
+
+// second line of the file, two points of the axis
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StrAxis {
+    pub start: (f64, f64, f64),
+    pub end: (f64, f64, f64),
+}
+
+impl StrAxis {
+    fn parse(record: &[String], path: &str, line: usize) -> Result<StrAxis, LegoError> {
+        let string_no: i32 = record_field(record, 0, "string number", path, line)?;
+        if string_no != 0 {
+            return Err(LegoError::parse(path, line, "axis", &record.join(",")));
+        }
+
+        // surpac string records are y, x, z
+        Ok(StrAxis {
+            start: (record_field(record, 2, "x", path, line)?, record_field(record, 1, "y", path, line)?,
+                    record_field(record, 3, "z", path, line)?),
+            end: (record_field(record, 5, "x", path, line)?, record_field(record, 4, "y", path, line)?,
+                  record_field(record, 6, "z", path, line)?),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrString {
+    pub number: i32,
+    pub segments: Vec<StrSegment>,
+}
+
+impl StrString {
+    pub fn points(&self) -> impl Iterator<Item = &StrPoint> {
+        self.segments.iter().flat_map(|s| s.points.iter())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrSegment {
+    pub points: Vec<StrPoint>,
+}
+
+impl StrSegment {
+    // surpac closes a segment by repeating its first point
+    pub fn is_closed(&self) -> bool {
+        self.points.len() > 2 && self.points.first().map(|p| (p.x, p.y, p.z)) ==
+            self.points.last().map(|p| (p.x, p.y, p.z))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrPoint {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    // description fields after the coordinates, empty trailing fields are dropped
+    pub d_fields: Vec<String>,
+    // line of the record in the file
+    pub line: usize,
+}
+
+impl StrPoint {
+    fn parse(record: &[String], path: &str, line: usize) -> Result<StrPoint, LegoError> {
+        // surpac string records are y, x, z
+        let y: f64 = record_field(record, 1, "y", path, line)?;
+        let x: f64 = record_field(record, 2, "x", path, line)?;
+        let z: f64 = record_field(record, 3, "z", path, line)?;
+
+        let mut d_fields: Vec<String> = record.iter().skip(4).cloned().collect();
+        while d_fields.last().map(|f| f.is_empty()).unwrap_or(false) {
+            d_fields.pop();
+        }
+
+        Ok(StrPoint {
+            x,
+            y,
+            z,
+            d_fields,
+            line,
+        })
+    }
+
+    // d1 is the first description field
+    pub fn get_d_field(&self, d: usize) -> Option<&str> {
+        d.checked_sub(1).and_then(|i| self.d_fields.get(i)).map(|f| f.as_str())
+    }
+}
+
+fn split_record(line: &str, seperator: char) -> Vec<String> {
+    line.split(seperator).map(|s| s.trim().to_string()).collect()
+}
+
+impl Display for StrFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "name : {} \n\
+                   date : {} \n\
+                   purpose : {} \n\
+                   strings : {} \n\
+                   segments : {} \n\
+                   points : {}", self.header.name, self.header.date, self.header.purpose, self.strings.len(),
+               self.segments().count(), self.point_count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lego_config::error::LegoError;
+
+    use crate::str::str_file::StrFile;
+
+    const COMPOSITE_STR_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../tests/data/halkalar/cu_composite1.str");
+    const CROSS_STR_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../tests/data/halkalar/cu_enkesit.str");
+
+    fn parse(text: &str) -> Result<StrFile, LegoError> {
+        StrFile::from_reader(text.as_bytes(), "test.str", ',')
+    }
+
+    #[test]
+    fn read_test_str_files() {
+        let composites = StrFile::read(COMPOSITE_STR_PATH).unwrap();
+        println!("{}", composites);
+        assert_eq!(composites.header.name, "cu_composite");
+        assert_eq!(composites.header.date, "11-Apr-18");
+        assert_eq!(composites.header.purpose, "Downhole composite of assays");
+        assert!(composites.segments().all(|s| s.points.iter().all(|p| p.d_fields.len() == 5)));
+        assert_eq!(composites.get_string(1).unwrap().points().next().unwrap().get_d_field(2), Some("SK-08"));
+
+        let crosses = StrFile::read(CROSS_STR_PATH).unwrap();
+        println!("{}", crosses);
+        assert_eq!(crosses.strings.len(), 1);
+        assert!(crosses.segments().all(|s| s.is_closed()));
+        assert!(crosses.segments().all(|s| s.points.iter().all(|p| p.d_fields.is_empty())));
+    }
+
+    #[test]
+    fn strings_segments_and_end() {
+        let str_file = parse("pit,11-Apr-18,outline,memo, with comma\n\
+                              0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0\n\
+                              1, 10.0, 20.0, 100.0, a, , b\n\
+                              1, 11.0, 21.0, 100.0\n\
+                              0, 0.000, 0.000, 0.000,\n\
+                              \n\
+                              1, 12.0, 22.0, 90.0\n\
+                              3, 13.0, 23.0, 90.0, , \n\
+                              0, 0.000, 0.000, 0.000, END\n\
+                              1, 99.0, 99.0, 99.0\n").unwrap();
+
+        assert_eq!(str_file.header.memo, "memo, with comma");
+        assert_eq!(str_file.axis.start, (2.0, 1.0, 3.0));
+        assert_eq!(str_file.axis.end, (5.0, 4.0, 6.0));
+        assert_eq!(str_file.strings.iter().map(|s| s.number).collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(str_file.get_string(1).unwrap().segments.len(), 2);
+        assert_eq!(str_file.point_count(), 4);
+
+        let first = &str_file.strings[0].segments[0].points[0];
+        assert_eq!((first.x, first.y, first.z, first.line), (20.0, 10.0, 100.0, 3));
+        assert_eq!(first.d_fields, vec!["a", "", "b"]);
+        assert_eq!(first.get_d_field(3), Some("b"));
+        assert!(str_file.get_string(3).unwrap().points().all(|p| p.d_fields.is_empty()));
+    }
+
+    #[test]
+    fn wrong_str_records() {
+        let error = parse("pit,11-Apr-18,,\n0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0\n1, 10.0, x, 100.0\n").unwrap_err();
+        assert_eq!(error.to_string(), "test.str:3: value 'x' of column x cannot be parsed");
+
+        let error = parse("pit,11-Apr-18,,\n1, 10.0, 20.0, 100.0\n").unwrap_err();
+        assert!(matches!(error, LegoError::Parse { line: 2, .. }));
+        assert!(parse("").is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use crate::str::cross_reader::info::CrossInformation;
+use lego_config::error::LegoError;
 use lego_config::read::{LegoConfig, DataManagementObjects};
-use std::error::Error;
 use geo::{Coordinate, LineString, Polygon};
 use geo::convexhull::ConvexHull;
 use crate::str::cross_reader::common::{Extent, Axis};
@@ -27,16 +27,15 @@ pub trait ICrossObject {
 
 
 pub trait ICrossInformation {
-    fn new_from_config(config: &LegoConfig) -> CrossInformation {
+    fn new_from_config(config: &LegoConfig) -> Result<CrossInformation, LegoError> {
         // getting mining information
-        let path = config.get_cross_section_str_path();
-        let mining_type = config.get_mining_type();
-        let seperator = config.get_cross_section_seperator();
+        let path = config.get_cross_section_str_path()?;
+        let mining_type = config.get_mining_type().to_string();
 
-        CrossInformation::new(path, mining_type, seperator)
+        Ok(CrossInformation::new(path, mining_type))
     }
 
-    fn read(&self) -> Result<Vec<Cross>, Box<dyn Error>>;
+    fn read(&self) -> Result<Vec<Cross>, LegoError>;
 }
 
 
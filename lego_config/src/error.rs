@@ -0,0 +1,99 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::str::FromStr;
+
+// errors of the config and the data readers
+#[derive(Debug)]
+pub enum LegoError {
+    Io { path: String, source: io::Error },
+    // line numbers start from 1, column is the header or the position of the value
+    Parse { path: String, line: usize, column: String, value: String },
+    MissingSection { section: String },
+    MissingKey { section: String, key: String },
+    UnknownSection { section: String },
+    UnknownKey { section: String, key: String },
+    // logical column whose header cannot be found in a data file
+    MissingColumn { path: String, column: String, header: String },
+    // config file which cannot be read or has values of a wrong type
+    Config { path: String, message: String },
+    Geometry(String),
+    // parameters of an algorithm which cannot be used
+    Parameter(String),
+}
+
+impl LegoError {
+    pub fn io(path: &str, source: io::Error) -> LegoError {
+        LegoError::Io { path: path.to_string(), source }
+    }
+
+    pub fn parse(path: &str, line: usize, column: &str, value: &str) -> LegoError {
+        LegoError::Parse {
+            path: path.to_string(),
+            line,
+            column: column.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    pub fn missing_key(section: &str, key: &str) -> LegoError {
+        LegoError::MissingKey { section: section.to_string(), key: key.to_string() }
+    }
+
+    pub fn geometry(message: impl Into<String>) -> LegoError {
+        LegoError::Geometry(message.into())
+    }
+
+    pub fn parameter(message: impl Into<String>) -> LegoError {
+        LegoError::Parameter(message.into())
+    }
+
+    // value of a field or a parse error pointing to it
+    pub fn parse_field<T: FromStr>(value: &str, path: &str, line: usize, column: &str) -> Result<T, LegoError> {
+        value.trim().parse().map_err(|_| LegoError::parse(path, line, column, value))
+    }
+}
+
+impl Display for LegoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LegoError::Io { path, source } => write!(f, "cannot read {} : {}", path, source),
+            LegoError::Parse { path, line, column, value } =>
+                write!(f, "{}:{}: value '{}' of column {} cannot be parsed", path, line, value, column),
+            LegoError::MissingSection { section } => write!(f, "section [{}] cannot be found in the config", section),
+            LegoError::MissingKey { section, key } =>
+                write!(f, "key {} cannot be found in section [{}] of the config", key, section),
+            LegoError::UnknownSection { section } => write!(f, "section [{}] of the config is not known", section),
+            LegoError::UnknownKey { section, key } => write!(f, "key {} of section [{}] is not known", key, section),
+            LegoError::MissingColumn { path, column, header } =>
+                write!(f, "{}: header {} of column {} cannot be found", path, header, column),
+            LegoError::Config { path, message } => write!(f, "config {} is not valid : {}", path, message),
+            LegoError::Geometry(message) => write!(f, "geometry error : {}", message),
+            LegoError::Parameter(message) => write!(f, "invalid parameter : {}", message),
+        }
+    }
+}
+
+impl Error for LegoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LegoError::Io { source, .. } => Some(source),
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LegoError;
+
+    #[test]
+    fn parse_errors() {
+        let value: f64 = LegoError::parse_field(" 12.5", "a.csv", 3, "X").unwrap();
+        assert_eq!(value, 12.5);
+
+        let error = LegoError::parse_field::<f64>("12,5", "a.csv", 3, "X").unwrap_err();
+        assert_eq!(error.to_string(), "a.csv:3: value '12,5' of column X cannot be parsed");
+    }
+}
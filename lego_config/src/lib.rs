@@ -1 +1,5 @@
 pub mod read;
+
+pub mod error;
+
+pub mod settings;
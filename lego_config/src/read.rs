@@ -1,175 +1,209 @@
 use config::{Config, File, Value};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::io;
 use std::path::Path;
 
+use crate::error::LegoError;
+use crate::settings::{schema_key, ExcelPaths, LegoSettings, MiningInformation, StrPaths};
+
 pub struct LegoConfig {
     path: String,
-    config_object: Config
+    config_object: Config,
+    settings: LegoSettings,
 }
 
+// environment variables like LEGO_EXCEL_PATHS__DRILL_CSV_PATH override the keys of the file
+pub const ENV_PREFIX: &str = "LEGO_";
+
 impl LegoConfig {
-    pub fn new(path: String) -> LegoConfig {
-        let _b = std::path::Path::new(&path).exists();
+    pub fn new(path: String) -> Result<LegoConfig, LegoError> {
+        LegoConfig::new_with_overrides(path, &[])
+    }
 
-        match Path::new(&path).exists() {
-            true => {
-                let mut config_object = Config::new();
-                config_object.merge(File::with_name(&path));
+    // overrides are section.key and value pairs, e.g. from the command line. They are applied
+    // after the environment variables.
+    pub fn new_with_overrides(path: String, overrides: &[(String, String)]) -> Result<LegoConfig, LegoError> {
+        if !Path::new(&path).exists() {
+            return Err(LegoError::io(&path, io::Error::new(io::ErrorKind::NotFound,
+                                                           "config file cannot be found")));
+        }
 
-                LegoConfig {
-                    path,
-                    config_object
-                }
+        let mut config_object = Config::new();
+        config_object.merge(File::with_name(&path))
+            .map_err(|e| LegoError::Config { path: path.clone(), message: e.to_string() })?;
 
-            } false => {
-                panic!("Config file cannot be found !")
-            }
+        let mut keys = env_overrides(env::vars())?;
+        for (key, value) in overrides.iter() {
+            keys.push((override_key(key)?, value.clone()));
         }
 
-    }
-
-    fn refresh_config(&mut self) {
-        &self.config_object.refresh();
-    }
-}
+        for ((section, key), value) in keys {
+            let (section, key) = file_key(&config_object, &section, &key);
+            config_object.set(&format!("{}.{}", section, key), value)
+                .map_err(|e| LegoError::Config { path: path.clone(), message: e.to_string() })?;
+        }
 
-pub trait DataManagementObjects {
-    fn get_section(&self, section_name: &str) -> HashMap<String, Value>;
+        let settings = LegoSettings::new(&config_object, &path)?;
 
-    fn get_mining_information(&self) -> HashMap<String, Value> {
-        let mining_information_section: HashMap<String, Value> = self.get_section("mining_information");
-        mining_information_section
+        Ok(LegoConfig {
+            path,
+            config_object,
+            settings,
+        })
     }
 
-    fn get_excel_paths(&self) -> HashMap<String, Value> {
-        let excel_path_section: HashMap<String, Value> = self.get_section("excel_paths");
-        excel_path_section
-    }
+    // arguments are the config path and section.key=value overrides
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<LegoConfig, LegoError> {
+        let mut args = args.into_iter();
+        let path = args.next().ok_or_else(|| LegoError::Config {
+            path: String::new(),
+            message: String::from("config path is not given"),
+        })?;
+
+        let mut overrides: Vec<(String, String)> = vec![];
+        for arg in args {
+            let (key, value) = arg.split_once('=').ok_or_else(|| LegoError::Config {
+                path: path.clone(),
+                message: format!("argument {} has to be section.key=value", arg),
+            })?;
+            overrides.push((key.trim().to_string(), value.trim().to_string()));
+        }
 
-    fn get_str_paths(&self) -> HashMap<String, Value> {
-        let str_path_section: HashMap<String, Value> = self.get_section("str_paths");
-        str_path_section
+        LegoConfig::new_with_overrides(path, &overrides)
     }
 
-    fn get_drill_column_section(&self) -> HashMap<String, Value> {
-        let column_section: HashMap<String, Value> = self.get_section("drill_columns");
-        column_section
+    // reads the file again, settings are checked again
+    pub fn refresh_config(&mut self) -> Result<(), LegoError> {
+        let path = self.path.clone();
+        self.config_object.refresh()
+            .map_err(|e| LegoError::Config { path, message: e.to_string() })?;
+        self.settings = LegoSettings::new(&self.config_object, &self.path)?;
+        Ok(())
     }
+}
 
-    fn get_drill_csv_path(&self) -> String {
-        let excel_section = self.get_excel_paths();
-        let drill_csv_path = excel_section.get("drill_csv_path")
-            .expect("drill csv path cannot be found !").kind.to_string();
-
-        drill_csv_path
-    }
+// value of a section and key
+type KeyOverride = ((String, String), String);
 
-    fn get_lythology_csv_path(&self) -> String {
-        let excel_section = self.get_excel_paths();
-        let lyth_csv_path = excel_section.get("lythology_csv_path")
-            .expect("lythology csv path cannot be found !").kind.to_string();
+// section.key, written in any case
+fn override_key(key: &str) -> Result<(String, String), LegoError> {
+    let (section, key) = key.split_once('.')
+        .ok_or_else(|| LegoError::UnknownSection { section: key.to_string() })?;
+    schema_key(section, key)
+}
 
-        lyth_csv_path
+// variables without a known section are not ours and left out
+fn env_overrides<I: Iterator<Item = (String, String)>>(vars: I) -> Result<Vec<KeyOverride>, LegoError> {
+    let mut keys: Vec<KeyOverride> = vec![];
+    for (name, value) in vars {
+        if let Some((section, key)) = name.strip_prefix(ENV_PREFIX).and_then(|n| n.split_once("__")) {
+            match schema_key(section, key) {
+                Ok(key) => keys.push((key, value)),
+                Err(LegoError::UnknownSection { .. }) => continue,
+                Err(e) => return Err(e)
+            }
+        }
     }
 
-    fn get_rawsample_csv_path(&self) -> String {
-        let excel_section = self.get_excel_paths();
-        let rawsample_csv_path = excel_section.get("rawsample_csv_path")
-            .expect("rawsample csv path cannot be found !").kind.to_string();
-
-        rawsample_csv_path
-    }
+    keys.sort();
+    Ok(keys)
+}
 
-    fn get_composite_str_path(&self) -> String {
-        let str_section = self.get_str_paths();
-        let composite_str_path = str_section.get("composite_str_path")
-            .expect("Composite path cannot be found !").kind.to_string();
+// section and key as they are written in the file, so an override replaces the value of the file
+// whatever case it is written in. keys which are not in the file are kept as they are.
+fn file_key(config: &Config, section: &str, key: &str) -> (String, String) {
+    let sections: HashMap<String, Value> = config.clone().try_into().unwrap_or_default();
+    let (section, table) = match sections.into_iter().find(|(s, _)| s.eq_ignore_ascii_case(section)) {
+        Some((section, value)) => (section, value.into_table().unwrap_or_default()),
+        None => return (section.to_string(), key.to_string())
+    };
+
+    let key = table.keys().find(|k| k.eq_ignore_ascii_case(key)).cloned().unwrap_or_else(|| key.to_string());
+    (section, key)
+}
 
-        composite_str_path
-    }
+// path of a dataset, an error if it is left out of the config
+fn dataset_path(section: &str, key: &str, path: &Option<String>) -> Result<String, LegoError> {
+    path.clone().ok_or_else(|| LegoError::missing_key(section, key))
+}
 
-    fn get_cross_section_str_path(&self) -> String {
-        let str_section = self.get_str_paths();
-        let cross_section_str_path = str_section.get("cross_section_str_path")
-            .expect("Cross section path cannot be found !").kind.to_string();
+pub trait DataManagementObjects {
+    fn get_path(&self) -> &str;
 
-        cross_section_str_path
+    fn get_settings(&self) -> &LegoSettings;
 
+    fn get_mining_information(&self) -> &MiningInformation {
+        &self.get_settings().mining_information
     }
-    fn get_slope_csv_path(&self) -> String {
-        let excel_section = self.get_excel_paths();
-        let slope_csv_path = excel_section.get("slope_csv_path")
-            .expect("slope csv path cannot be found !").kind.to_string();
 
-        slope_csv_path
+    fn get_excel_paths(&self) -> &ExcelPaths {
+        &self.get_settings().excel_paths
     }
 
-    fn get_x_columns(&self, section_name: &str) -> HashMap<String, String> {
-        let column_section = self.get_section(section_name);
-
-        // convert Value to String
-        let mut columns: HashMap<String, String> = HashMap::new();
-        for (k, v) in column_section.iter() {
-            columns.insert(k.clone(), v.kind.to_string());
-        }
-
-        columns
+    fn get_str_paths(&self) -> &StrPaths {
+        &self.get_settings().str_paths
     }
 
-    fn get_mining_type(&self) -> String {
-        let m_section = self.get_mining_information();
-        let mining_type = m_section.get("mining_type")
-            .expect("Mining type cannot be found !").kind.to_string();
+    fn get_drill_csv_path(&self) -> Result<String, LegoError> {
+        dataset_path("excel_paths", "drill_csv_path", &self.get_excel_paths().drill_csv_path)
+    }
 
-        mining_type
+    fn get_lythology_csv_path(&self) -> Result<String, LegoError> {
+        dataset_path("excel_paths", "lythology_csv_path", &self.get_excel_paths().lythology_csv_path)
     }
 
-    fn get_x_seperator(&self, data_name: &str) -> char {
-        // default is ;
-        let m_section = &self.get_mining_information();
-        let csv_seperator = m_section.get(data_name);
+    fn get_rawsample_csv_path(&self) -> Result<String, LegoError> {
+        dataset_path("excel_paths", "rawsample_csv_path", &self.get_excel_paths().rawsample_csv_path)
+    }
 
-        match csv_seperator {
-            Some(t) => {
-                let s = t.kind.to_string();
-                let k = s.chars().last().unwrap();
-                k
-            },  // length cannot be more than 1 => char !
-            None => ';'
-        }
+    fn get_composite_str_path(&self) -> Result<String, LegoError> {
+        dataset_path("str_paths", "composite_str_path", &self.get_str_paths().composite_str_path)
     }
 
-    fn get_cross_section_seperator(&self) -> String {
-        // default is ;
-        let m_section = &self.get_mining_information();
-        let cross_seperator = m_section.get("cross_section_str_seperator")
-            .expect("Cross section seperator cannot be found !")
-            .kind
-            .to_string();
+    fn get_cross_section_str_path(&self) -> Result<String, LegoError> {
+        dataset_path("str_paths", "cross_section_str_path", &self.get_str_paths().cross_section_str_path)
+    }
 
-        cross_seperator
+    fn get_slope_csv_path(&self) -> Result<String, LegoError> {
+        dataset_path("excel_paths", "slope_csv_path", &self.get_excel_paths().slope_csv_path)
     }
 
+    fn get_mining_type(&self) -> &str {
+        &self.get_mining_information().mining_type
+    }
 
+    // caps of the grades per domain, "default" is used for the domains without a cap
+    fn get_top_cuts(&self) -> &BTreeMap<String, f64> {
+        &self.get_settings().top_cuts
+    }
 }
 
 impl DataManagementObjects for LegoConfig {
-    fn get_section(&self, section_name: &str) -> HashMap<String, Value> {
-        let table = &self.config_object.get_table(section_name)
-            .expect(format!("section cannot be found ! {}", section_name).as_str());
-        table.clone()
+    fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    fn get_settings(&self) -> &LegoSettings {
+        &self.settings
     }
 }
 
 
+#[cfg(test)]
 mod tests {
-    use super::{LegoConfig, DataManagementObjects};
-    const TEST_SETTING: &str = r"/home/umut/CLionProjects/LegoRust/lego_config/test_settings.toml";
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+
+    use super::{env_overrides, LegoConfig, DataManagementObjects};
+    use crate::error::LegoError;
+    const TEST_SETTING: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test_settings.toml");
 
     #[test]
     fn create_config() {
         let p = String::from(TEST_SETTING);
-        let c = LegoConfig::new(p);
+        let c = LegoConfig::new(p).unwrap();
 
         // excel section
         let excel_section = c.get_excel_paths();
@@ -182,10 +216,90 @@ mod tests {
         // mining information
         let mining_section = c.get_mining_information();
         println!("mining section : {:?}", mining_section);
-        let scp = excel_section.get("slope_csv_path").unwrap();
+        assert_eq!(c.get_mining_type(), "cu");
+        assert_eq!(mining_section.composite_str_seperator, ',');
 
         // slope csv path
-        println!("scp : {}", scp.kind);
+        let scp = c.get_slope_csv_path().unwrap();
+        println!("scp : {}", scp);
+        assert_eq!(c.get_settings().drill_columns.depth, "DERINLIK");
+        assert_eq!(c.get_top_cuts().get("kalkopirit"), Some(&5.5));
+    }
+
+    #[test]
+    fn missing_config_parts() {
+        assert!(matches!(LegoConfig::new(String::from("/not/here.toml")), Err(LegoError::Io { .. })));
+    }
+
+    #[test]
+    fn relative_paths_and_overrides() {
+        // paths of the test settings are relative to its directory
+        let c = LegoConfig::new(String::from(TEST_SETTING)).unwrap();
+        assert!(Path::new(&c.get_drill_csv_path().unwrap()).exists());
+
+        let vars = vec![(String::from("LEGO_EXCEL_PATHS__DRILL_CSV_PATH"), String::from("/data/collars.csv")),
+                        (String::from("LEGO_DRILL_COLUMNS__drillno"), String::from("HOLE")),
+                        (String::from("LEGO_HOME"), String::from("/opt/lego")),
+                        (String::from("PATH"), String::from("/usr/bin"))];
+        let keys = env_overrides(vars.into_iter()).unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].0, (String::from("drill_columns"), String::from("DRILLNO")));
+        assert_eq!(keys[1].0, (String::from("excel_paths"), String::from("drill_csv_path")));
+
+        let vars = vec![(String::from("LEGO_EXCEL_PATHS__DRIL_CSV_PATH"), String::from("a.csv"))];
+        assert!(matches!(env_overrides(vars.into_iter()), Err(LegoError::UnknownKey { .. })));
+
+        let args = vec![String::from(TEST_SETTING), String::from("mining_information.mining_type=au"),
+                        String::from("STR_PATHS.composite_str_path = composites.str"),
+                        String::from("top_cuts.kalkopirit=4")];
+        let c = LegoConfig::from_args(args).unwrap();
+        assert_eq!(c.get_mining_type(), "au");
+        assert_eq!(c.get_top_cuts().get("kalkopirit"), Some(&4.0));
+        assert_eq!(Path::new(&c.get_composite_str_path().unwrap()),
+                   Path::new(TEST_SETTING).parent().unwrap().join("composites.str"));
+
+        assert!(LegoConfig::from_args(vec![String::from(TEST_SETTING), String::from("mining_type")]).is_err());
+    }
+    #[test]
+    fn overrides_in_any_case() {
+        // the file writes the keys in other cases than the structs and the overrides
+        let path = env::temp_dir().join(format!("lego_config_cases_{}.toml", std::process::id()));
+        fs::write(&path, "[mining_information]\nmining_type = \"cu\"\n\
+                          [lythology_columns]\nLytho = \"LITOLOJI\"\n\
+                          [top_cuts]\nKalkopirit = 5.5\nbazalt = 2.0\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let c = LegoConfig::new(path.clone()).unwrap();
+        assert_eq!(c.get_settings().lythology_columns.lytho, "LITOLOJI");
+        assert_eq!(c.get_top_cuts().get("kalkopirit"), Some(&5.5));
+
+        let overrides = [(String::from("lythology_columns.LYTHO"), String::from("LITO")),
+                         (String::from("top_cuts.KALKOPIRIT"), String::from("4"))];
+        let c = LegoConfig::new_with_overrides(path.clone(), &overrides).unwrap();
+        assert_eq!(c.get_settings().lythology_columns.lytho, "LITO");
+        assert_eq!(c.get_top_cuts().len(), 2);
+        assert_eq!(c.get_top_cuts().get("kalkopirit"), Some(&4.0));
+
+        // the test settings write the lythology column as lytho
+        let overrides = [(String::from("lythology_columns.LYTHO"), String::from("LITO"))];
+        let c = LegoConfig::new_with_overrides(String::from(TEST_SETTING), &overrides).unwrap();
+        assert_eq!(c.get_settings().lythology_columns.lytho, "LITO");
+
+        fs::remove_file(&path).unwrap();
+    }
 
+    #[test]
+    fn unknown_sections_of_the_environment() {
+        let vars = vec![(String::from("LEGO_BUILD__TARGET"), String::from("release"))];
+        assert!(env_overrides(vars.into_iter()).unwrap().is_empty());
+
+        // variables of other tools can share the prefix
+        env::set_var("LEGO_CONFIG_TEST__CACHE_DIR", "/tmp/lego");
+        let c = LegoConfig::new(String::from(TEST_SETTING));
+        env::remove_var("LEGO_CONFIG_TEST__CACHE_DIR");
+        assert!(c.is_ok());
+
+        let args = vec![String::from(TEST_SETTING), String::from("build.target=release")];
+        assert!(matches!(LegoConfig::from_args(args), Err(LegoError::UnknownSection { .. })));
     }
-}
\ No newline at end of file
+}
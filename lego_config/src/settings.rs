@@ -0,0 +1,399 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use config::{Config, Value};
+use serde::de::{self, value, Deserializer, Visitor};
+use serde::{forward_to_deserialize_any, Deserialize};
+
+use crate::error::LegoError;
+
+// required keys, the others have defaults
+const REQUIRED: &[(&str, &str)] = &[("mining_information", "mining_type")];
+
+// Project configuration. Datasets whose paths are left out are not read, columns which are
+// left out are looked up by their logical name.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LegoSettings {
+    #[serde(default)]
+    pub excel_paths: ExcelPaths,
+    #[serde(default)]
+    pub str_paths: StrPaths,
+    pub mining_information: MiningInformation,
+    #[serde(default)]
+    pub drill_columns: DrillHeaders,
+    #[serde(default)]
+    pub slope_columns: SlopeHeaders,
+    #[serde(default)]
+    pub lythology_columns: LythologyHeaders,
+    #[serde(default)]
+    pub rawsample_columns: RawSampleHeaders,
+    // grade caps per lythology, "default" is used for the domains without a cap
+    #[serde(default)]
+    pub top_cuts: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExcelPaths {
+    pub drill_csv_path: Option<String>,
+    pub slope_csv_path: Option<String>,
+    pub lythology_csv_path: Option<String>,
+    pub rawsample_csv_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StrPaths {
+    pub composite_str_path: Option<String>,
+    pub cross_section_str_path: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MiningInformation {
+    pub mining_type: String,
+    #[serde(default = "csv_seperator")]
+    pub drill_csv_seperator: char,
+    #[serde(default = "csv_seperator")]
+    pub slope_csv_seperator: char,
+    #[serde(default = "csv_seperator")]
+    pub lythology_csv_seperator: char,
+    #[serde(default = "csv_seperator")]
+    pub rawsample_csv_seperator: char,
+    #[serde(default = "str_seperator")]
+    pub composite_str_seperator: char,
+    // deprecated, segments are ended by the records with string number 0 in every str file.
+    // kept so the old configs can still be read, other values than the default are rejected
+    #[serde(default)]
+    cross_section_str_seperator: Option<String>,
+}
+
+fn csv_seperator() -> char {
+    ';'
+}
+
+fn str_seperator() -> char {
+    ','
+}
+
+const CROSS_SECTION_STR_SEPERATOR: &str = "0, 0.000, 0.000, 0.000,";
+
+// headers of the logical columns in the data files
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DrillHeaders {
+    #[serde(rename = "DRILLNO")]
+    pub drill_no: String,
+    #[serde(rename = "X")]
+    pub x: String,
+    #[serde(rename = "Y")]
+    pub y: String,
+    #[serde(rename = "Z")]
+    pub z: String,
+    #[serde(rename = "DEPTH")]
+    pub depth: String,
+}
+
+impl Default for DrillHeaders {
+    fn default() -> DrillHeaders {
+        DrillHeaders {
+            drill_no: String::from("DRILLNO"),
+            x: String::from("X"),
+            y: String::from("Y"),
+            z: String::from("Z"),
+            depth: String::from("DEPTH"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SlopeHeaders {
+    #[serde(rename = "DRILLNO")]
+    pub drill_no: String,
+    #[serde(rename = "DEPTH")]
+    pub depth: String,
+    #[serde(rename = "DIP")]
+    pub dip: String,
+    #[serde(rename = "AZIMUTH")]
+    pub azimuth: String,
+}
+
+impl Default for SlopeHeaders {
+    fn default() -> SlopeHeaders {
+        SlopeHeaders {
+            drill_no: String::from("DRILLNO"),
+            depth: String::from("DEPTH"),
+            dip: String::from("DIP"),
+            azimuth: String::from("AZIMUTH"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LythologyHeaders {
+    #[serde(rename = "DRILLNO")]
+    pub drill_no: String,
+    #[serde(rename = "FROM")]
+    pub from: String,
+    #[serde(rename = "TO")]
+    pub to: String,
+    #[serde(rename = "LYTHO")]
+    pub lytho: String,
+}
+
+impl Default for LythologyHeaders {
+    fn default() -> LythologyHeaders {
+        LythologyHeaders {
+            drill_no: String::from("DRILLNO"),
+            from: String::from("FROM"),
+            to: String::from("TO"),
+            lytho: String::from("LYTHO"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RawSampleHeaders {
+    #[serde(rename = "DRILLNO")]
+    pub drill_no: String,
+    #[serde(rename = "FROM")]
+    pub from: String,
+    #[serde(rename = "TO")]
+    pub to: String,
+    #[serde(rename = "PERCENT")]
+    pub percent: String,
+}
+
+impl Default for RawSampleHeaders {
+    fn default() -> RawSampleHeaders {
+        RawSampleHeaders {
+            drill_no: String::from("DRILLNO"),
+            from: String::from("FROM"),
+            to: String::from("TO"),
+            percent: String::from("PERCENT"),
+        }
+    }
+}
+
+// Field names of a settings struct as its Deserialize impl asks for them, so the known keys
+// cannot drift from the structs.
+fn struct_fields<T: for<'de> Deserialize<'de>>() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de, 'a> Deserializer<'de> for FieldNames<'a> {
+    type Error = value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, value::Error> {
+        Err(de::Error::custom("settings sections are structs"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str],
+                                           _visitor: V) -> Result<V::Value, value::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("only the fields are read"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option unit
+        unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+    }
+}
+
+// sections of the settings
+fn section_names() -> &'static [&'static str] {
+    struct_fields::<LegoSettings>()
+}
+
+// keys of a section, None for the sections with free keys
+fn section_keys(section: &str) -> Option<&'static [&'static str]> {
+    match section {
+        "excel_paths" => Some(struct_fields::<ExcelPaths>()),
+        "str_paths" => Some(struct_fields::<StrPaths>()),
+        "mining_information" => Some(struct_fields::<MiningInformation>()),
+        "drill_columns" => Some(struct_fields::<DrillHeaders>()),
+        "slope_columns" => Some(struct_fields::<SlopeHeaders>()),
+        "lythology_columns" => Some(struct_fields::<LythologyHeaders>()),
+        "rawsample_columns" => Some(struct_fields::<RawSampleHeaders>()),
+        _ => None
+    }
+}
+
+// section as it is written in the structs
+fn schema_section(section: &str) -> Result<&'static str, LegoError> {
+    section_names().iter().find(|name| name.eq_ignore_ascii_case(section))
+        .copied()
+        .ok_or_else(|| LegoError::UnknownSection { section: section.to_string() })
+}
+
+// section and key as they are written in the structs, keys of the free sections are lowercased
+pub(crate) fn schema_key(section: &str, key: &str) -> Result<(String, String), LegoError> {
+    let name = schema_section(section)?;
+
+    let key = match section_keys(name) {
+        Some(keys) => keys.iter().find(|k| k.eq_ignore_ascii_case(key))
+            .map(|k| k.to_string())
+            .ok_or_else(|| LegoError::UnknownKey { section: name.to_string(), key: key.to_string() })?,
+        None => key.to_lowercase()
+    };
+
+    Ok((name.to_string(), key))
+}
+
+// Sections and keys of the config in the spelling of the structs, they can be written in any case.
+// Unknown sections and keys are reported before the values.
+fn canonical_sections(config: &Config, path: &str) -> Result<HashMap<String, HashMap<String, Value>>, LegoError> {
+    let sections: HashMap<String, Value> = config.clone().try_into()
+        .map_err(|e| LegoError::Config { path: path.to_string(), message: e.to_string() })?;
+
+    let mut canonical: HashMap<String, HashMap<String, Value>> = HashMap::new();
+    for (name, value) in sections {
+        let section = schema_section(&name)?;
+        let table = value.into_table().map_err(|_| LegoError::Config {
+            path: path.to_string(),
+            message: format!("[{}] has to be a section", name),
+        })?;
+
+        let keys = canonical.entry(section.to_string()).or_default();
+        for (key, value) in table {
+            let (_, key) = schema_key(section, &key)?;
+            if keys.insert(key.clone(), value).is_some() {
+                return Err(LegoError::Config {
+                    path: path.to_string(),
+                    message: format!("key {} of section [{}] is given more than once", key, section),
+                });
+            }
+        }
+    }
+
+    Ok(canonical)
+}
+
+// relative paths are taken from the directory of the config file
+fn resolve(path: &mut Option<String>, base: &Path) {
+    if let Some(p) = path {
+        if Path::new(p).is_relative() {
+            *p = base.join(&p).to_string_lossy().to_string();
+        }
+    }
+}
+
+impl LegoSettings {
+    // unknown and missing keys are checked before the values so they can be reported with their section
+    pub fn new(config: &Config, path: &str) -> Result<LegoSettings, LegoError> {
+        let sections = canonical_sections(config, path)?;
+
+        for (section, key) in REQUIRED.iter() {
+            let table = sections.get(*section)
+                .ok_or_else(|| LegoError::MissingSection { section: section.to_string() })?;
+            if !table.contains_key(*key) {
+                return Err(LegoError::missing_key(section, key));
+            }
+        }
+
+        // wrong types are reported with their keys by the config
+        let mut settings: LegoSettings = Value::new(None, sections).try_into()
+            .map_err(|e| LegoError::Config { path: path.to_string(), message: e.to_string() })?;
+
+        if let Some(seperator) = &settings.mining_information.cross_section_str_seperator {
+            if seperator.trim() != CROSS_SECTION_STR_SEPERATOR.trim() {
+                return Err(LegoError::Config {
+                    path: path.to_string(),
+                    message: format!("cross_section_str_seperator is deprecated and has to be left out, got '{}'",
+                                     seperator),
+                });
+            }
+        }
+
+        let base = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+        settings.resolve_paths(base);
+        Ok(settings)
+    }
+
+    pub fn resolve_paths(&mut self, base: &Path) {
+        resolve(&mut self.excel_paths.drill_csv_path, base);
+        resolve(&mut self.excel_paths.slope_csv_path, base);
+        resolve(&mut self.excel_paths.lythology_csv_path, base);
+        resolve(&mut self.excel_paths.rawsample_csv_path, base);
+        resolve(&mut self.str_paths.composite_str_path, base);
+        resolve(&mut self.str_paths.cross_section_str_path, base);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::{Config, File, FileFormat};
+
+    use crate::error::LegoError;
+    use crate::settings::{schema_key, section_keys, section_names, LegoSettings};
+
+    fn settings(toml: &str) -> Result<LegoSettings, LegoError> {
+        let mut config = Config::new();
+        config.merge(File::from_str(toml, FileFormat::Toml)).unwrap();
+        LegoSettings::new(&config, "test.toml")
+    }
+
+    #[test]
+    fn typed_sections() {
+        let s = settings("[mining_information]\nmining_type = \"cu\"\ncomposite_str_seperator = ';'\n\
+                          [lythology_columns]\nDRILLNO = \"SONDAJNO\"\nlytho = \"LITOLOJI\"\n\
+                          [top_cuts]\nkalkopirit = 5.5").unwrap();
+
+        assert_eq!(s.mining_information.mining_type, "cu");
+        assert_eq!(s.mining_information.drill_csv_seperator, ';');
+        assert_eq!(s.mining_information.composite_str_seperator, ';');
+        assert_eq!(s.excel_paths.drill_csv_path, None);
+        assert_eq!(s.lythology_columns.drill_no, "SONDAJNO");
+        assert_eq!(s.lythology_columns.lytho, "LITOLOJI");
+        assert_eq!(s.lythology_columns.from, "FROM");
+        assert_eq!(s.drill_columns.depth, "DEPTH");
+        assert_eq!(s.top_cuts.get("kalkopirit"), Some(&5.5));
+    }
+
+    #[test]
+    fn wrong_settings() {
+        let error = settings("[mining_information]\nmining_type = \"cu\"\n[excel_paths]\ndril_csv_path = \"a.csv\"")
+            .unwrap_err();
+        assert_eq!(error.to_string(), "key dril_csv_path of section [excel_paths] is not known");
+
+        let error = settings("[mining_information]\nmining_type = \"cu\"\n[drill]\nX = \"X\"").unwrap_err();
+        assert!(matches!(error, LegoError::UnknownSection { .. }));
+
+        let error = settings("[mining_information]\ndrill_csv_seperator = ';'").unwrap_err();
+        assert!(matches!(error, LegoError::MissingKey { .. }));
+
+        let error = settings("[mining_information]\nmining_type = \"cu\"\n[top_cuts]\nkalkopirit = \"high\"")
+            .unwrap_err();
+        assert!(error.to_string().contains("top_cuts.kalkopirit"), "{}", error);
+
+        let error = settings("[mining_information]\nmining_type = \"cu\"\ncross_section_str_seperator = \"0,0,0,0\"")
+            .unwrap_err();
+        assert!(matches!(error, LegoError::Config { .. }));
+        assert!(settings("[mining_information]\nmining_type = \"cu\"\n\
+                          cross_section_str_seperator = \"0, 0.000, 0.000, 0.000,\"").is_ok());
+
+        let error = settings("[mining_information]\nmining_type = \"cu\"\n\
+                              [lythology_columns]\nLYTHO = \"LITO\"\nlytho = \"LITOLOJI\"").unwrap_err();
+        assert_eq!(error.to_string(), "config test.toml is not valid : key LYTHO of section [lythology_columns] \
+                                       is given more than once");
+    }
+    #[test]
+    fn keys_of_the_structs() {
+        assert!(section_names().contains(&"rawsample_columns"));
+        assert_eq!(section_keys("drill_columns"), Some(&["DRILLNO", "X", "Y", "Z", "DEPTH"][..]));
+        assert!(section_keys("mining_information").unwrap().contains(&"cross_section_str_seperator"));
+        assert_eq!(section_keys("top_cuts"), None);
+
+        assert_eq!(schema_key("Slope_Columns", "azimuth").unwrap(),
+                   (String::from("slope_columns"), String::from("AZIMUTH")));
+        assert!(matches!(schema_key("slope", "DIP"), Err(LegoError::UnknownSection { .. })));
+    }
+}